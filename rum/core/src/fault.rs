@@ -0,0 +1,47 @@
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Fault
+///
+///The `enum` describes every way a UM program can fail during runtime. Instead
+///of panicking, the `Rum` machine returns a `Fault` so the caller (the binary,
+///the debugger, ...) can decide how to report it.
+pub enum Fault {
+    ///The program counter points outside of segment 0.
+    PcOutOfBounds { pc: usize },
+    ///The instruction word has an opcode of 14 or 15 and no host call is
+    ///registered for it.
+    InvalidOpcode { pc: usize, word: u32 },
+    ///A segment identifier that is not mapped was used.
+    UnmappedSegment { id: u32 },
+    ///Segment 0 holds the running program and cannot be unmapped. Only
+    ///`Mode::Strict` checks it: in `Mode::Fast` the program is unmapped and
+    ///the next instruction faults with `PcOutOfBounds`.
+    UnmapProgram,
+    ///An offset past the end of a mapped segment was used.
+    OutOfBounds { id: u32, offset: u32 },
+    ///The divisor register of a `Div` instruction held 0.
+    DivideByZero,
+    ///The `Output` instruction was given a value outside of `[0-255]`.
+    OutputRange { value: u32 },
+    ///Mapping a segment of `size` words would take the machine past its
+    ///memory limit.
+    MemoryLimit { size: u32 },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::PcOutOfBounds { pc } => write!(f, "program counter {} is outside of segment 0", pc),
+            Fault::InvalidOpcode { pc, word } => write!(f, "unknown opcode in word {:#010x} at pc {}", word, pc),
+            Fault::UnmappedSegment { id } => write!(f, "segment {} is not mapped", id),
+            Fault::UnmapProgram => write!(f, "segment 0 cannot be unmapped"),
+            Fault::OutOfBounds { id, offset } => write!(f, "offset {} is outside of segment {}", offset, id),
+            Fault::DivideByZero => write!(f, "division by zero"),
+            Fault::OutputRange { value } => write!(f, "the value {} is outside of [0-255]", value),
+            Fault::MemoryLimit { size } => write!(f, "mapping {} more words would exceed the memory limit", size),
+        }
+    }
+}
+
+impl core::error::Error for Fault {}
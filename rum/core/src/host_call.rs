@@ -0,0 +1,96 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt;
+
+use crate::{fault::Fault, segment::Segment, um_io::UmIo};
+
+//The opcodes the UM specification leaves undefined, which host calls extend
+const FIRST: u32 = 14;
+const LAST: u32 = 15;
+
+///Structure: HostCall
+///
+///What a host call handler works with when the program executes opcode 14
+///or 15: the instruction `word` at `pc`, its `a`, `b` and `c` register
+///fields decoded like those of the other three-register instructions, and
+///mutable access to the registers and I/O of the machine. Memory is only
+///reached through `load` and `store`, which fault like the `SegmentLoad` and
+///`SegmentStore` instructions instead of handing out the segments.
+pub struct HostCall<'a> {
+    pub pc: usize,
+    pub word: u32,
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub registers: &'a mut [u32; 8],
+    pub io: &'a mut dyn UmIo,
+    pub(crate) segments: &'a mut Segment,
+}
+
+impl HostCall<'_> {
+    ///Function: `load(&self, id: u32, offset: u32) -> Result<u32, Fault>`
+    ///
+    ///This function returns the word at `offset` in segment `id`, faulting
+    ///when the segment is not mapped or is too short.
+    pub fn load(&self, id: u32, offset: u32) -> Result<u32, Fault> {
+        self.segment_len(id)?;
+
+        self.segments.get_segment_value(id as usize, offset as usize).ok_or(Fault::OutOfBounds { id, offset })
+    }
+
+    ///Function: `store(&mut self, id: u32, offset: u32, value: u32) -> Result<(), Fault>`
+    ///
+    ///This function writes `value` at `offset` in segment `id`, faulting
+    ///when the segment is not mapped or is too short.
+    pub fn store(&mut self, id: u32, offset: u32, value: u32) -> Result<(), Fault> {
+        self.segments.set_segment_value(id as usize, offset as usize, value)
+    }
+
+    ///Function: `segment_len(&self, id: u32) -> Result<usize, Fault>`
+    ///
+    ///This function returns the number of words in segment `id`, faulting
+    ///when the segment is not mapped.
+    pub fn segment_len(&self, id: u32) -> Result<usize, Fault> {
+        self.segments.segment_len(id as usize).ok_or(Fault::UnmappedSegment { id })
+    }
+}
+
+///A handler registered for opcode 14 or 15. Returning a `Fault` stops the
+///machine like any other faulting instruction.
+pub type HostHandler = Rc<RefCell<dyn FnMut(&mut HostCall<'_>) -> Result<(), Fault>>>;
+
+#[derive(Clone, Default)]
+///Structure: HostCalls
+///
+///The handlers registered for opcodes 14 and 15. Without one, the opcode
+///faults as the UM specification requires. Copies of a machine share the
+///handlers, along with any state they captured.
+pub struct HostCalls {
+    handlers: [Option<HostHandler>; 2],
+}
+
+impl HostCalls {
+    ///Function: `set(&mut self, opcode: u32, handler: Option<HostHandler>)`
+    ///
+    ///This function registers `handler` for `opcode`, or removes the handler
+    ///of `opcode` when `handler` is `None`. It panics when `opcode` is not 14
+    ///or 15, since every other opcode already has a meaning.
+    pub fn set(&mut self, opcode: u32, handler: Option<HostHandler>) {
+        assert!((FIRST..=LAST).contains(&opcode), "host calls only extend opcodes 14 and 15, not {}", opcode);
+
+        self.handlers[(opcode - FIRST) as usize] = handler;
+    }
+
+    ///Function: `get(&self, opcode: u32) -> Option<&HostHandler>`
+    ///
+    ///This function returns the handler registered for `opcode`, if any.
+    pub fn get(&self, opcode: u32) -> Option<&HostHandler> {
+        opcode.checked_sub(FIRST).and_then(|index| self.handlers.get(index as usize)).and_then(Option::as_ref)
+    }
+}
+
+impl fmt::Debug for HostCalls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries((FIRST..=LAST).filter(|opcode| self.get(*opcode).is_some())).finish()
+    }
+}
@@ -0,0 +1,18 @@
+//! The core of the Universal Machine: registers, segments, instruction
+//! decoding and the semantics of every opcode. The crate is `no_std` and only
+//! needs `alloc`, so the machine can run on embedded boards or in a kernel;
+//! the `Input` and `Output` opcodes go through the `UmIo` trait, which the
+//! host implements. Loading programs from files, stdin/stdout and the process
+//! exit status are left to the `rum` crate.
+#![no_std]
+
+extern crate alloc;
+
+pub mod rum;
+pub mod segment;
+pub mod register;
+pub mod um_instruction;
+pub mod um_io;
+pub mod fault;
+pub mod monitor;
+pub mod host_call;
@@ -0,0 +1,50 @@
+use crate::rum::Rum;
+use crate::um_instruction::Instruction;
+
+///Trait: Monitor
+///
+///The trait lets a tool observe every instruction executed by
+///`Rum::run_monitored`. `before_step` is called right before the instruction
+///at `pc` executes and `after_step` once it executed without faulting
+///(`Halt` only gets `before_step`). Both methods default to doing nothing.
+pub trait Monitor {
+    fn before_step(&mut self, _machine: &Rum, _pc: usize, _instruction: Instruction) {}
+
+    fn after_step(&mut self, _machine: &Rum, _pc: usize, _instruction: Instruction) {}
+}
+
+impl<M: Monitor + ?Sized> Monitor for &mut M {
+    fn before_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        (**self).before_step(machine, pc, instruction);
+    }
+
+    fn after_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        (**self).after_step(machine, pc, instruction);
+    }
+}
+
+impl<A: Monitor, B: Monitor> Monitor for (A, B) {
+    fn before_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        self.0.before_step(machine, pc, instruction);
+        self.1.before_step(machine, pc, instruction);
+    }
+
+    fn after_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        self.0.after_step(machine, pc, instruction);
+        self.1.after_step(machine, pc, instruction);
+    }
+}
+
+impl<M: Monitor> Monitor for Option<M> {
+    fn before_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        if let Some(monitor) = self {
+            monitor.before_step(machine, pc, instruction);
+        }
+    }
+
+    fn after_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        if let Some(monitor) = self {
+            monitor.after_step(machine, pc, instruction);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+#[derive(Clone, Debug)]
+
+///Structure: Register
+///
+///This structure is an array of the 8 u32 values which
+///is intended to hold the register addresses during 
+//runtime
+pub struct Register {
+    vec_registers: [u32; 8],
+
+}
+//Register Implementation
+impl Register {
+
+    ///Function: new() -> Register
+    ///
+    ///This function is intended to initialize a register during runtime which has
+    ///only 8 0_u32 values representing a blank set of registers
+    pub fn new() -> Register {
+        Register {
+            vec_registers: [0; 8],
+        }
+    }
+
+    ///Function: get_register_value(&self, register: usize) -> u32
+    ///
+    ///The getter function is intended to return the value
+    ///at some `register` addresses in the `Register` structure
+    ///vector. Then the getter function will return the value
+    ///at the register's address as a u32 value. It panics when
+    ///`register` is not one of the 8 registers.
+    pub fn get_register_value(&self, register: usize) -> u32 {
+        self.vec_registers[register]
+    }
+
+    ///Function: set_register_value(&mut self, register: usize) -> u32
+    ///
+    ///The setter function is intneded to recieve a register address
+    ///and value which will be set within the `Register` vector at the
+    ///address `register` with the passed in `value`. It panics when
+    ///`register` is not one of the 8 registers.
+    pub fn set_register_value(&mut self, register: usize, value: u32) {
+        self.vec_registers[register] = value;
+    }
+
+    ///Function: `values(&self) -> &[u32]`
+    ///
+    ///This getter function is intended to return all 8 register
+    ///values so the machine state can be inspected or compared.
+    pub fn values(&self) -> &[u32] {
+        &self.vec_registers
+    }
+
+    ///Function: `values_mut(&mut self) -> &mut [u32; 8]`
+    ///
+    ///This function returns the 8 registers for the interpreter loop and host
+    ///calls, which index them with 3-bit register numbers.
+    pub fn values_mut(&mut self) -> &mut [u32; 8] {
+        &mut self.vec_registers
+    }
+}
+
+impl Default for Register {
+    fn default() -> Register {
+        Register::new()
+    }
+}
\ No newline at end of file
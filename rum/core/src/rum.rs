@@ -0,0 +1,698 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::task::Poll;
+
+use crate::{fault::Fault, host_call::{HostCall, HostCalls}, monitor::Monitor, register::Register, segment::Segment, um_instruction::{Instruction, Opcode}, um_io::UmIo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum State
+///
+///The `enum` is what `step` reports after executing a single instruction.
+///`NeedsInput` means the instruction is an `Input` the `UmIo` had no data
+///for yet: it was not executed, and `pc` still points at it until the host
+///calls `provide_input` or the `UmIo` has data.
+pub enum State {
+    Running,
+    Halted,
+    NeedsInput
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///Enum Mode
+///
+///`Fast` only checks what is needed to keep the emulator itself sound, which
+///includes faulting on any use of a segment that is not mapped, while
+///`Strict` checks the segment of every access up front and also faults on
+///unmapping segment 0, which the UM specification forbids. Both modes behave
+///the same on any program that does not fault in `Strict` mode.
+pub enum Mode {
+    #[default]
+    Fast,
+    Strict
+}
+
+#[derive(Debug, Clone)]
+///Structure: Rum
+///
+///This structure has a `Segment` and `Register` which
+///the `Segment` will have the instruction word during runtime
+///and the `Register` will have the values and address of the 
+///value assignments based on the given instructions. `pc` is the
+///offset in segment 0 of the next instruction to execute, `executed` the
+///number of instructions executed so far, `host_calls` the handlers the
+///host registered for opcodes 14 and 15, and `input` what the host passed
+///to `provide_input` for the next `Input` instruction.
+pub struct Rum {
+    segment: Segment,
+    register: Register,
+    pc: usize,
+    executed: u64,
+    mode: Mode,
+    host_calls: HostCalls,
+    input: Option<Option<u8>>
+}
+//Rum Implementation
+impl Rum {
+
+    ///Function: `new(some_instruction: &Vec<u32>) -> Rum`
+    ///
+    ///This function is intended to initialize the `Rum`
+    ///machine where the `segment` will have the instruction value
+    ///and a new vector `register` will be initialized before 
+    ///following the opcode functions.
+    pub fn new(some_instruction: &[u32]) -> Rum
+    {
+        Rum{
+
+            segment: Segment::new(some_instruction),
+            register: Register::new(),
+            pc: 0,
+            executed: 0,
+            mode: Mode::Fast,
+            host_calls: HostCalls::default(),
+            input: None
+        }
+    }
+
+    ///Function: `set_mode(&mut self, mode: Mode)`
+    ///
+    ///This function switches the machine between `Fast` and `Strict` execution.
+    pub fn set_mode(&mut self, mode: Mode)
+    {
+        self.mode = mode;
+    }
+
+    ///Function: `mode(&self) -> Mode`
+    ///
+    ///This getter function returns the execution mode of the machine.
+    pub fn mode(&self) -> Mode
+    {
+        self.mode
+    }
+
+    ///Function: `set_memory_limit(&mut self, words: usize)`
+    ///
+    ///This function caps the number of words the segments of the machine may
+    ///hold in total, segment 0 included: a `MapSegment` that would go past it
+    ///faults with `Fault::MemoryLimit` instead of allocating. Forks of the
+    ///machine keep the limit.
+    pub fn set_memory_limit(&mut self, words: usize)
+    {
+        self.segment.set_limit(words);
+    }
+
+    ///Function: `register_host_call(&mut self, opcode: u32, handler: impl FnMut(&mut HostCall<'_>) -> Result<(), Fault> + 'static)`
+    ///
+    ///This function opts into an extension of the UM: executing `opcode` (14
+    ///or 15) calls `handler` instead of faulting, letting the host offer
+    ///services such as a clock or random numbers to its own programs. It
+    ///replaces any handler already registered for `opcode` and panics when
+    ///`opcode` is not 14 or 15.
+    pub fn register_host_call(&mut self, opcode: u32, handler: impl FnMut(&mut HostCall<'_>) -> Result<(), Fault> + 'static)
+    {
+        self.host_calls.set(opcode, Some(Rc::new(RefCell::new(handler))));
+    }
+
+    ///Function: `unregister_host_call(&mut self, opcode: u32)`
+    ///
+    ///This function removes the handler of `opcode` (14 or 15), which faults
+    ///again from then on.
+    pub fn unregister_host_call(&mut self, opcode: u32)
+    {
+        self.host_calls.set(opcode, None);
+    }
+
+    ///Function: `check_mapped(&self, some_address: usize) -> Result<(), Fault>`
+    ///
+    ///This helper function faults when `Strict` mode is on and nothing is
+    ///mapped at `some_address`.
+    #[inline]
+    fn check_mapped(&self, some_address: usize) -> Result<(), Fault>
+    {
+        if self.mode == Mode::Strict && self.segment.allocation(some_address).is_none() {
+            return Err(Fault::UnmappedSegment { id: some_address as u32 });
+        }
+
+        Ok(())
+    }
+
+    ///Function: `fn get_instruction(&self, c: usize) -> Result<Instruction, Fault>`
+    ///
+    ///This function is intended to get the instruction from a helper
+    ///function. The function will return the found `Instruction` from the
+    ///helper function `find_instruction`.
+    pub fn get_instruction(&self, c: usize) -> Result<Instruction, Fault> {
+        self.segment.find_instruction(c)
+    }
+
+    ///Function: `step(&mut self, io: &mut dyn UmIo) -> Result<State, Fault>`
+    ///
+    ///This function executes the instruction at `pc` and moves `pc` to the
+    ///next instruction. When the instruction faults, the machine is left
+    ///untouched, except for what a host call changed before faulting, and
+    ///`pc` still points at the faulting instruction.
+    pub fn step(&mut self, io: &mut dyn UmIo) -> Result<State, Fault>
+    {
+        let word = self.segment.fetch(self.pc).ok_or(Fault::PcOutOfBounds { pc: self.pc })?;
+        let this_instruction = Instruction::new(word);
+        let mut next_pc = self.pc + 1;
+
+        match this_instruction.opcode {
+            Opcode::CMov => self.conditional_move(this_instruction),
+            Opcode::Load => self.segment_load(this_instruction)?,
+            Opcode::Store => self.segment_store(this_instruction)?,
+            Opcode::Add => self.addition(this_instruction),
+            Opcode::Mul => self.multiplication(this_instruction),
+            Opcode::Div => self.division(this_instruction)?,
+            Opcode::Nand => self.bit_nand(this_instruction),
+            Opcode::Halt => {
+                self.executed += 1;
+                return Ok(State::Halted);
+            }
+            Opcode::MapSegment => self.map_segment(this_instruction)?,
+            Opcode::UnmapSegment => self.unmap_segment(this_instruction)?,
+            Opcode::Output => self.output_program(this_instruction, io)?,
+            Opcode::Input => if !self.user_input(this_instruction, io) {
+                return Ok(State::NeedsInput);
+            },
+            Opcode::LoadProgram => next_pc = self.load_program(this_instruction)?,
+            Opcode::LoadValue => self.load_value(this_instruction),
+            Opcode::Err => self.host_call(this_instruction, word, io)?,
+        }
+
+        self.pc = next_pc;
+        self.executed += 1;
+
+        Ok(State::Running)
+    }
+
+    ///Function: `run<I: UmIo + ?Sized>(&mut self, io: &mut I) -> Result<State, Fault>`
+    ///
+    ///This function steps the machine until it halts or needs input, and
+    ///returns `State::Halted` or `State::NeedsInput` accordingly.
+    pub fn run<I: UmIo + ?Sized>(&mut self, mut io: &mut I) -> Result<State, Fault>
+    {
+        let fast = self.mode == Mode::Fast;
+
+        loop {
+            if fast && self.execute_fast() {
+                continue;
+            }
+
+            let state = self.step(&mut io)?;
+
+            if state != State::Running {
+                return Ok(state);
+            }
+        }
+    }
+
+    ///Function: `run_for<I: UmIo + ?Sized>(&mut self, io: &mut I, budget: u64) -> Result<State, Fault>`
+    ///
+    ///This function executes at most `budget` instructions. It returns
+    ///`State::Running` when the budget ran out before the machine halted or
+    ///needed input.
+    pub fn run_for<I: UmIo + ?Sized>(&mut self, mut io: &mut I, budget: u64) -> Result<State, Fault>
+    {
+        let fast = self.mode == Mode::Fast;
+
+        for _ in 0..budget {
+            if fast && self.execute_fast() {
+                continue;
+            }
+
+            let state = self.step(&mut io)?;
+
+            if state != State::Running {
+                return Ok(state);
+            }
+        }
+
+        Ok(State::Running)
+    }
+
+    ///Function: `execute_fast(&mut self) -> bool`
+    ///
+    ///This helper function is the hot path of `run` and `run_for` in `Fast`
+    ///mode: it decodes the word at `pc` in place and executes it when it is a
+    ///common instruction that does not fault. It returns `false`, leaving the machine untouched, for
+    ///anything else (I/O, `Halt`, copying `LoadProgram`s and every fault),
+    ///which `step` then executes.
+    #[inline(always)]
+    fn execute_fast(&mut self) -> bool
+    {
+        let Some(word) = self.segment.fetch(self.pc) else {
+            return false;
+        };
+        let registers = self.register.values_mut();
+        let (a, b, c) = ((word >> 6 & 7) as usize, (word >> 3 & 7) as usize, (word & 7) as usize);
+
+        match word >> 28 {
+            0 => if registers[c] != 0 {
+                registers[a] = registers[b];
+            },
+            1 => match self.segment.get_segment_value(registers[b] as usize, registers[c] as usize) {
+                Some(value) => registers[a] = value,
+                None => return false,
+            },
+            2 => if self.segment.set_segment_value(registers[a] as usize, registers[b] as usize, registers[c]).is_err() {
+                return false;
+            },
+            3 => registers[a] = registers[b].wrapping_add(registers[c]),
+            4 => registers[a] = registers[b].wrapping_mul(registers[c]),
+            5 => match registers[b].checked_div(registers[c]) {
+                Some(value) => registers[a] = value,
+                None => return false,
+            },
+            6 => registers[a] = !(registers[b] & registers[c]),
+            8 => match self.segment.map_segment(registers[c] as usize) {
+                Ok(address) => registers[b] = address as u32,
+                Err(_) => return false,
+            },
+            9 => if self.segment.unmap_segment(registers[c] as usize).is_err() {
+                return false;
+            },
+            12 if registers[b] == 0 => {
+                self.pc = registers[c] as usize;
+                self.executed += 1;
+                return true;
+            }
+            13 => registers[(word >> 25 & 7) as usize] = word & 0x1ff_ffff,
+            _ => return false,
+        }
+
+        self.pc += 1;
+        self.executed += 1;
+
+        true
+    }
+
+    ///Function: `run_monitored(&mut self, io: &mut dyn UmIo, monitor: &mut dyn Monitor) -> Result<State, Fault>`
+    ///
+    ///This function behaves like `run` but lets `monitor` observe every
+    ///instruction before and after it executes. An `Input` instruction left
+    ///waiting for input is only seen by `before_step`, and again once the
+    ///machine runs on.
+    pub fn run_monitored(&mut self, io: &mut dyn UmIo, monitor: &mut dyn Monitor) -> Result<State, Fault>
+    {
+        self.run_monitored_for(io, monitor, u64::MAX)
+    }
+
+    ///Function: `run_monitored_for(&mut self, io: &mut dyn UmIo, monitor: &mut dyn Monitor, budget: u64) -> Result<State, Fault>`
+    ///
+    ///This function is `run_monitored` executing at most `budget`
+    ///instructions, like `run_for`.
+    pub fn run_monitored_for(&mut self, io: &mut dyn UmIo, monitor: &mut dyn Monitor, budget: u64) -> Result<State, Fault>
+    {
+        for _ in 0..budget {
+            let pc = self.pc;
+            let this_instruction = self.get_instruction(pc)?;
+
+            monitor.before_step(self, pc, this_instruction);
+
+            let state = self.step(io)?;
+
+            if state != State::Running {
+                return Ok(state);
+            }
+
+            monitor.after_step(self, pc, this_instruction);
+        }
+
+        Ok(State::Running)
+    }
+
+    ///Function: `provide_input(&mut self, value: Option<u8>)`
+    ///
+    ///This function answers the `Input` instruction a machine stopped at with
+    ///`State::NeedsInput`: the next `Input` reads `value` instead of asking
+    ///the `UmIo`, `None` being the end of input. Calling it again before the
+    ///machine resumes replaces `value`.
+    pub fn provide_input(&mut self, value: Option<u8>)
+    {
+        self.input = Some(value);
+    }
+
+    ///Function: `pc(&self) -> usize`
+    ///
+    ///This getter function returns the offset of the next instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    ///Function: `executed(&self) -> u64`
+    ///
+    ///This getter function returns the number of instructions executed so
+    ///far, counting the `Halt` but not an instruction that faulted.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    ///Function: `registers(&self) -> &[u32]`
+    ///
+    ///This getter function returns the values of the 8 registers.
+    pub fn registers(&self) -> &[u32] {
+        self.register.values()
+    }
+
+    ///Function: `segments(&self) -> &Segment`
+    ///
+    ///This getter function returns the segmented memory of the machine.
+    pub fn segments(&self) -> &Segment {
+        &self.segment
+    }
+
+    ///Function: `set_register(&mut self, register: usize, value: u32)`
+    ///
+    ///This setter function overwrites one of the 8 registers, for hosts
+    ///driving the machine from the outside. It panics when `register` is not
+    ///below 8.
+    pub fn set_register(&mut self, register: usize, value: u32) {
+        self.register.set_register_value(register, value);
+    }
+
+    ///Function: `set_word(&mut self, id: usize, offset: usize, value: u32) -> Result<(), Fault>`
+    ///
+    ///This setter function overwrites the word at `offset` in segment `id`,
+    ///faulting like the `Store` opcode when the segment is not mapped or too
+    ///short.
+    pub fn set_word(&mut self, id: usize, offset: usize, value: u32) -> Result<(), Fault> {
+        self.check_mapped(id)?;
+        self.segment.set_segment_value(id, offset, value)
+    }
+
+    ///Function: `fork(&self) -> Rum`
+    ///
+    ///This function returns an independent copy of the machine, ready to run
+    ///from the same state. The copy shares the words of every segment with
+    ///the original until either writes to them, so forking costs a pointer per
+    ///segment and a write afterwards copies at most one page. The copy shares
+    ///the host call handlers too, like a clone.
+    pub fn fork(&self) -> Rum {
+        self.clone()
+    }
+
+    ///Function: `conditional_move(&mut self, some_instruction: Instruction)`
+    ///
+    ///The function `conditional_move` takes in the instruction `some_instruction`
+    ///which has the values A (`a_bit`),B (`b_bit`), and C(c_bit) that are used 
+    ///to conditionally move a value and its address based on a condition. 
+    ///The function will determine whether the value at `register[c_bit]` is 0 or not.
+    ///Based on this condition, the value of B will be moved to `register[A]`.
+    pub fn conditional_move(&mut self, some_instruction: Instruction)
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        if self.register.get_register_value(c_bit) != 0{
+            
+            let value = self.register.get_register_value(b_bit);
+
+            self.register.set_register_value(a_bit, value);
+        }
+    }
+
+    ///Function: `segment_load(&mut self, some_instruction: Instruction)`
+    ///
+    ///This function will load a value to from the `some_instruction` that
+    ///will be assigned at address at the values of `b_bit` and `c_bit` which
+    ///stores the found value of `b_bit` and `c_bit` in the register at the address
+    ///of value `a_bit` in the current `register` during runtime.
+    #[inline]
+    pub fn segment_load(&mut self, some_instruction: Instruction) -> Result<(), Fault>
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let this_address = self.register.get_register_value(b_bit) as usize;
+
+        self.check_mapped(this_address)?;
+
+        let reg_index = self.register.get_register_value(c_bit) as usize;
+
+        let value = self.segment.get_segment_value(this_address, reg_index)
+            .ok_or_else(|| match self.segment.segment_len(this_address) {
+                Some(_) => Fault::OutOfBounds { id: this_address as u32, offset: reg_index as u32 },
+                None => Fault::UnmappedSegment { id: this_address as u32 },
+            })?;
+
+        self.register.set_register_value(a_bit, value);
+
+        Ok(())
+    }
+
+    ///Function: `segment_store(&mut self, instruction: Instruction)`
+    ///
+    ///The function will store the segmented instruction based on the values
+    ///of `a_bit`, `b_bit`, and `c_bit`. Once the values are found within the
+    ///object `Instruction` the segment will store a value from `c_bit` at the
+    ///address (`a_bit`) at index (`b_bit`) within segment.
+    pub fn segment_store(&mut self, some_instruction: Instruction) -> Result<(), Fault>
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+        
+        let this_address = self.register.get_register_value(a_bit) as usize;
+
+        let index = self.register.get_register_value(b_bit) as usize;
+
+        let value = self.register.get_register_value(c_bit);
+
+        self.check_mapped(this_address)?;
+
+        self.segment.set_segment_value(this_address, index, value)
+    }
+
+    ///Function: `addition(&mut self, some_instruction: Instruction)`
+    ///
+    ///The `addition` function will add the values of `b_bit` and `c_bit` at the 
+    ///address of `a_bit` from the passed in `some_instruction`. The values 
+    ///will be stored at the address of `a_bit` in the register during runtime.
+    pub fn addition(&mut self, some_instruction: Instruction) {
+
+        let (a_bit, b_bit, c_bit) = (
+            
+            some_instruction.a as usize,
+            some_instruction.b.unwrap() as usize,
+            some_instruction.c.unwrap() as usize,
+        );
+    
+        let value = self.register.get_register_value(b_bit).wrapping_add(self.register.get_register_value(c_bit));
+        self.register.set_register_value(a_bit, value);
+    }
+    
+
+    ///Function: `multiplication(&mut self, some_instruction: Instruction)`
+    ///
+    ///The `multiplication` function will multiply the values of `b_bit` and `c_bit` at the 
+    ///address of `a_bit` from the passed in `some_instruction`. The values 
+    ///will be stored at the address of `a_bit` in the register during runtime.
+    pub fn multiplication(&mut self, some_instruction: Instruction)
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let value = self.register.get_register_value(b_bit).wrapping_mul(self.register.get_register_value(c_bit));
+
+        self.register.set_register_value(a_bit, value);
+    }
+
+    ///Function: `division(&mut self, some_instruction: Instruction)`
+    ///
+    ///The `division` function will divide the values of `b_bit` and `c_bit` at the 
+    ///address of `a_bit` from the passed in `some_instruction`. The values 
+    ///will be stored at the address of `a_bit` in the register during runtime.
+    pub fn division(&mut self, some_instruction: Instruction) -> Result<(), Fault>
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap();
+
+        let c_bit = some_instruction.c.unwrap();
+
+        let value = self.register.get_register_value(b_bit as usize)
+            .checked_div(self.register.get_register_value(c_bit as usize))
+            .ok_or(Fault::DivideByZero)?;
+
+        self.register.set_register_value(a_bit, value);
+
+        Ok(())
+    }
+
+    ///Function: `bit_nand(&mut self, some_instruction: Instruction)`
+    ///
+    ///The `bit_nand` function will compute the bit operator nand the values of `b_bit` and `c_bit` at the 
+    ///address of `a_bit` from the passed in `some_instruction`. The values 
+    ///will be stored at the address of `a_bit` in the register during runtime.
+    pub fn bit_nand(&mut self, some_instruction: Instruction)
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let value = !(self.register.get_register_value(b_bit) & self.register.get_register_value(c_bit));
+
+        self.register.set_register_value(a_bit, value);
+    }
+
+    ///Function: `map_segment(&mut self, some_instruction: Instruction) -> Result<(), Fault>`
+    ///
+    ///The `map_segment` function will be reassigning the a segment to a new
+    ///location in the `register`, or faulting past the memory limit.
+    pub fn map_segment(&mut self, some_instruction: Instruction) -> Result<(), Fault> {
+
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+    
+        let new_size = self.register.get_register_value(c_bit) as usize;
+    
+        let new_address = self.segment.map_segment(new_size)?;
+    
+        self.register.set_register_value(b_bit, new_address as u32);
+
+        Ok(())
+    }
+    
+
+    ///Function: `unmap_segment(&mut self, some_instruction: Instruction)`
+    ///
+    ///The `unmap_segment` function will be unmaping a segment of its location
+    ///in the current `register`.
+    pub fn unmap_segment(&mut self, some_instruction: Instruction) -> Result<(), Fault>
+    {
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let this_address = self.register.get_register_value(c_bit) as usize;
+
+        if self.mode == Mode::Strict && this_address == 0
+        {
+            return Err(Fault::UnmapProgram);
+        }
+
+        self.check_mapped(this_address)?;
+
+        self.segment.unmap_segment(this_address)
+    }
+
+    ///Function: `output_program(&mut self, some_instruction: Instruction, io: &mut dyn UmIo) -> Result<(), Fault>`
+    ///
+    ///The function will be printing out the `char` values from a provided 
+    ///program of values `[0-255]` to the given `io`.
+    pub fn output_program(&mut self, some_instruction: Instruction, io: &mut dyn UmIo) -> Result<(), Fault>
+    {
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let c_value = self.register.get_register_value(c_bit);
+
+        if c_value > 255
+        {
+            return Err(Fault::OutputRange { value: c_value });
+        }
+
+        io.output(c_value as u8);
+
+        Ok(())
+    }
+
+    ///Function: `user_input(&mut self, some_instruction: Instruction, io: &mut dyn UmIo) -> bool`
+    ///
+    ///This function is intended to handle user input during program runtime.
+    ///The byte comes from `provide_input` if the host called it, from `io`
+    ///otherwise. Once there is no more input, `register[c_bit]` is filled with
+    ///all 1's. The function returns `false`, leaving the registers alone, when
+    ///`io` has no input available yet.
+    pub fn user_input(&mut self, some_instruction: Instruction, io: &mut dyn UmIo) -> bool
+    {
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        let input = match self.input.take() {
+            Some(input) => input,
+            None => match io.try_input() {
+                Poll::Ready(input) => input,
+                Poll::Pending => return false,
+            }
+        };
+
+        if let Some(value) = input
+        {
+            
+            self.register.set_register_value(c_bit, value as u32);
+        }
+        else
+        {
+            self.register.set_register_value(c_bit, u32::MAX);
+        }
+
+        true
+    }
+
+    ///Function: `load_program(&mut self, some_instruction: Instruction) -> Result<usize, Fault>`
+    ///
+    ///This function is intended to load a program which it may have to insert its
+    ///value to `register[b_bit]` when the value equals 0. Otherwise, the function
+    ///will return the value of `c_bit` from the instruction.
+    pub fn load_program(&mut self, some_instruction: Instruction) -> Result<usize, Fault>
+    {
+        let b_bit = some_instruction.b.unwrap() as usize;
+
+        let c_bit = some_instruction.c.unwrap() as usize;
+
+        if self.register.get_register_value(b_bit) != 0
+        {
+            self.check_mapped(self.register.get_register_value(b_bit) as usize)?;
+            self.segment.insert_value(self.register.get_register_value(b_bit) as usize)?;
+        }
+
+        Ok(self.register.get_register_value(c_bit) as usize)
+    }
+
+    ///Function: `host_call(&mut self, some_instruction: Instruction, word: u32, io: &mut dyn UmIo) -> Result<(), Fault>`
+    ///
+    ///This function hands an instruction with opcode 14 or 15 to the handler
+    ///registered for it, and faults when there is none.
+    pub fn host_call(&mut self, some_instruction: Instruction, word: u32, io: &mut dyn UmIo) -> Result<(), Fault>
+    {
+        let handler = self.host_calls.get(word >> 28).cloned().ok_or(Fault::InvalidOpcode { pc: self.pc, word })?;
+        let mut handler = handler.borrow_mut();
+        let mut call = HostCall {
+            pc: self.pc,
+            word,
+            a: some_instruction.a as usize,
+            b: some_instruction.b.unwrap() as usize,
+            c: some_instruction.c.unwrap() as usize,
+            registers: self.register.values_mut(),
+            io,
+            segments: &mut self.segment,
+        };
+
+        handler(&mut call)
+    }
+
+    ///Function: `load_value(&mut self, some_instruction: Instruction)`
+    ///
+    ///This function is intended to get set the `value` from `some_instruction` at the
+    ///address of `a_bit` in the register during runtime.
+    pub fn load_value(&mut self, some_instruction: Instruction)
+    {
+        let a_bit = some_instruction.a as usize;
+
+        let value = some_instruction.value.unwrap();
+
+        self.register.set_register_value(a_bit, value);
+    }
+}
\ No newline at end of file
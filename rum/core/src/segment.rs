@@ -0,0 +1,397 @@
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{iter, mem};
+use crate::{fault::Fault, um_instruction::Instruction};
+
+//Segments longer than `PAGE_SIZE` words are split into pages of that size,
+//the unit a write copies when the page is shared with a fork of the machine
+const PAGE_BITS: usize = 14;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+///Function: `zeros(length: usize) -> Rc<[u32]>`
+///
+///This helper function allocates a page of `length` words of 0. Collecting a
+///sized iterator writes the words straight into the `Rc`, where going through
+///a `Vec` would allocate twice.
+#[inline]
+fn zeros(length: usize) -> Rc<[u32]>
+{
+    iter::repeat_n(0, length).collect()
+}
+
+#[derive(Debug, Clone)]
+///Structure: Words
+///
+///The words of a mapped segment, shared with the forks of the machine until
+///one of them writes to it. `first` holds the first `PAGE_SIZE` words and
+///`rest`, for a longer segment, the following pages (the last one shorter).
+///A write only copies the page it lands in, and the list of pages when it is
+///shared too, so a fork costs the same whatever the size of the segments.
+struct Words {
+    first: Rc<[u32]>,
+    rest: Option<Rc<[Rc<[u32]>]>>
+}
+
+impl Words {
+    ///Function: `new(words: &[u32]) -> Words`
+    ///
+    ///This function copies `words` into pages.
+    fn new(words: &[u32]) -> Words
+    {
+        let mut pages = words.chunks(PAGE_SIZE).map(Rc::from);
+
+        Words {
+            first: pages.next().unwrap_or_else(|| Rc::from([])),
+            rest: (words.len() > PAGE_SIZE).then(|| pages.collect())
+        }
+    }
+
+    ///Function: `zeroed(length: usize) -> Words`
+    ///
+    ///This function creates `length` words of 0. The full pages of a long
+    ///segment all share a single page until they are written to.
+    fn zeroed(length: usize) -> Words
+    {
+        if length <= PAGE_SIZE {
+            return Words { first: zeros(length), rest: None };
+        }
+
+        let zeros = zeros(PAGE_SIZE);
+        let mut rest = vec![zeros.clone(); length / PAGE_SIZE - 1];
+
+        if !length.is_multiple_of(PAGE_SIZE) {
+            rest.push(self::zeros(length % PAGE_SIZE));
+        }
+
+        Words { first: zeros, rest: Some(Rc::from(rest)) }
+    }
+
+    ///Function: `len(&self) -> usize`
+    ///
+    ///This function returns the number of words.
+    fn len(&self) -> usize
+    {
+        self.first.len() + self.rest.iter().flat_map(|rest| rest.iter()).map(|page| page.len()).sum::<usize>()
+    }
+
+    ///Function: `get(&self, index: usize) -> Option<u32>`
+    ///
+    ///This function returns the word at `index`, or `None` past the end.
+    #[inline]
+    fn get(&self, index: usize) -> Option<u32>
+    {
+        match self.first.get(index) {
+            Some(word) => Some(*word),
+            None => self.get_paged(index)
+        }
+    }
+
+    ///Function: `get_paged(&self, index: usize) -> Option<u32>`
+    ///
+    ///This helper function is `get` past the first page, kept out of line
+    ///since most segments fit in one.
+    #[cold]
+    #[inline(never)]
+    fn get_paged(&self, index: usize) -> Option<u32>
+    {
+        self.page(index).and_then(|page| page.get(index & (PAGE_SIZE - 1))).copied()
+    }
+
+    ///Function: `page(&self, index: usize) -> Option<&Rc<[u32]>>`
+    ///
+    ///This helper function returns the page of `rest` holding `index`.
+    #[inline]
+    fn page(&self, index: usize) -> Option<&Rc<[u32]>>
+    {
+        self.rest.as_ref().and_then(|rest| rest.get((index >> PAGE_BITS).wrapping_sub(1)))
+    }
+
+    ///Function: `set(&mut self, index: usize, value: u32) -> bool`
+    ///
+    ///This function overwrites the word at `index`, copying its page first when
+    ///a fork shares it. It returns `false` when `index` is past the end.
+    #[inline]
+    fn set(&mut self, index: usize, value: u32) -> bool
+    {
+        //The common case: a word of the first page, which nothing else shares
+        if let Some(word) = Rc::get_mut(&mut self.first).and_then(|page| page.get_mut(index)) {
+            *word = value;
+            return true;
+        }
+
+        self.copy_and_set(index, value)
+    }
+
+    ///Function: `copy_and_set(&mut self, index: usize, value: u32) -> bool`
+    ///
+    ///This helper function is `set` for the shared pages and the pages of
+    ///`rest`.
+    #[cold]
+    #[inline(never)]
+    fn copy_and_set(&mut self, index: usize, value: u32) -> bool
+    {
+        if index < self.first.len() {
+            Rc::make_mut(&mut self.first)[index] = value;
+            return true;
+        }
+
+        if self.page(index).is_none_or(|page| (index & (PAGE_SIZE - 1)) >= page.len()) {
+            return false;
+        }
+
+        let rest = Rc::make_mut(self.rest.as_mut().unwrap());
+
+        Rc::make_mut(&mut rest[(index >> PAGE_BITS) - 1])[index & (PAGE_SIZE - 1)] = value;
+
+        true
+    }
+
+    ///Function: `to_vec(&self) -> Vec<u32>`
+    ///
+    ///This function copies the words out of their pages.
+    fn to_vec(&self) -> Vec<u32>
+    {
+        let mut words = self.first.to_vec();
+
+        for page in self.rest.iter().flat_map(|rest| rest.iter()) {
+            words.extend_from_slice(page);
+        }
+
+        words
+    }
+}
+
+#[derive(Debug, Clone)]
+///Structure: Segment
+///
+///The structure will have many addresses and instructions during runtime and during testing.
+///`addresses` is a vector of `u32` values at are determined from the `u32` word and the `instructions`
+///are based on an adresses making the `instructions` a 2D vector of `u32` values, where a
+///segment that is not mapped is `None`.
+///`allocations` holds, for every mapped segment, the sequence number of the `map_segment`
+///that created it (segment 0 being allocation 0), which tells apart two segments that
+///reused the same address. An unmapped address has no `Words`. Cloning a `Segment`
+///(forking the machine) only costs a pointer per segment: the words are copied on
+///write, a page at a time. `words` counts the words of every mapped segment,
+///which `map_segment` keeps under `limit`.
+pub struct Segment {
+    addresses: Vec<usize>,
+    instructions: Vec<Option<Words>>,
+    allocations: Vec<Option<u64>>,
+    next_allocation: u64,
+    words: usize,
+    limit: usize
+}
+
+impl Segment {
+
+    ///Function: `new(some_instruction: &[u32]) -> Segment`
+    ///
+    ///This function initializes a new `Segment` which has
+    ///`addresses` and `instructions` which are vector respected to
+    ///the passed `some_instruction` during runtime.
+    #[inline]
+    pub fn new(some_instruction: &[u32]) -> Segment
+    {
+        Segment{
+            addresses: Vec::new(),
+            instructions: vec![Some(Words::new(some_instruction))],
+            allocations: vec![Some(0)],
+            next_allocation: 1,
+            words: some_instruction.len(),
+            limit: usize::MAX
+        }
+    }
+
+    ///Function: `set_limit(&mut self, limit: usize)`
+    ///
+    ///This function caps the number of words the segments may hold in total.
+    ///Segments already mapped are kept even when they hold more.
+    pub fn set_limit(&mut self, limit: usize)
+    {
+        self.limit = limit;
+    }
+
+    ///Function: `map_segment(& mut self, size: usize) -> Result<usize, Fault>`
+    ///
+    ///This function will returning an removed address from the vector of `addresses` that
+    ///will be returned and replaces with a zero of all zero's based on an instruction's `size`.
+    ///It faults, before allocating anything, when the new segment would take the words of
+    ///every segment past the limit.
+    #[inline]
+    pub fn map_segment(& mut self, size: usize) -> Result<usize, Fault>
+    {
+        if size > self.limit.saturating_sub(self.words) {
+            return Err(Fault::MemoryLimit { size: size as u32 });
+        }
+
+        self.words += size;
+
+        let zero_vec = Words::zeroed(size);
+        let allocation = Some(self.next_allocation);
+
+        self.next_allocation += 1;
+
+        if self.addresses.is_empty()
+        {
+            self.instructions.push(Some(zero_vec));
+            self.allocations.push(allocation);
+
+
+            Ok(self.instructions.len() - 1)
+        }
+        else
+        {
+            let this_address = self.addresses.pop().unwrap();
+            self.instructions[this_address] = Some(zero_vec);
+            self.allocations[this_address] = allocation;
+
+
+            Ok(this_address)
+        }
+    }
+
+    ///Function: `unmap_segment(& mut self, some_address: usize) -> Result<(), Fault>`
+    ///
+    ///This function will be unmapping and replacing a value at `some_address` in the
+    ///`instructions` vector. Unmapping a segment that is not mapped faults, since
+    ///its address would end up twice on the list of addresses to reuse.
+    #[inline]
+    pub fn unmap_segment(& mut self, some_address: usize) -> Result<(), Fault>
+    {
+        let old_segment = self.instructions.get_mut(some_address)
+            .and_then(Option::take)
+            .ok_or(Fault::UnmappedSegment { id: some_address as u32 })?;
+
+        self.words -= old_segment.len();
+
+        self.allocations[some_address] = None;
+        self.addresses.push(some_address);
+
+        Ok(())
+    }
+
+    ///Function: `get_segment_value(&self, some_address: usize, index: usize) -> Option<u32>`
+    ///
+    ///The helper function is designed to return the word at `index` of the segment
+    ///at `some_address`, or `None` when there is no such segment or word.
+    #[inline]
+    pub fn get_segment_value(&self, some_address: usize, index: usize) -> Option<u32>
+    {
+        self.instructions.get(some_address)?.as_ref()?.get(index)
+    }
+
+    ///Function: `segment_len(&self, some_address: usize) -> Option<usize>`
+    ///
+    ///This function returns the number of words of the segment at `some_address`,
+    ///or `None` when that segment is not mapped.
+    #[inline]
+    pub fn segment_len(&self, some_address: usize) -> Option<usize>
+    {
+        self.instructions.get(some_address)?.as_ref().map(Words::len)
+    }
+
+    ///Function: `segment_words(&self, some_address: usize) -> Option<Vec<u32>>`
+    ///
+    ///This function returns a copy of the words of the segment at `some_address`,
+    ///or `None` when that segment is not mapped.
+    pub fn segment_words(&self, some_address: usize) -> Option<Vec<u32>>
+    {
+        self.instructions.get(some_address)?.as_ref().map(Words::to_vec)
+    }
+
+    ///Function: `fetch(&self, c: usize) -> Option<u32>`
+    ///
+    ///This function returns the raw `u32` word at offset `c` of segment 0, or
+    ///`None` when `c` is past the end of the program.
+    #[inline]
+    pub fn fetch(&self, c: usize) -> Option<u32>
+    {
+        self.instructions.first()?.as_ref()?.get(c)
+    }
+
+    ///Function: `find_instruction(&self, c: usize) -> Result<Instruction, Fault>`
+    ///
+    ///This function is intended to find the `Instruction` of the segment's opcode
+    ///value that will have an intended `Instruction`.
+    #[inline]
+    pub fn find_instruction(&self, c: usize) -> Result<Instruction, Fault>
+    {
+        match self.fetch(c) {
+            Some(word) => Ok(Instruction::new(word)),
+            None => Err(Fault::PcOutOfBounds { pc: c })
+        }
+    }
+
+    ///Function: `set_segment_value(&mut self, some_address: usize, index: usize, value: u32) -> Result<(), Fault>`
+    ///
+    ///The function will be recieving `some_address`, `index`, and `value` from the `u32` word
+    ///during runtime. The function will obtain the `current_segment` at `some_address` that will
+    ///replaced at the `current_segment`'s at `index` and will have a new `value` that is passed
+    ///into the function.
+    #[inline]
+    pub fn set_segment_value(&mut self, some_address: usize, index: usize, value: u32) -> Result<(), Fault>
+    {
+        let current_segment = self.instructions.get_mut(some_address)
+            .and_then(Option::as_mut)
+            .ok_or(Fault::UnmappedSegment { id: some_address as u32 })?;
+
+        if current_segment.set(index, value) {
+            Ok(())
+        } else {
+            Err(Fault::OutOfBounds { id: some_address as u32, offset: index as u32 })
+        }
+    }
+
+    ///Function: `insert_value(&mut self, some_address: usize) -> Result<(), Fault>`
+    ///
+    ///This function will be inserting a segment at the `0` position of `instructions` that is a
+    ///`cloned_segment` of `some_address` to a newer segment.
+    #[inline]
+    pub fn insert_value(&mut self, some_address: usize) -> Result<(), Fault>
+    {
+        let cloned_segment = self.instructions.get(some_address)
+            .filter(|segment| segment.is_some())
+            .ok_or(Fault::UnmappedSegment { id: some_address as u32 })?
+            .clone();
+
+        //The copy shares the pages of the source, but counts against the limit
+        //like the words it will hold once either is written to
+        self.words += cloned_segment.as_ref().map_or(0, Words::len);
+
+        let old_segment = mem::replace(&mut self.instructions[0], cloned_segment);
+
+        self.words -= old_segment.as_ref().map_or(0, Words::len);
+
+        Ok(())
+    }
+
+    ///Function: `allocation(&self, some_address: usize) -> Option<u64>`
+    ///
+    ///This function returns the sequence number of the allocation currently
+    ///mapped at `some_address`, or `None` when nothing is mapped there.
+    #[inline]
+    pub fn allocation(&self, some_address: usize) -> Option<u64>
+    {
+        self.allocations.get(some_address).copied().flatten()
+    }
+
+    ///Function: `mapped(&self) -> impl Iterator<Item = (usize, u64)>`
+    ///
+    ///This function returns the address and allocation of every mapped
+    ///segment, including segment 0.
+    pub fn mapped(&self) -> impl Iterator<Item = (usize, u64)> + '_
+    {
+        self.allocations.iter().enumerate().filter_map(|(address, allocation)| allocation.map(|allocation| (address, allocation)))
+    }
+
+    ///Function: `total_words(&self) -> usize`
+    ///
+    ///This function returns the number of `u32` words held by every segment,
+    ///which is used to estimate how much memory a copy of the machine takes.
+    pub fn total_words(&self) -> usize
+    {
+        self.words
+    }
+}
@@ -0,0 +1,307 @@
+//use bitpack::bitpack::{getu};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Structure Instruction
+///
+///This structure has the opcode, a, b, c, and the value of the `u32` bit word
+///from runtime.
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub a: u32,
+    pub b: Option<u32>,
+    pub c: Option<u32>,
+    pub value: Option<u32>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///Enum Opcode
+///
+///The `enum` used will have all the instruction functions necessary to emulate the rum machine
+pub enum Opcode {
+    CMov,
+    Load,
+    Store,
+    Add,
+    Mul,
+    Div,
+    Nand,
+    Halt,
+    MapSegment,
+    UnmapSegment,
+    Output,
+    Input,
+    LoadProgram,
+    LoadValue,
+    Err
+}
+
+///The largest value a `LoadValue` instruction can hold in its 25 bits.
+pub const MAX_VALUE: u32 = (1 << 25) - 1;
+
+///Function: `get_opcode(instruction: u64) -> Opcode`
+///
+///This function is intended to return the `Opcode` in order to call the
+///proper rum function during runtime.
+pub fn get_opcode(instruction: u64) -> Opcode {
+
+    //getting the opcode number form the u32 bit word
+    let opcode_num = getu(instruction.try_into().unwrap(), 28, 4);
+
+    if opcode_num == 0{
+        Opcode::CMov
+    }
+    else if opcode_num == 1{
+        Opcode::Load
+    }
+    else if opcode_num == 2{
+        Opcode::Store
+    }
+    else if opcode_num == 3{
+        Opcode::Add
+    }
+    else if opcode_num == 4{
+        Opcode::Mul
+    }
+    else if opcode_num == 5{
+        Opcode::Div
+    }
+    else if opcode_num == 6{
+        Opcode::Nand
+    }
+    else if opcode_num == 7{
+        Opcode::Halt
+    }
+    else if opcode_num == 8{
+        Opcode::MapSegment
+    }
+    else if opcode_num == 9{
+        Opcode::UnmapSegment
+    }
+    else if opcode_num == 10{
+        Opcode::Output
+    }
+    else if opcode_num == 11{
+        Opcode::Input
+    }
+    else if opcode_num == 12{
+        Opcode::LoadProgram
+    }
+    else if opcode_num == 13{
+        Opcode::LoadValue
+    }
+    else{
+        Opcode::Err
+    }
+}
+
+#[inline]
+pub fn getu(word: u32, lsb: u32, width: u32) -> u32 {
+    /*  //left shifting to get the most  |   //After the left shift, then
+        //significant bit position in    |   //right shift off the uneeded
+        //the unsigned value             |   //bits from the initial left shift */
+        //eprintln!("{:032b} this is the word {},{}", word, width, lsb);
+        (word << (32 - width - lsb)) >> (32 - width)
+    }
+
+///Function: `get_a_bit(some_instruction: u64, opcode: &Opcode) -> u32`
+///
+///This function is intended to get and return the value of `A` in the instruction.
+pub fn get_a_bit(some_instruction: u64, opcode: &Opcode) -> u32 {
+    if *opcode == Opcode::LoadValue{
+        getu(some_instruction.try_into().unwrap(), 25, 3)
+    }
+    else{
+        getu(some_instruction.try_into().unwrap(), 6, 3)
+    }
+}
+
+///Function: `get_b_bit(some_instruction: u64, opcode: &Opcode) -> u32`
+///
+///This function is intended to get and return the value of `B` in the instruction.
+pub fn get_b_bit(some_instruction: u64, opcode: &Opcode) -> Option<u32> {
+    if *opcode == Opcode::LoadValue{
+        None
+    }
+    else{
+        Some(getu(some_instruction.try_into().unwrap(), 3, 3))
+    }
+
+}
+
+///Function: `get_c_bit(some_instruction: u64, opcode: &Opcode) -> u32`
+///
+///This function is intended to get and return the value of `C` in the instruction.
+pub fn get_c_bit(some_instruction: u32, opcode: &Opcode) -> Option<u32> {
+    if *opcode == Opcode::LoadValue{
+        None
+    }
+    else{
+        Some(getu(some_instruction, 0, 3))
+    }
+    
+}
+
+///Function: `get_value(some_instruction: u64, opcode: &Opcode) -> Option<u32>`
+///
+///This function is intended to get and return the value from the instruction.
+#[inline]
+pub fn get_value(some_instruction: u32, opcode: &Opcode) -> Option<u32> {
+        if *opcode == Opcode::LoadValue{
+            Some(getu(some_instruction, 0, 25))
+        }
+        else{
+            None
+        }
+}
+
+//Instruction Implementation
+impl Instruction {
+
+    ///Function: `new(instruction: u32) -> Instruction`
+    ///
+    ///This function is intended to initialize an `Instruction` and return
+    ///its structure data attributes.
+    pub fn new(instruction: u32) -> Instruction {
+        let opcode = get_opcode(instruction.into());
+        let a = get_a_bit(instruction.into(), &opcode);
+        let b = get_b_bit(instruction.into(), &opcode);
+        let c = get_c_bit(instruction, &opcode);
+        let value = get_value(instruction, &opcode);
+
+        Instruction {
+            opcode,
+            a,
+            b,
+            c,
+            value
+        }
+    }
+
+    ///Function: `encode(&self) -> u32`
+    ///
+    ///This function is the inverse of `new`: it packs the instruction back
+    ///into a `u32` word. Registers are masked to 3 bits and the value of a
+    ///`LoadValue` to 25 bits, the bits the UM ignores are left at 0 and an
+    ///`Opcode::Err` instruction is packed with opcode 14.
+    pub fn encode(&self) -> u32 {
+        let opcode = self.opcode as u32;
+
+        if self.opcode == Opcode::LoadValue {
+            (opcode << 28) | ((self.a & 0x7) << 25) | (self.value.unwrap_or(0) & 0x1ff_ffff)
+        }
+        else {
+            (opcode << 28) | ((self.a & 0x7) << 6) | ((self.b.unwrap_or(0) & 0x7) << 3) | (self.c.unwrap_or(0) & 0x7)
+        }
+    }
+
+    ///Function: `three_registers(opcode: Opcode, a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///This helper function builds an instruction that uses registers A, B
+    ///and C. Registers must be in `0..8`.
+    fn three_registers(opcode: Opcode, a: u32, b: u32, c: u32) -> Instruction {
+        assert!(a < 8 && b < 8 && c < 8, "registers are numbered 0 to 7");
+
+        Instruction { opcode, a, b: Some(b), c: Some(c), value: None }
+    }
+
+    ///Function: `cmov(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`if r[c] != 0 then r[a] := r[b]`
+    pub fn cmov(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::CMov, a, b, c)
+    }
+
+    ///Function: `load(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`r[a] := m[r[b]][r[c]]`
+    pub fn load(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Load, a, b, c)
+    }
+
+    ///Function: `store(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`m[r[a]][r[b]] := r[c]`
+    pub fn store(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Store, a, b, c)
+    }
+
+    ///Function: `add(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`r[a] := (r[b] + r[c]) mod 2^32`
+    pub fn add(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Add, a, b, c)
+    }
+
+    ///Function: `mul(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`r[a] := (r[b] * r[c]) mod 2^32`
+    pub fn mul(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Mul, a, b, c)
+    }
+
+    ///Function: `div(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`r[a] := r[b] / r[c]`
+    pub fn div(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Div, a, b, c)
+    }
+
+    ///Function: `nand(a: u32, b: u32, c: u32) -> Instruction`
+    ///
+    ///`r[a] := !(r[b] & r[c])`
+    pub fn nand(a: u32, b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Nand, a, b, c)
+    }
+
+    ///Function: `halt() -> Instruction`
+    ///
+    ///Stops the machine.
+    pub fn halt() -> Instruction {
+        Instruction::three_registers(Opcode::Halt, 0, 0, 0)
+    }
+
+    ///Function: `map_segment(b: u32, c: u32) -> Instruction`
+    ///
+    ///Maps a segment of `r[c]` words and puts its identifier in `r[b]`.
+    pub fn map_segment(b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::MapSegment, 0, b, c)
+    }
+
+    ///Function: `unmap_segment(c: u32) -> Instruction`
+    ///
+    ///Unmaps the segment `r[c]`.
+    pub fn unmap_segment(c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::UnmapSegment, 0, 0, c)
+    }
+
+    ///Function: `output(c: u32) -> Instruction`
+    ///
+    ///Outputs the byte in `r[c]`.
+    pub fn output(c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Output, 0, 0, c)
+    }
+
+    ///Function: `input(c: u32) -> Instruction`
+    ///
+    ///Reads a byte into `r[c]`, or all 1's at the end of input.
+    pub fn input(c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::Input, 0, 0, c)
+    }
+
+    ///Function: `load_program(b: u32, c: u32) -> Instruction`
+    ///
+    ///Replaces segment 0 with a copy of `m[r[b]]` (unless `r[b]` is 0) and
+    ///jumps to offset `r[c]`.
+    pub fn load_program(b: u32, c: u32) -> Instruction {
+        Instruction::three_registers(Opcode::LoadProgram, 0, b, c)
+    }
+
+    ///Function: `load_value(a: u32, value: u32) -> Option<Instruction>`
+    ///
+    ///`r[a] := value`. Like `u32::checked_add`, this returns `None` when
+    ///`value` does not fit in the 25 bits of the instruction (`MAX_VALUE`)
+    ///or `a` is not a register.
+    pub fn load_value(a: u32, value: u32) -> Option<Instruction> {
+        (a < 8 && value <= MAX_VALUE).then_some(Instruction { opcode: Opcode::LoadValue, a, b: None, c: None, value: Some(value) })
+    }
+}
\ No newline at end of file
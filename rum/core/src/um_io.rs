@@ -0,0 +1,78 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::task::Poll;
+
+///Trait: UmIo
+///
+///The trait is what the `Input` and `Output` opcodes talk to. The `rum` binary
+///uses `StdIo`, while tools that need to control or record the I/O of a program
+///(the debugger, tests, ...) provide their own implementation.
+pub trait UmIo {
+    ///Function: `input(&mut self) -> Option<u8>`
+    ///
+    ///Returns the next byte of input, or `None` once the input is exhausted.
+    fn input(&mut self) -> Option<u8>;
+
+    ///Function: `try_input(&mut self) -> Poll<Option<u8>>`
+    ///
+    ///Returns what `input` does, or `Poll::Pending` when no input is
+    ///available yet, which suspends the machine with `State::NeedsInput`
+    ///instead of blocking. By default it waits for `input`.
+    fn try_input(&mut self) -> Poll<Option<u8>> {
+        Poll::Ready(self.input())
+    }
+
+    ///Function: `output(&mut self, value: u8)`
+    ///
+    ///Writes a single byte of output.
+    fn output(&mut self, value: u8);
+}
+
+//Lets generic code hand a `&mut I`, even of an unsized `dyn UmIo`, to
+//functions taking a `&mut dyn UmIo`
+impl<I: UmIo + ?Sized> UmIo for &mut I {
+    fn input(&mut self) -> Option<u8> {
+        (**self).input()
+    }
+
+    fn try_input(&mut self) -> Poll<Option<u8>> {
+        (**self).try_input()
+    }
+
+    fn output(&mut self, value: u8) {
+        (**self).output(value);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+///Structure: BufferIo
+///
+///An in-memory I/O where the `input` bytes are given up front and every
+///byte of output is collected into `output`.
+pub struct BufferIo {
+    pub input: VecDeque<u8>,
+    pub output: Vec<u8>,
+}
+
+impl BufferIo {
+    ///Function: `new(input: &[u8]) -> BufferIo`
+    ///
+    ///This function creates a `BufferIo` that will hand out `input` one
+    ///byte at a time before reporting the end of input.
+    pub fn new(input: &[u8]) -> BufferIo {
+        BufferIo {
+            input: input.iter().copied().collect(),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl UmIo for BufferIo {
+    fn input(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+
+    fn output(&mut self, value: u8) {
+        self.output.push(value);
+    }
+}
@@ -0,0 +1,34 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rum::um_instruction::{Instruction, Opcode};
+
+//Feeds arbitrary words to `Instruction::new` and checks that decoding
+//agrees with the UM word layout and round-trips through `encode`
+fuzz_target!(|word: u32| {
+    let instruction = Instruction::new(word);
+    let opcode = word >> 28;
+
+    assert!(instruction.a < 8);
+
+    if opcode >= 14 {
+        assert_eq!(instruction.opcode, Opcode::Err);
+        assert_eq!(Instruction::new(instruction.encode()).opcode, Opcode::Err);
+        return;
+    }
+
+    assert_eq!(instruction.opcode as u32, opcode);
+
+    let significant = if instruction.opcode == Opcode::LoadValue {
+        assert_eq!((instruction.b, instruction.c), (None, None));
+        assert!(instruction.value.unwrap() < 1 << 25);
+        0xffff_ffff
+    } else {
+        assert!(instruction.b.unwrap() < 8 && instruction.c.unwrap() < 8);
+        assert_eq!(instruction.value, None);
+        0xf000_01ff
+    };
+
+    assert_eq!(instruction.encode(), word & significant);
+    assert_eq!(Instruction::new(instruction.encode()), instruction);
+});
@@ -0,0 +1,68 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rum::fault::Fault;
+use rum::rum::{Mode, Rum, State};
+use rum::um_instruction::Opcode;
+use rum::um_io::BufferIo;
+
+//Instructions executed before a run is cut short
+const STEP_LIMIT: u64 = 10_000;
+
+//Largest segment a program may map, so that a random size cannot
+//exhaust the memory of the fuzzer
+const MAX_SEGMENT_WORDS: u32 = 1 << 16;
+
+struct Outcome {
+    result: Result<State, Fault>,
+    pc: usize,
+    registers: Vec<u32>,
+    output: Vec<u8>,
+}
+
+fn execute(program: &[u32], input: &[u8], mode: Mode) -> Option<Outcome> {
+    let mut machine = Rum::new(program);
+    let mut io = BufferIo::new(input);
+    let mut result = Ok(State::Running);
+
+    machine.set_mode(mode);
+
+    for _ in 0..STEP_LIMIT {
+        if let Ok(instruction) = machine.get_instruction(machine.pc()) {
+            if instruction.opcode == Opcode::MapSegment
+                && machine.registers()[instruction.c.unwrap() as usize] > MAX_SEGMENT_WORDS
+            {
+                return None;
+            }
+        }
+
+        //A budget of one still goes through the fast path of `run_for`, so
+        //the fast run checks it against the strict run, which always steps
+        result = machine.run_for(&mut io, 1);
+
+        if result != Ok(State::Running) {
+            break;
+        }
+    }
+
+    Some(Outcome { result, pc: machine.pc(), registers: machine.registers().to_vec(), output: io.output })
+}
+
+//Runs random bytes as a program (the bytes past the last whole word are
+//the input) in both execution modes. A panic escaping either run is a bug,
+//and whenever the strict run does not fault the fast run must match it
+fuzz_target!(|data: &[u8]| {
+    let program: Vec<u32> = data.chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect();
+    let input = &data[program.len() * 4..];
+
+    let (Some(fast), Some(strict)) = (execute(&program, input, Mode::Fast), execute(&program, input, Mode::Strict)) else {
+        return;
+    };
+
+    if strict.result.is_ok() {
+        assert_eq!(fast.result, strict.result);
+        assert_eq!(fast.pc, strict.pc);
+        assert_eq!(fast.registers, strict.registers);
+        assert_eq!(fast.output, strict.output);
+    }
+});
@@ -0,0 +1,204 @@
+/* The C interface of the rum Universal Machine.
+ *
+ * Generated by `rum header` from src/ffi.rs; do not edit. */
+#ifndef RUM_H
+#define RUM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A machine, created by rum_new and released by rum_free.
+ *
+ * A machine and its snapshots share the pages of their segments through
+ * reference counts that are not atomic: a machine and every snapshot taken
+ * from it or restored into it must only be used from one thread. Separate
+ * machines can run on separate threads. */
+typedef struct rum_machine rum_machine;
+
+/* A copy of a machine, taken by rum_snapshot_take and released by
+ * rum_snapshot_free. */
+typedef struct rum_snapshot rum_snapshot;
+
+/* Returns the next byte of input (0 to 255), -1 at the end of input, or
+ * RUM_NO_INPUT when there is none yet. */
+typedef int (*rum_input_fn)(void *context);
+
+/* Receives a single byte of output. */
+typedef void (*rum_output_fn)(void *context, uint8_t value);
+
+/* The machine has not halted yet and can keep running. */
+#define RUM_RUNNING 0
+
+/* The machine executed a Halt instruction. */
+#define RUM_HALTED 1
+
+/* The machine faulted on the instruction at its pc; rum_fault says why. */
+#define RUM_FAULTED 2
+
+/* The machine stopped at an Input instruction its input function had no
+ * data for; it executes it once resumed after rum_provide_input or once
+ * the input function has data. */
+#define RUM_NEEDS_INPUT 3
+
+/* Returned by an input function that has no data yet. */
+#define RUM_NO_INPUT (-2)
+
+/* An accessor succeeded. */
+#define RUM_OK 0
+
+/* A call was given a null machine, a register past 7, or a segment word
+ * that is not mapped. */
+#define RUM_ERROR (-1)
+
+/* Creates a machine running the length instruction words at words,
+ * which are copied. Returns NULL when words is NULL but length is not 0.
+ *
+ * Safety:
+ *
+ * words must point to length readable words. */
+rum_machine *rum_new(const uint32_t *words, size_t length);
+
+/* Creates a machine from the contents of a .um file: length bytes
+ * holding big-endian words. Returns NULL when bytes is NULL or length
+ * is not a multiple of 4.
+ *
+ * Safety:
+ *
+ * bytes must point to length readable bytes. */
+rum_machine *rum_new_from_bytes(const uint8_t *bytes, size_t length);
+
+/* Releases a machine. Does nothing when machine is NULL.
+ *
+ * Safety:
+ *
+ * machine must come from rum_new and not be used afterwards. */
+void rum_free(rum_machine *machine);
+
+/* Sets the functions the Input and Output opcodes call, both given
+ * context. Either may be NULL: the program then sees the end of input, or
+ * its output is dropped.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and the functions must be safe
+ * to call with context for as long as the machine runs. */
+int rum_set_io(rum_machine *machine, rum_input_fn input, rum_output_fn output, void *context);
+
+/* Executes a single instruction and returns RUM_RUNNING, RUM_HALTED,
+ * RUM_FAULTED or RUM_NEEDS_INPUT. A faulting instruction, or an Input
+ * without input, leaves the machine untouched.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine. */
+int rum_step(rum_machine *machine);
+
+/* Executes at most budget instructions and returns RUM_RUNNING when
+ * the budget ran out, RUM_HALTED, RUM_FAULTED or RUM_NEEDS_INPUT. The number of
+ * instructions executed, counting the Halt, is stored in executed
+ * unless it is NULL.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and executed NULL or
+ * writable. */
+int rum_run(rum_machine *machine, uint64_t budget, uint64_t *executed);
+
+/* Gives value (0 to 255, or -1 for the end of input) to the next Input
+ * instruction instead of calling the input function, typically after
+ * RUM_NEEDS_INPUT. Returns RUM_ERROR for any other value.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine. */
+int rum_provide_input(rum_machine *machine, int value);
+
+/* Describes the fault of the last rum_step or rum_run, writing at most
+ * size bytes to buffer, NUL included. Returns the length of the whole
+ * description, or 0 when there was no fault.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and buffer must have room for
+ * size bytes. */
+size_t rum_fault(const rum_machine *machine, char *buffer, size_t size);
+
+/* Returns the offset in segment 0 of the next instruction, or 0 when
+ * machine is NULL.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine. */
+size_t rum_pc(const rum_machine *machine);
+
+/* Stores the value of register index (0 to 7) in value.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and value NULL or writable. */
+int rum_get_register(const rum_machine *machine, uint32_t index, uint32_t *value);
+
+/* Overwrites register index (0 to 7) with value.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine. */
+int rum_set_register(rum_machine *machine, uint32_t index, uint32_t value);
+
+/* Stores the number of words of the mapped segment in length.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and length NULL or writable. */
+int rum_segment_length(const rum_machine *machine, uint32_t segment, size_t *length);
+
+/* Stores the word at offset in the mapped segment in value.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and value NULL or writable. */
+int rum_read_word(const rum_machine *machine, uint32_t segment, uint32_t offset, uint32_t *value);
+
+/* Overwrites the word at offset in the mapped segment with value.
+ * Writing to segment 0 changes the running program.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine. */
+int rum_write_word(rum_machine *machine, uint32_t segment, uint32_t offset, uint32_t value);
+
+/* Copies the registers, segments and pc of a machine. Returns NULL when
+ * machine is NULL. The snapshot shares the segments of the machine until
+ * either changes them.
+ *
+ * Safety:
+ *
+ * machine must be NULL or a live machine, and the snapshot must only be
+ * used from the thread of machine. */
+rum_snapshot *rum_snapshot_take(const rum_machine *machine);
+
+/* Puts a machine back in the state of snapshot, which can be restored
+ * again later. The I/O functions of the machine are kept.
+ *
+ * Safety:
+ *
+ * machine and snapshot must each be NULL or live, and be used from
+ * the same thread. */
+int rum_snapshot_restore(rum_machine *machine, const rum_snapshot *snapshot);
+
+/* Releases a snapshot. Does nothing when snapshot is NULL.
+ *
+ * Safety:
+ *
+ * snapshot must come from rum_snapshot_take and not be used afterwards. */
+void rum_snapshot_free(rum_snapshot *snapshot);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUM_H */
@@ -0,0 +1,1086 @@
+//! The UM assembler.
+//!
+//! A source file holds one statement per line, optionally preceded by labels
+//! (`name:`). Comments start with `;`. Operands are registers (`r0` to `r7`),
+//! numbers (`42`, `-1`, `0x2a`, `'*'`), labels, `.equ` constants or strings.
+//!
+//! Besides the 14 UM instructions (spelled as the disassembler prints them),
+//! the assembler accepts the directives `.word`, `.string`, `.space`, `.equ`,
+//! `.include`, `.macro`/`.endm`, `.global`/`.extern` and the
+//! pseudo-instructions below.
+//!
+//! # Modules
+//!
+//! A program can be split into modules assembled separately into objects
+//! (see `object`) and linked together. Labels are local to their module unless
+//! `.global name` exports them; `.extern name` declares a label exported by
+//! another module. The module holding `initstack` exports `__stack_id`, the
+//! others import it.
+//!
+//! # Register conventions
+//!
+//! Pseudo-instructions rely on registers the program must leave alone:
+//!
+//! * `r0` always holds 0 (every register starts at 0, so it only needs to be
+//!   left untouched),
+//! * `r5` is the stack pointer: the offset of the next free word of the
+//!   stack segment set up by `initstack`,
+//! * `r6` and `r7` are temporaries clobbered by every pseudo-instruction.
+//!
+//! The identifier of the stack segment is kept in segment 0, in the word
+//! labelled `__stack_id` which is added to the program when needed.
+//!
+//! # Pseudo-instructions
+//!
+//! | statement           | effect                                            |
+//! |---------------------|---------------------------------------------------|
+//! | `mov a, b`          | `a := b`                                          |
+//! | `li32 a, value`     | `a := value` for any 32-bit value or label       |
+//! | `not a, b`          | `a := ~b`                                         |
+//! | `and a, b, c`       | `a := b & c`                                      |
+//! | `or a, b, c`        | `a := b \| c`                                     |
+//! | `sub a, b, c`       | `a := b - c`                                      |
+//! | `jmp target`        | jump to a label or to the offset held in a register |
+//! | `jz a, target`      | jump to the label `target` when `a` is 0          |
+//! | `jnz a, target`     | jump to the label `target` when `a` is not 0      |
+//! | `initstack size`    | map a stack segment of `size` words, `r5 := 0`    |
+//! | `push a` / `pop a`  | push `a` on / pop `a` off the stack               |
+//! | `call target`       | push the return offset and jump (label or register) |
+//! | `ret`               | pop an offset and jump to it                      |
+//!
+//! # Macros
+//!
+//! ```text
+//! .macro inc reg
+//!     loadv r7, 1
+//!     add \reg, \reg, r7
+//! .endm
+//! ```
+//!
+//! Inside a macro body `\name` is replaced by the argument given for the
+//! parameter `name` (the longest parameter name that matches, so `\ab` is not
+//! `\a` followed by `b` when both exist) and `\@` by `$` and a number unique
+//! to each expansion, which makes labels local to the expansion (`loop\@:`).
+//! `$` is reserved for these labels and the ones pseudo-instructions generate
+//! (`.L$1`, ...): source code cannot use it, so they never collide with its
+//! labels.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use crate::disassembler::mnemonic;
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, Opcode, MAX_VALUE};
+
+///The register that always holds 0.
+pub const ZERO: u32 = 0;
+
+///The register holding the stack pointer.
+pub const STACK_POINTER: u32 = 5;
+
+///The registers clobbered by pseudo-instructions.
+pub const TEMPORARIES: [u32; 2] = [6, 7];
+
+///The label of the word holding the identifier of the stack segment.
+pub const STACK_ID: &str = "__stack_id";
+
+//Nested `.include`s and macro expansions deeper than this are reported as
+//an error, which is how recursive includes and macros are caught
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: AsmError
+///
+///An assembly error and the source line it was found on.
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum FixupKind
+///
+///How the offset of a label is patched into a word: into the 25-bit value of
+///a `LoadValue` or as a whole data word.
+pub enum FixupKind {
+    LoadValue,
+    Word,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Fixup
+///
+///A word at `offset` that needs the offset of `label`.
+pub struct Fixup {
+    pub offset: u32,
+    pub kind: FixupKind,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+///Structure: Unit
+///
+///The output of the assembler before labels are resolved: the `words`, the
+///`fixups` still waiting for a label, the `labels` defined, the source line
+///of every word, and the labels the unit `exports` to and `imports` from
+///other modules.
+pub struct Unit {
+    pub words: Vec<u32>,
+    pub fixups: Vec<Fixup>,
+    pub labels: Vec<(String, u32)>,
+    pub lines: Vec<Option<(String, u32)>>,
+    pub exports: Vec<String>,
+    pub imports: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Assembly
+///
+///An assembled program and its symbol map.
+pub struct Assembly {
+    pub words: Vec<u32>,
+    pub symbols: SymbolMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Operand
+///
+///A single parsed operand.
+enum Operand {
+    Register(u32),
+    Number(u32),
+    Label(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+///Structure: Macro
+///
+///A macro definition: its parameter names and body lines.
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+///Structure: Location
+///
+///Where a statement comes from, for errors and the symbol map.
+struct Location {
+    file: String,
+    line: usize,
+}
+
+///Structure: Assembler
+///
+///The state of an assembly in progress.
+struct Assembler {
+    unit: Unit,
+    defined: HashMap<String, u32>,
+    equs: HashMap<String, u32>,
+    macros: HashMap<String, Macro>,
+    recording: Option<(String, Macro, Location)>,
+    expansions: usize,
+    locals: usize,
+    uses_stack: bool,
+    owns_stack: bool,
+}
+
+///Function: `assemble(source: &str, name: &str) -> Result<Assembly, AsmError>`
+///
+///This function assembles `source`, named `name` in errors and in the symbol
+///map. `.include` paths are relative to the directory of `name`.
+pub fn assemble(source: &str, name: &str) -> Result<Assembly, AsmError> {
+    assemble_unit(source, name)?.resolve()
+}
+
+///Function: `assemble_file(path: &str) -> Result<Assembly, AsmError>`
+///
+///This function reads and assembles the file at `path`.
+pub fn assemble_file(path: &str) -> Result<Assembly, AsmError> {
+    let source = read_source(path, &Location { file: path.to_string(), line: 0 })?;
+
+    assemble(&source, path)
+}
+
+///Function: `assemble_unit(source: &str, name: &str) -> Result<Unit, AsmError>`
+///
+///This function assembles `source` without resolving its labels.
+pub fn assemble_unit(source: &str, name: &str) -> Result<Unit, AsmError> {
+    let mut assembler = Assembler::run(source, name)?;
+
+    if assembler.uses_stack && !assembler.defined.contains_key(STACK_ID) {
+        assembler.stack_id(name)?;
+    }
+
+    Ok(assembler.unit)
+}
+
+///Function: `assemble_object(source: &str, name: &str) -> Result<Unit, AsmError>`
+///
+///This function assembles `source` as a module of a larger program: every
+///label used must be defined in the module or declared `.extern`.
+pub fn assemble_object(source: &str, name: &str) -> Result<Unit, AsmError> {
+    let mut assembler = Assembler::run(source, name)?;
+
+    if assembler.uses_stack && !assembler.defined.contains_key(STACK_ID) {
+        if assembler.owns_stack {
+            assembler.stack_id(name)?;
+            assembler.unit.exports.push(STACK_ID.to_string());
+        } else {
+            assembler.unit.imports.push(STACK_ID.to_string());
+        }
+    }
+
+    let unit = &assembler.unit;
+
+    for export in &unit.exports {
+        if !assembler.defined.contains_key(export) {
+            return Err(Location { file: name.to_string(), line: 0 }.error(format!("label `{}` is exported but never defined", export)));
+        }
+    }
+
+    for fixup in &unit.fixups {
+        if !assembler.defined.contains_key(&fixup.label) && !unit.imports.contains(&fixup.label) {
+            return Err(unit.location(fixup.offset).error(format!("label `{}` is never defined nor declared `.extern`", fixup.label)));
+        }
+    }
+
+    Ok(assembler.unit)
+}
+
+///Function: `to_bytes(words: &[u32]) -> Vec<u8>`
+///
+///This function turns a program into the big-endian bytes of a `.um` file.
+pub fn to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+///Function: `read_source(path: &str, location: &Location) -> Result<String, AsmError>`
+///
+///This helper function reads a source file, reporting failures at `location`.
+fn read_source(path: &str, location: &Location) -> Result<String, AsmError> {
+    std::fs::read_to_string(path).map_err(|error| location.error(format!("cannot read `{}`: {}", path, error)))
+}
+
+impl Location {
+    fn error(&self, message: String) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.line, message }
+    }
+}
+
+impl Unit {
+
+    ///Function: `location(&self, offset: u32) -> Location`
+    ///
+    ///This function returns the source line of the word at `offset`.
+    fn location(&self, offset: u32) -> Location {
+        match &self.lines[offset as usize] {
+            Some((file, line)) => Location { file: file.clone(), line: *line as usize },
+            None => Location { file: String::new(), line: 0 },
+        }
+    }
+
+    ///Function: `resolve(&self) -> Result<Assembly, AsmError>`
+    ///
+    ///This function patches every fixup with the offset of its label and
+    ///builds the symbol map of the program.
+    pub fn resolve(&self) -> Result<Assembly, AsmError> {
+        let labels: HashMap<&str, u32> = self.labels.iter().map(|(name, offset)| (name.as_str(), *offset)).collect();
+        let mut words = self.words.clone();
+
+        for fixup in &self.fixups {
+            let location = self.location(fixup.offset);
+            let value = *labels
+                .get(fixup.label.as_str())
+                .ok_or_else(|| location.error(format!("label `{}` is never defined", fixup.label)))?;
+
+            patch(&mut words[fixup.offset as usize], fixup.kind, value)
+                .ok_or_else(|| location.error(format!("label `{}` is past the reach of `loadv`", fixup.label)))?;
+        }
+
+        Ok(Assembly { words, symbols: self.symbols() })
+    }
+
+    ///Function: `symbols(&self) -> SymbolMap`
+    ///
+    ///This function returns the labels (except the ones generated by the
+    ///assembler) and source lines of the unit.
+    pub fn symbols(&self) -> SymbolMap {
+        let mut symbols = SymbolMap::new();
+
+        for (name, offset) in &self.labels {
+            if !name.starts_with('.') {
+                symbols.add_label(*offset, name);
+            }
+        }
+
+        for (offset, line) in self.lines.iter().enumerate() {
+            if let Some((file, line)) = line {
+                symbols.add_line(offset as u32, file, *line);
+            }
+        }
+
+        symbols
+    }
+}
+
+///Function: `patch(word: &mut u32, kind: FixupKind, value: u32) -> Option<()>`
+///
+///This function stores `value` in `word` as described by `kind`, returning
+///`None` when it does not fit.
+pub fn patch(word: &mut u32, kind: FixupKind, value: u32) -> Option<()> {
+    match kind {
+        FixupKind::LoadValue => {
+            let instruction = Instruction::new(*word);
+
+            *word = Instruction::load_value(instruction.a, value)?.encode();
+        }
+        FixupKind::Word => *word = value,
+    }
+
+    Some(())
+}
+
+impl Assembler {
+
+    ///Function: `run(source: &str, name: &str) -> Result<Assembler, AsmError>`
+    ///
+    ///This function assembles every line of `source`.
+    fn run(source: &str, name: &str) -> Result<Assembler, AsmError> {
+        let mut assembler = Assembler {
+            unit: Unit::default(),
+            defined: HashMap::new(),
+            equs: HashMap::new(),
+            macros: HashMap::new(),
+            recording: None,
+            expansions: 0,
+            locals: 0,
+            uses_stack: false,
+            owns_stack: false,
+        };
+
+        assembler.source(source, name, 0)?;
+
+        if let Some((name, _, location)) = assembler.recording {
+            return Err(location.error(format!("macro `{}` has no `.endm`", name)));
+        }
+
+        Ok(assembler)
+    }
+
+    ///Function: `stack_id(&mut self, name: &str) -> Result<(), AsmError>`
+    ///
+    ///This function appends the word holding the identifier of the stack segment.
+    fn stack_id(&mut self, name: &str) -> Result<(), AsmError> {
+        self.define(STACK_ID, &Location { file: name.to_string(), line: 0 })?;
+        self.unit.words.push(0);
+        self.unit.lines.push(None);
+
+        Ok(())
+    }
+
+    ///Function: `source(&mut self, text: &str, file: &str, depth: usize) -> Result<(), AsmError>`
+    ///
+    ///This function assembles every line of a source file.
+    fn source(&mut self, text: &str, file: &str, depth: usize) -> Result<(), AsmError> {
+        for (number, line) in text.lines().enumerate() {
+            let location = Location { file: file.to_string(), line: number + 1 };
+
+            if uses_reserved(line) {
+                return Err(location.error("`$` is reserved for the labels macros and pseudo-instructions generate".to_string()));
+            }
+
+            self.line(line, &location, depth)?;
+        }
+
+        Ok(())
+    }
+
+    ///Function: `line(&mut self, text: &str, location: &Location, depth: usize) -> Result<(), AsmError>`
+    ///
+    ///This function assembles a single line: its labels, then its statement.
+    fn line(&mut self, text: &str, location: &Location, depth: usize) -> Result<(), AsmError> {
+        let text = strip_comment(text);
+
+        if let Some((name, definition, start)) = &mut self.recording {
+            if text.trim() == ".endm" {
+                let (name, definition) = (name.clone(), definition.clone());
+
+                self.macros.insert(name, definition);
+                self.recording = None;
+            } else if text.trim().starts_with(".macro") {
+                return Err(location.error(format!("macro `{}` (line {}) is missing its `.endm`", name, start.line)));
+            } else {
+                definition.body.push(text.to_string());
+            }
+
+            return Ok(());
+        }
+
+        let mut rest = text.trim();
+
+        while let Some((label, after)) = split_label(rest) {
+            self.define(label, location)?;
+            rest = after.trim();
+        }
+
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let (name, operands) = match rest.find(char::is_whitespace) {
+            Some(index) => (&rest[..index], rest[index..].trim()),
+            None => (rest, ""),
+        };
+
+        if name == ".macro" {
+            let mut words = operands.split(|c: char| c == ',' || c.is_whitespace()).filter(|word| !word.is_empty());
+            let macro_name = words.next().ok_or_else(|| location.error("`.macro` needs a name".to_string()))?;
+            let parameters = words.map(|word| word.to_string()).collect();
+
+            self.recording = Some((macro_name.to_string(), Macro { parameters, body: Vec::new() }, location.clone()));
+            return Ok(());
+        }
+
+        if let Some(definition) = self.macros.get(name).cloned() {
+            return self.expand(name, &definition, operands, location, depth);
+        }
+
+        let operands = split_operands(operands)
+            .iter()
+            .map(|operand| self.operand(operand, location))
+            .collect::<Result<Vec<Operand>, AsmError>>()?;
+
+        self.statement(name, &operands, location, depth)
+    }
+
+    ///Function: `expand(&mut self, name: &str, definition: &Macro, arguments: &str, location: &Location, depth: usize) -> Result<(), AsmError>`
+    ///
+    ///This function assembles the body of a macro with its parameters replaced.
+    fn expand(&mut self, name: &str, definition: &Macro, arguments: &str, location: &Location, depth: usize) -> Result<(), AsmError> {
+        if depth >= MAX_DEPTH {
+            return Err(location.error(format!("macro `{}` expands too deeply (is it recursive?)", name)));
+        }
+
+        let arguments = split_operands(arguments);
+
+        if arguments.len() != definition.parameters.len() {
+            return Err(location.error(format!(
+                "macro `{}` takes {} argument(s), {} given",
+                name, definition.parameters.len(), arguments.len()
+            )));
+        }
+
+        self.expansions += 1;
+        let unique = format!("${}", self.expansions);
+
+        //Longest first, so that `\ab` is never taken for `\a`
+        let mut substitutions: Vec<(&str, &str)> = definition.parameters.iter()
+            .map(String::as_str)
+            .zip(arguments.iter().map(String::as_str))
+            .chain([("@", unique.as_str())])
+            .collect();
+
+        substitutions.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+        for body in &definition.body {
+            self.line(&substitute(body, &substitutions), location, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    ///Function: `operand(&self, text: &str, location: &Location) -> Result<Operand, AsmError>`
+    ///
+    ///This function parses a single operand.
+    fn operand(&self, text: &str, location: &Location) -> Result<Operand, AsmError> {
+        let error = || location.error(format!("cannot read operand `{}`", text));
+
+        if let Some(register) = text.strip_prefix('r').and_then(|number| number.parse::<u32>().ok()) {
+            return if register < 8 { Ok(Operand::Register(register)) } else { Err(error()) };
+        }
+
+        if text.starts_with('"') {
+            return parse_string(text).map(Operand::Text).ok_or_else(error);
+        }
+
+        if text.starts_with('\'') {
+            return match parse_string(text).as_deref().map(|text| text.as_bytes()) {
+                Some([byte]) => Ok(Operand::Number(*byte as u32)),
+                _ => Err(error()),
+            };
+        }
+
+        if let Some(number) = parse_number(text) {
+            return Ok(Operand::Number(number));
+        }
+
+        if is_identifier(text) {
+            return Ok(match self.equs.get(text) {
+                Some(value) => Operand::Number(*value),
+                None => Operand::Label(text.to_string()),
+            });
+        }
+
+        Err(error())
+    }
+
+    ///Function: `define(&mut self, label: &str, location: &Location) -> Result<(), AsmError>`
+    ///
+    ///This function defines `label` at the current offset.
+    fn define(&mut self, label: &str, location: &Location) -> Result<(), AsmError> {
+        if self.defined.insert(label.to_string(), self.here()).is_some() {
+            return Err(location.error(format!("label `{}` is defined more than once", label)));
+        }
+
+        self.unit.labels.push((label.to_string(), self.here()));
+
+        Ok(())
+    }
+
+    ///Function: `here(&self) -> u32`
+    ///
+    ///This function returns the offset of the next word.
+    fn here(&self) -> u32 {
+        self.unit.words.len() as u32
+    }
+
+    ///Function: `local(&mut self) -> String`
+    ///
+    ///This function returns a fresh label for the code a pseudo-instruction generates.
+    fn local(&mut self) -> String {
+        self.locals += 1;
+        format!(".L${}", self.locals)
+    }
+
+    ///Function: `emit(&mut self, instruction: Instruction, location: &Location)`
+    ///
+    ///This function appends an instruction.
+    fn emit(&mut self, instruction: Instruction, location: &Location) {
+        self.emit_word(instruction.encode(), location);
+    }
+
+    ///Function: `emit_word(&mut self, word: u32, location: &Location)`
+    ///
+    ///This function appends a raw word.
+    fn emit_word(&mut self, word: u32, location: &Location) {
+        self.unit.words.push(word);
+        self.unit.lines.push(Some((location.file.clone(), location.line as u32)));
+    }
+
+    ///Function: `emit_fixup(&mut self, word: u32, kind: FixupKind, label: &str, location: &Location)`
+    ///
+    ///This function appends a word that will be patched with the offset of `label`.
+    fn emit_fixup(&mut self, word: u32, kind: FixupKind, label: &str, location: &Location) {
+        self.unit.fixups.push(Fixup { offset: self.here(), kind, label: label.to_string() });
+        self.emit_word(word, location);
+    }
+
+    ///Function: `load_value(&mut self, a: u32, value: &Operand, location: &Location) -> Result<(), AsmError>`
+    ///
+    ///This function emits a `LoadValue` of a number or of the offset of a label.
+    fn load_value(&mut self, a: u32, value: &Operand, location: &Location) -> Result<(), AsmError> {
+        match value {
+            Operand::Number(number) => {
+                let instruction = Instruction::load_value(a, *number).ok_or_else(|| {
+                    location.error(format!("{} does not fit in the 25 bits of `loadv`, use `li32`", number))
+                })?;
+
+                self.emit(instruction, location);
+            }
+            Operand::Label(label) => {
+                self.emit_fixup(Instruction::load_value(a, 0).unwrap().encode(), FixupKind::LoadValue, label, location);
+            }
+            _ => return Err(location.error("expected a number or a label".to_string())),
+        }
+
+        Ok(())
+    }
+
+    ///Function: `load_label(&mut self, a: u32, label: &str, location: &Location)`
+    ///
+    ///This function emits a `LoadValue` of the offset of `label`.
+    fn load_label(&mut self, a: u32, label: &str, location: &Location) {
+        self.emit_fixup(Instruction::load_value(a, 0).unwrap().encode(), FixupKind::LoadValue, label, location);
+    }
+
+    ///Function: `load_stack_id(&mut self, location: &Location)`
+    ///
+    ///This function emits the code putting the identifier of the stack segment in `r6`.
+    fn load_stack_id(&mut self, location: &Location) {
+        self.uses_stack = true;
+        self.load_label(6, STACK_ID, location);
+        self.emit(Instruction::load(6, ZERO, 6), location);
+    }
+
+    ///Function: `jump(&mut self, target: &Operand, location: &Location) -> Result<(), AsmError>`
+    ///
+    ///This function emits an unconditional jump to a label or to the offset in a register.
+    fn jump(&mut self, target: &Operand, location: &Location) -> Result<(), AsmError> {
+        match target {
+            Operand::Register(register) => self.emit(Instruction::load_program(ZERO, *register), location),
+            _ => {
+                self.load_value(7, target, location)?;
+                self.emit(Instruction::load_program(ZERO, 7), location);
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Function: `statement(&mut self, name: &str, operands: &[Operand], location: &Location, depth: usize) -> Result<(), AsmError>`
+    ///
+    ///This function assembles an instruction, pseudo-instruction or directive.
+    fn statement(&mut self, name: &str, operands: &[Operand], location: &Location, depth: usize) -> Result<(), AsmError> {
+        let registers = |count: usize| -> Result<Vec<u32>, AsmError> {
+            let registers: Vec<u32> = operands
+                .iter()
+                .filter_map(|operand| match operand {
+                    Operand::Register(register) => Some(*register),
+                    _ => None,
+                })
+                .collect();
+
+            if operands.len() != count || registers.len() != count {
+                return Err(location.error(format!("`{}` takes {} register(s)", name, count)));
+            }
+
+            Ok(registers)
+        };
+        let register_and_value = || -> Result<(u32, &Operand), AsmError> {
+            match operands {
+                [Operand::Register(register), value] => Ok((*register, value)),
+                _ => Err(location.error(format!("`{}` takes a register and a value", name))),
+            }
+        };
+        let no_temporaries = |registers: &[u32]| -> Result<(), AsmError> {
+            match registers.iter().find(|register| TEMPORARIES.contains(register)) {
+                Some(register) => Err(location.error(format!("`{}` clobbers r{}, it cannot be an operand", name, register))),
+                None => Ok(()),
+            }
+        };
+
+        let opcode = (0..14).map(|opcode| Instruction::new(opcode << 28).opcode).find(|opcode| mnemonic(*opcode) == name);
+
+        if let Some(opcode) = opcode {
+            let instruction = match opcode {
+                Opcode::CMov => { let r = registers(3)?; Instruction::cmov(r[0], r[1], r[2]) }
+                Opcode::Load => { let r = registers(3)?; Instruction::load(r[0], r[1], r[2]) }
+                Opcode::Store => { let r = registers(3)?; Instruction::store(r[0], r[1], r[2]) }
+                Opcode::Add => { let r = registers(3)?; Instruction::add(r[0], r[1], r[2]) }
+                Opcode::Mul => { let r = registers(3)?; Instruction::mul(r[0], r[1], r[2]) }
+                Opcode::Div => { let r = registers(3)?; Instruction::div(r[0], r[1], r[2]) }
+                Opcode::Nand => { let r = registers(3)?; Instruction::nand(r[0], r[1], r[2]) }
+                Opcode::Halt => { registers(0)?; Instruction::halt() }
+                Opcode::MapSegment => { let r = registers(2)?; Instruction::map_segment(r[0], r[1]) }
+                Opcode::UnmapSegment => Instruction::unmap_segment(registers(1)?[0]),
+                Opcode::Output => Instruction::output(registers(1)?[0]),
+                Opcode::Input => Instruction::input(registers(1)?[0]),
+                Opcode::LoadProgram => { let r = registers(2)?; Instruction::load_program(r[0], r[1]) }
+                Opcode::LoadValue | Opcode::Err => {
+                    let (a, value) = register_and_value()?;
+                    return self.load_value(a, value, location);
+                }
+            };
+
+            self.emit(instruction, location);
+            return Ok(());
+        }
+
+        match name {
+            "mov" => {
+                let r = registers(2)?;
+                self.emit(Instruction::add(r[0], r[1], ZERO), location);
+            }
+            "li32" => {
+                let (a, value) = register_and_value()?;
+
+                match value {
+                    Operand::Number(number) if *number > MAX_VALUE && !*number <= MAX_VALUE => {
+                        self.emit(Instruction::load_value(a, !*number).unwrap(), location);
+                        self.emit(Instruction::nand(a, a, a), location);
+                    }
+                    Operand::Number(number) if *number > MAX_VALUE => {
+                        no_temporaries(&[a])?;
+                        self.emit(Instruction::load_value(a, *number >> 16).unwrap(), location);
+                        self.emit(Instruction::load_value(7, 1 << 16).unwrap(), location);
+                        self.emit(Instruction::mul(a, a, 7), location);
+
+                        if *number & 0xffff != 0 {
+                            self.emit(Instruction::load_value(7, *number & 0xffff).unwrap(), location);
+                            self.emit(Instruction::add(a, a, 7), location);
+                        }
+                    }
+                    _ => self.load_value(a, value, location)?,
+                }
+            }
+            "not" => {
+                let r = registers(2)?;
+                self.emit(Instruction::nand(r[0], r[1], r[1]), location);
+            }
+            "and" => {
+                let r = registers(3)?;
+                self.emit(Instruction::nand(r[0], r[1], r[2]), location);
+                self.emit(Instruction::nand(r[0], r[0], r[0]), location);
+            }
+            "or" => {
+                let r = registers(3)?;
+                no_temporaries(&r)?;
+                self.emit(Instruction::nand(7, r[1], r[1]), location);
+                self.emit(Instruction::nand(r[0], r[2], r[2]), location);
+                self.emit(Instruction::nand(r[0], 7, r[0]), location);
+            }
+            "sub" => {
+                let r = registers(3)?;
+                no_temporaries(&r)?;
+                self.emit(Instruction::nand(7, r[2], r[2]), location);
+                self.emit(Instruction::add(r[0], r[1], 7), location);
+                self.emit(Instruction::load_value(7, 1).unwrap(), location);
+                self.emit(Instruction::add(r[0], r[0], 7), location);
+            }
+            "jmp" => match operands {
+                [target] => self.jump(target, location)?,
+                _ => return Err(location.error("`jmp` takes a target".to_string())),
+            },
+            "jz" | "jnz" => {
+                let (a, target) = match operands {
+                    [Operand::Register(a), Operand::Label(target)] => (*a, target.clone()),
+                    _ => return Err(location.error(format!("`{}` takes a register and a label", name))),
+                };
+                let next = self.local();
+
+                no_temporaries(&[a])?;
+
+                //r7 ends up with the offset to jump to: the first label
+                //loaded, unless r[a] is not 0
+                let (first, second) = if name == "jz" { (target.as_str(), next.as_str()) } else { (next.as_str(), target.as_str()) };
+
+                self.load_label(7, first, location);
+                self.load_label(6, second, location);
+                self.emit(Instruction::cmov(7, 6, a), location);
+                self.emit(Instruction::load_program(ZERO, 7), location);
+                self.define(&next, location)?;
+            }
+            "initstack" => {
+                let size = match operands {
+                    [size @ Operand::Number(_)] => size,
+                    _ => return Err(location.error("`initstack` takes a size".to_string())),
+                };
+
+                self.uses_stack = true;
+                self.owns_stack = true;
+                self.load_value(6, size, location)?;
+                self.emit(Instruction::map_segment(7, 6), location);
+                self.load_label(6, STACK_ID, location);
+                self.emit(Instruction::store(ZERO, 6, 7), location);
+                self.emit(Instruction::load_value(STACK_POINTER, 0).unwrap(), location);
+            }
+            "push" => {
+                let r = registers(1)?;
+                no_temporaries(&r)?;
+                self.load_stack_id(location);
+                self.emit(Instruction::store(6, STACK_POINTER, r[0]), location);
+                self.emit(Instruction::load_value(7, 1).unwrap(), location);
+                self.emit(Instruction::add(STACK_POINTER, STACK_POINTER, 7), location);
+            }
+            "pop" => {
+                let r = registers(1)?;
+                no_temporaries(&r)?;
+                self.emit(Instruction::nand(7, ZERO, ZERO), location);
+                self.emit(Instruction::add(STACK_POINTER, STACK_POINTER, 7), location);
+                self.load_stack_id(location);
+                self.emit(Instruction::load(r[0], 6, STACK_POINTER), location);
+            }
+            "call" => {
+                let target = match operands {
+                    [Operand::Register(register)] if TEMPORARIES.contains(register) => {
+                        return Err(location.error(format!("`call` clobbers r{}, it cannot be the target", register)));
+                    }
+                    [target @ (Operand::Register(_) | Operand::Label(_))] => target.clone(),
+                    _ => return Err(location.error("`call` takes a label or a register".to_string())),
+                };
+                let back = self.local();
+
+                self.load_stack_id(location);
+                self.load_label(7, &back, location);
+                self.emit(Instruction::store(6, STACK_POINTER, 7), location);
+                self.emit(Instruction::load_value(7, 1).unwrap(), location);
+                self.emit(Instruction::add(STACK_POINTER, STACK_POINTER, 7), location);
+                self.jump(&target, location)?;
+                self.define(&back, location)?;
+            }
+            "ret" => {
+                registers(0)?;
+                self.emit(Instruction::nand(7, ZERO, ZERO), location);
+                self.emit(Instruction::add(STACK_POINTER, STACK_POINTER, 7), location);
+                self.load_stack_id(location);
+                self.emit(Instruction::load(7, 6, STACK_POINTER), location);
+                self.emit(Instruction::load_program(ZERO, 7), location);
+            }
+            ".word" => {
+                for operand in operands {
+                    match operand {
+                        Operand::Number(number) => self.emit_word(*number, location),
+                        Operand::Label(label) => self.emit_fixup(0, FixupKind::Word, label, location),
+                        _ => return Err(location.error("`.word` takes numbers or labels".to_string())),
+                    }
+                }
+            }
+            ".string" => match operands {
+                [Operand::Text(text)] => {
+                    for byte in text.bytes().chain([0]) {
+                        self.emit_word(byte as u32, location);
+                    }
+                }
+                _ => return Err(location.error("`.string` takes a string".to_string())),
+            },
+            ".space" => match operands {
+                [Operand::Number(count)] => {
+                    for _ in 0..*count {
+                        self.emit_word(0, location);
+                    }
+                }
+                _ => return Err(location.error("`.space` takes a number of words".to_string())),
+            },
+            ".equ" => match operands {
+                [Operand::Label(constant), Operand::Number(value)] => {
+                    self.equs.insert(constant.clone(), *value);
+                }
+                _ => return Err(location.error("`.equ` takes a name and a number".to_string())),
+            },
+            ".global" | ".extern" => {
+                for operand in operands {
+                    let label = match operand {
+                        Operand::Label(label) => label.clone(),
+                        _ => return Err(location.error(format!("`{}` takes labels", name))),
+                    };
+
+                    if name == ".global" {
+                        self.unit.exports.push(label);
+                    } else {
+                        self.unit.imports.push(label);
+                    }
+                }
+            }
+            ".include" => {
+                let path = match operands {
+                    [Operand::Text(path)] => path,
+                    _ => return Err(location.error("`.include` takes a file name".to_string())),
+                };
+
+                if depth >= MAX_DEPTH {
+                    return Err(location.error(format!("`{}` is included too deeply (is it recursive?)", path)));
+                }
+
+                let path = include_path(&location.file, path);
+                let source = read_source(&path, location)?;
+
+                self.source(&source, &path, depth + 1)?;
+            }
+            _ => return Err(location.error(format!("unknown instruction `{}`", name))),
+        }
+
+        Ok(())
+    }
+}
+
+///Function: `include_path(file: &str, path: &str) -> String`
+///
+///This function resolves an included `path` relative to the including `file`.
+fn include_path(file: &str, path: &str) -> String {
+    let directory = Path::new(file).parent().map(Path::to_path_buf).unwrap_or_default();
+
+    directory.join(path).to_string_lossy().into_owned()
+}
+
+///Function: `strip_comment(text: &str) -> &str`
+///
+///This function removes a `;` comment that is not inside quotes.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(_), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(open), c) if c == open && !escaped => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, ';') => return &text[..index],
+            _ => {}
+        }
+
+        escaped = false;
+    }
+
+    text
+}
+
+///Function: `uses_reserved(text: &str) -> bool`
+///
+///This function tells whether a line uses `$` outside quotes and comments.
+fn uses_reserved(text: &str) -> bool {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in strip_comment(text).chars() {
+        match (quote, c) {
+            (Some(_), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(open), c) if c == open && !escaped => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '$') => return true,
+            _ => {}
+        }
+
+        escaped = false;
+    }
+
+    false
+}
+
+///Function: `substitute(body: &str, substitutions: &[(&str, &str)]) -> String`
+///
+///This function replaces every `\name` of a line of a macro body by the
+///value of the first of `substitutions` whose name it starts with. A `\`
+///followed by no name is left alone.
+fn substitute(body: &str, substitutions: &[(&str, &str)]) -> String {
+    let mut text = String::new();
+    let mut rest = body;
+
+    while let Some(index) = rest.find('\\') {
+        text.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        match substitutions.iter().find(|(name, _)| rest.starts_with(name)) {
+            Some((name, value)) => {
+                text.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => text.push('\\'),
+        }
+    }
+
+    text.push_str(rest);
+
+    text
+}
+
+///Function: `split_label(text: &str) -> Option<(&str, &str)>`
+///
+///This function splits a leading `label:` off a statement.
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let index = text.find(':')?;
+    let label = text[..index].trim();
+
+    is_identifier(label).then(|| (label, &text[index + 1..]))
+}
+
+///Function: `split_operands(text: &str) -> Vec<String>`
+///
+///This function splits operands on the commas that are not inside quotes.
+fn split_operands(text: &str) -> Vec<String> {
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(_), '\\') if !escaped => {
+                escaped = true;
+                current.push(c);
+                continue;
+            }
+            (Some(open), c) if c == open && !escaped => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, ',') => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        escaped = false;
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+
+    operands
+}
+
+///Function: `is_identifier(text: &str) -> bool`
+///
+///This function tells whether `text` can name a label, macro or constant.
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+///Function: `parse_number(text: &str) -> Option<u32>`
+///
+///This function reads a decimal or `0x` hexadecimal number, negative numbers
+///being stored in two's complement.
+fn parse_number(text: &str) -> Option<u32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse::<u32>().ok()?,
+        None => return None,
+    };
+
+    Some(if negative { value.wrapping_neg() } else { value })
+}
+
+///Function: `parse_string(text: &str) -> Option<String>`
+///
+///This function reads a quoted string or character with the escapes
+///`\n`, `\t`, `\r`, `\0`, `\\`, `\'` and `\"`.
+fn parse_string(text: &str) -> Option<String> {
+    let quote = text.chars().next()?;
+    let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        value.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            escaped @ ('\\' | '\'' | '"') => escaped,
+            _ => return None,
+        });
+    }
+
+    Some(value)
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
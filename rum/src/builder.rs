@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, MAX_VALUE};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum BuildError
+///
+///The reasons `ProgramBuilder::build` can fail.
+pub enum BuildError {
+    ///A label was used but never defined.
+    UndefinedLabel(String),
+    ///A label was defined more than once.
+    DuplicateLabel(String),
+    ///A label sits past `MAX_VALUE`, so `LoadValue` cannot hold its offset.
+    LabelOutOfRange { label: String, offset: u32 },
+    ///A label was loaded into a register outside `0..8`.
+    InvalidRegister { label: String, register: u32 },
+}
+
+#[derive(Debug, Clone, Default)]
+///Structure: ProgramBuilder
+///
+///The structure emits a UM program one instruction at a time. Labels name
+///offsets of the program and may be used before they are defined: the
+///`LoadValue` instructions that refer to them are patched by `build`.
+///
+///```
+///use rum::builder::ProgramBuilder;
+///use rum::um_instruction::Instruction;
+///
+///let mut builder = ProgramBuilder::new();
+///builder
+///    .load_label(1, "end")
+///    .push(Instruction::load_program(0, 1))
+///    .push(Instruction::output(0))
+///    .label("end")
+///    .push(Instruction::halt());
+///
+///let program = builder.build().unwrap();
+///assert_eq!(program[0], Instruction::load_value(1, 3).unwrap().encode());
+///```
+pub struct ProgramBuilder {
+    words: Vec<u32>,
+    labels: HashMap<String, u32>,
+    order: Vec<String>,
+    fixups: Vec<(usize, u32, String)>,
+    duplicates: Vec<String>,
+}
+
+impl ProgramBuilder {
+
+    ///Function: `new() -> ProgramBuilder`
+    ///
+    ///This function creates a builder for an empty program.
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder::default()
+    }
+
+    ///Function: `here(&self) -> u32`
+    ///
+    ///This function returns the offset the next instruction will have.
+    pub fn here(&self) -> u32 {
+        self.words.len() as u32
+    }
+
+    ///Function: `push(&mut self, instruction: Instruction) -> &mut ProgramBuilder`
+    ///
+    ///This function appends `instruction` to the program.
+    pub fn push(&mut self, instruction: Instruction) -> &mut ProgramBuilder {
+        self.words.push(instruction.encode());
+        self
+    }
+
+    ///Function: `word(&mut self, word: u32) -> &mut ProgramBuilder`
+    ///
+    ///This function appends a raw `word`, typically data read by the program.
+    pub fn word(&mut self, word: u32) -> &mut ProgramBuilder {
+        self.words.push(word);
+        self
+    }
+
+    ///Function: `label(&mut self, name: &str) -> &mut ProgramBuilder`
+    ///
+    ///This function defines the label `name` at the current offset.
+    pub fn label(&mut self, name: &str) -> &mut ProgramBuilder {
+        if self.labels.insert(name.to_string(), self.here()).is_some() {
+            self.duplicates.push(name.to_string());
+        } else {
+            self.order.push(name.to_string());
+        }
+
+        self
+    }
+
+    ///Function: `load_label(&mut self, a: u32, name: &str) -> &mut ProgramBuilder`
+    ///
+    ///This function appends a `LoadValue` putting the offset of the label
+    ///`name` in `r[a]`, whether the label is defined yet or not. The word is
+    ///a placeholder until `build` patches it.
+    pub fn load_label(&mut self, a: u32, name: &str) -> &mut ProgramBuilder {
+        self.fixups.push((self.words.len(), a, name.to_string()));
+        self.word(0)
+    }
+
+    ///Function: `build(&self) -> Result<Vec<u32>, BuildError>`
+    ///
+    ///This function resolves every label and returns the program words.
+    pub fn build(&self) -> Result<Vec<u32>, BuildError> {
+        if let Some(name) = self.duplicates.first() {
+            return Err(BuildError::DuplicateLabel(name.clone()));
+        }
+
+        let mut words = self.words.clone();
+
+        for (index, a, name) in &self.fixups {
+            if *a >= 8 {
+                return Err(BuildError::InvalidRegister { label: name.clone(), register: *a });
+            }
+
+            let offset = *self.labels.get(name).ok_or_else(|| BuildError::UndefinedLabel(name.clone()))?;
+            let instruction = Instruction::load_value(*a, offset)
+                .ok_or_else(|| BuildError::LabelOutOfRange { label: name.clone(), offset })?;
+
+            words[*index] = instruction.encode();
+        }
+
+        Ok(words)
+    }
+
+    ///Function: `symbols(&self) -> SymbolMap`
+    ///
+    ///This function returns a symbol map holding every label defined so far.
+    pub fn symbols(&self) -> SymbolMap {
+        let mut symbols = SymbolMap::new();
+
+        for name in &self.order {
+            symbols.add_label(self.labels[name], name);
+        }
+
+        symbols
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::UndefinedLabel(label) => write!(f, "label `{}` is never defined", label),
+            BuildError::DuplicateLabel(label) => write!(f, "label `{}` is defined more than once", label),
+            BuildError::LabelOutOfRange { label, offset } =>
+                write!(f, "label `{}` is at offset {}, past the {} a `LoadValue` can hold", label, offset, MAX_VALUE),
+            BuildError::InvalidRegister { label, register } =>
+                write!(f, "label `{}` is loaded into r{}, but registers are numbered 0 to 7", label, register),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
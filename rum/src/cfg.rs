@@ -0,0 +1,295 @@
+//! Control-flow graph recovery.
+//!
+//! The blocks, jump targets and known register values come from the
+//! optimizer's analysis (`optimizer::analyze`), which follows constants from
+//! block to block to resolve the targets of `LoadProgram`. A block whose end
+//! the program stores as an offset to come back to, as `call` does, calls a
+//! routine: its
+//! targets are function entries, and execution comes back to the block after
+//! it. A function is made of the blocks reached from its entry without
+//! following calls, stopping at jumps to targets only known at runtime (like
+//! `ret`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use crate::disassembler;
+use crate::optimizer::{self, Registers};
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, Opcode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+///Enum EdgeKind
+///
+///How control goes from one block to another.
+pub enum EdgeKind {
+    ///The block ends and execution continues with the next one.
+    FallThrough,
+    ///A `LoadProgram` to a known target.
+    Jump,
+    ///A call: a jump to a function entry that pushed its return offset.
+    Call,
+    ///The return of a call, to the block following it.
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Block
+///
+///A reachable basic block `start..end` of segment 0, as `optimizer::analyze`
+///found it: the register values known whenever it starts, the offsets of the
+///blocks it may continue with and whether it ends jumping to an offset only
+///known at runtime. `call` tells whether it ends calling a routine.
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub registers: Registers,
+    pub successors: Vec<usize>,
+    pub unknown: bool,
+    pub call: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Function
+///
+///A likely function: its entry offset, its name (a label from the symbol map,
+///`start` for offset 0 or `sub_<entry>`), and the starts of its blocks.
+pub struct Function {
+    pub entry: usize,
+    pub name: String,
+    pub blocks: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+///Structure: Cfg
+///
+///The control-flow graph recovered from segment 0 of a program. Edges go from
+///block start to block start.
+pub struct Cfg {
+    pub words: Vec<u32>,
+    pub blocks: Vec<Block>,
+    pub edges: Vec<(usize, usize, EdgeKind)>,
+    pub functions: Vec<Function>,
+    symbols: Option<SymbolMap>,
+}
+
+///Function: `recover(program: &[u32], symbols: Option<&SymbolMap>) -> Cfg`
+///
+///This function recovers the control-flow graph of `program`, naming
+///functions and labelling listings after `symbols` when available.
+pub fn recover(program: &[u32], symbols: Option<&SymbolMap>) -> Cfg {
+    let analysis = optimizer::analyze(program);
+    let blocks: Vec<Block> = analysis
+        .blocks
+        .into_iter()
+        .map(|block| Block {
+            call: analysis.returns.contains(&block.end) && Instruction::new(program[block.end - 1]).opcode == Opcode::LoadProgram,
+            start: block.start,
+            end: block.end,
+            registers: block.registers,
+            successors: block.successors,
+            unknown: block.unknown,
+        })
+        .collect();
+    let starts: BTreeSet<usize> = blocks.iter().map(|block| block.start).collect();
+    let mut edges = Vec::new();
+
+    for block in &blocks {
+        let falls = !ends_block(program[block.end - 1]);
+
+        for successor in &block.successors {
+            let kind = match (falls && *successor == block.end, block.call) {
+                (true, _) => EdgeKind::FallThrough,
+                (false, true) => EdgeKind::Call,
+                (false, false) => EdgeKind::Jump,
+            };
+
+            edges.push((block.start, *successor, kind));
+        }
+
+        if block.call && starts.contains(&block.end) {
+            edges.push((block.start, block.end, EdgeKind::Return));
+        }
+    }
+
+    //Entries are offset 0 and every call target, and a block belongs to the
+    //first function reaching it without going through another entry
+    let mut entries: BTreeSet<usize> = edges.iter().filter(|edge| edge.2 == EdgeKind::Call).map(|edge| edge.1).collect();
+    let mut owned = BTreeSet::new();
+    let mut functions = Vec::new();
+
+    entries.insert(0);
+    entries.retain(|entry| starts.contains(entry));
+
+    for entry in entries.iter().copied() {
+        let mut work = vec![entry];
+        let mut members = Vec::new();
+
+        while let Some(start) = work.pop() {
+            if !owned.insert(start) {
+                continue;
+            }
+
+            members.push(start);
+            work.extend(
+                edges
+                    .iter()
+                    .filter(|edge| edge.0 == start && edge.2 != EdgeKind::Call && !entries.contains(&edge.1))
+                    .map(|edge| edge.1),
+            );
+        }
+
+        members.sort_unstable();
+
+        let name = match symbols.and_then(|symbols| symbols.labels_at(entry as u32).next()) {
+            Some(label) => label.to_string(),
+            None if entry == 0 => "start".to_string(),
+            None => format!("sub_{}", entry),
+        };
+
+        functions.push(Function { entry, name, blocks: members });
+    }
+
+    Cfg { words: program.to_vec(), blocks, edges, functions, symbols: symbols.cloned() }
+}
+
+impl Cfg {
+
+    ///Function: `function_of(&self, start: usize) -> Option<&Function>`
+    ///
+    ///This function returns the function the block starting at `start`
+    ///belongs to.
+    pub fn function_of(&self, start: usize) -> Option<&Function> {
+        self.functions.iter().find(|function| function.blocks.contains(&start))
+    }
+
+    ///Function: `dot(&self) -> String`
+    ///
+    ///This function exports the graph in the Graphviz DOT format, one cluster
+    ///per function. Calls are bold, returns dashed, and blocks jumping to
+    ///targets only known at runtime end with `-> ?`.
+    pub fn dot(&self) -> String {
+        let mut text = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        let blocks: BTreeMap<usize, &Block> = self.blocks.iter().map(|block| (block.start, block)).collect();
+        let mut drawn = BTreeSet::new();
+
+        for (number, function) in self.functions.iter().enumerate() {
+            writeln!(text, "    subgraph cluster_{} {{\n        label=\"{}\";", number, escape(&function.name)).unwrap();
+
+            for start in &function.blocks {
+                writeln!(text, "        {}", self.node(blocks[start])).unwrap();
+                drawn.insert(*start);
+            }
+
+            text.push_str("    }\n");
+        }
+
+        for block in self.blocks.iter().filter(|block| !drawn.contains(&block.start)) {
+            writeln!(text, "    {}", self.node(block)).unwrap();
+        }
+
+        for (from, to, kind) in &self.edges {
+            let style = match kind {
+                EdgeKind::FallThrough | EdgeKind::Jump => "",
+                EdgeKind::Call => " [style=bold]",
+                EdgeKind::Return => " [style=dashed]",
+            };
+
+            writeln!(text, "    b{} -> b{}{};", from, to, style).unwrap();
+        }
+
+        text.push_str("}\n");
+        text
+    }
+
+    ///Function: `listing(&self) -> String`
+    ///
+    ///This function disassembles the whole program, starting every block with
+    ///a comment giving its function and successors, and every function with
+    ///its name. Words outside any reachable block are marked unreachable.
+    pub fn listing(&self) -> String {
+        let mut text = String::new();
+        let blocks: BTreeMap<usize, &Block> = self.blocks.iter().map(|block| (block.start, block)).collect();
+        let mut end = 0;
+        let mut reachable = true;
+
+        for (offset, word) in self.words.iter().enumerate() {
+            if let Some(block) = blocks.get(&offset) {
+                if let Some(function) = self.functions.iter().find(|function| function.entry == offset) {
+                    writeln!(text, "\n; function {}", function.name).unwrap();
+                }
+
+                let owner = self.function_of(offset).map_or("?", |function| function.name.as_str());
+
+                writeln!(text, "; block {}..{} in {}{}", block.start, block.end, owner, self.successors(offset)).unwrap();
+                end = block.end;
+                reachable = true;
+            } else if offset >= end && reachable {
+                writeln!(text, "; unreachable").unwrap();
+                reachable = false;
+            }
+
+            text.push_str(&disassembler::annotate_line(offset as u32, *word, self.symbols.as_ref()));
+            text.push('\n');
+        }
+
+        text
+    }
+
+    ///Function: `node(&self, block: &Block) -> String`
+    ///
+    ///This helper function formats the DOT node of `block`.
+    fn node(&self, block: &Block) -> String {
+        let mut label = String::new();
+
+        for offset in block.start..block.end {
+            label.push_str(&escape(&format!("{:>6}: {}", offset, disassembler::disassemble(self.words[offset]))));
+            label.push_str("\\l");
+        }
+
+        if block.unknown {
+            label.push_str("-> ?\\l");
+        }
+
+        format!("b{} [label=\"{}\"];", block.start, label)
+    }
+
+    ///Function: `successors(&self, start: usize) -> String`
+    ///
+    ///This helper function describes where the block starting at `start`
+    ///goes next, for the listing.
+    fn successors(&self, start: usize) -> String {
+        let mut parts: Vec<String> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.0 == start)
+            .map(|(_, to, kind)| match kind {
+                EdgeKind::FallThrough | EdgeKind::Jump => to.to_string(),
+                EdgeKind::Call => format!("call {}", self.function_of(*to).map_or(to.to_string(), |function| function.name.clone())),
+                EdgeKind::Return => format!("returns to {}", to),
+            })
+            .collect();
+
+        if self.blocks.iter().any(|block| block.start == start && block.unknown) {
+            parts.push("?".to_string());
+        }
+
+        match parts.is_empty() {
+            true => String::new(),
+            false => format!(" -> {}", parts.join(", ")),
+        }
+    }
+}
+
+///Function: `ends_block(word: u32) -> bool`
+///
+///This helper function tells whether execution never goes on past `word`.
+fn ends_block(word: u32) -> bool {
+    matches!(Instruction::new(word).opcode, Opcode::Halt | Opcode::LoadProgram | Opcode::Err)
+}
+
+///Function: `escape(text: &str) -> String`
+///
+///This helper function escapes `text` for a DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,148 @@
+use crate::rum::Rum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Structure: CheckpointConfig
+///
+///`interval` is the number of instructions between two checkpoints, while
+///`max_checkpoints` and `max_words` bound how many checkpoints are kept and
+///how many segment words they may hold all together.
+pub struct CheckpointConfig {
+    pub interval: u64,
+    pub max_checkpoints: usize,
+    pub max_words: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> CheckpointConfig {
+        CheckpointConfig {
+            interval: 10_000,
+            max_checkpoints: 256,
+            max_words: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+///Structure: Checkpoint
+///
+///A copy of the whole machine (registers, segments, free list and pc) taken
+///after `step` instructions, along with how much of the recorded input had
+///been consumed at that point.
+pub struct Checkpoint {
+    pub step: u64,
+    pub input_position: usize,
+    pub machine: Rum,
+}
+
+#[derive(Debug, Clone)]
+///Structure: CheckpointStore
+///
+///The structure keeps the checkpoints in order of `step`. The checkpoint of
+///the initial machine is never dropped. Once the bounds of the `config` are
+///exceeded, every other checkpoint is dropped and the interval doubles, so
+///the memory use stays bounded however long the program runs.
+pub struct CheckpointStore {
+    config: CheckpointConfig,
+    interval: u64,
+    words: usize,
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl CheckpointStore {
+
+    ///Function: `new(config: CheckpointConfig, initial: &Rum) -> CheckpointStore`
+    ///
+    ///This function creates a store whose first checkpoint is the `initial`
+    ///machine at step 0.
+    pub fn new(config: CheckpointConfig, initial: &Rum) -> CheckpointStore
+    {
+        CheckpointStore {
+            config,
+            interval: config.interval.max(1),
+            words: initial.segments().total_words(),
+            checkpoints: vec![Checkpoint { step: 0, input_position: 0, machine: initial.clone() }],
+        }
+    }
+
+    ///Function: `record(&mut self, step: u64, input_position: usize, machine: &Rum)`
+    ///
+    ///This function takes a checkpoint of `machine` when at least `interval`
+    ///instructions ran since the latest checkpoint.
+    pub fn record(&mut self, step: u64, input_position: usize, machine: &Rum)
+    {
+        let latest = self.checkpoints.last().map_or(0, |checkpoint| checkpoint.step);
+
+        if step < latest + self.interval {
+            return;
+        }
+
+        self.words += machine.segments().total_words();
+        self.checkpoints.push(Checkpoint { step, input_position, machine: machine.clone() });
+
+        while self.checkpoints.len() > 1
+            && (self.checkpoints.len() > self.config.max_checkpoints || self.words > self.config.max_words)
+        {
+            self.thin();
+        }
+    }
+
+    ///Function: `nearest(&self, step: u64) -> &Checkpoint`
+    ///
+    ///This function returns the latest checkpoint taken at or before `step`.
+    pub fn nearest(&self, step: u64) -> &Checkpoint
+    {
+        let index = self.checkpoints.partition_point(|checkpoint| checkpoint.step <= step);
+
+        &self.checkpoints[index.saturating_sub(1)]
+    }
+
+    ///Function: `before(&self, step: u64) -> Option<&Checkpoint>`
+    ///
+    ///This function returns the latest checkpoint taken strictly before `step`.
+    pub fn before(&self, step: u64) -> Option<&Checkpoint>
+    {
+        let index = self.checkpoints.partition_point(|checkpoint| checkpoint.step < step);
+
+        index.checked_sub(1).map(|index| &self.checkpoints[index])
+    }
+
+    ///Function: `len(&self) -> usize`
+    ///
+    ///This function returns how many checkpoints are currently kept.
+    pub fn len(&self) -> usize
+    {
+        self.checkpoints.len()
+    }
+
+    ///Function: `is_empty(&self) -> bool`
+    ///
+    ///The store always holds the initial checkpoint, so it is never empty.
+    pub fn is_empty(&self) -> bool
+    {
+        self.checkpoints.is_empty()
+    }
+
+    ///Function: `words(&self) -> usize`
+    ///
+    ///This function returns the number of segment words held by all checkpoints.
+    pub fn words(&self) -> usize
+    {
+        self.words
+    }
+
+    ///Function: `thin(&mut self)`
+    ///
+    ///This helper function drops every other checkpoint (keeping the initial
+    ///one) and doubles the interval between future checkpoints.
+    fn thin(&mut self)
+    {
+        let mut index = 0;
+        self.checkpoints.retain(|_| {
+            index += 1;
+            index % 2 == 1
+        });
+
+        self.interval = self.interval.saturating_mul(2);
+        self.words = self.checkpoints.iter().map(|checkpoint| checkpoint.machine.segments().total_words()).sum();
+    }
+}
@@ -0,0 +1,1127 @@
+//! A compiler from a tiny structured language to UM assembly.
+//!
+//! ```text
+//! // prints the numbers from 1 to 10
+//! var limit = 10;
+//!
+//! fn print(n) {
+//!     if (n >= 10) { print(n / 10); }
+//!     putc('0' + n % 10);
+//! }
+//!
+//! fn main() {
+//!     var i = 1;
+//!     while (i <= limit) {
+//!         print(i);
+//!         putc('\n');
+//!         i = i + 1;
+//!     }
+//! }
+//! ```
+//!
+//! A program is a list of global `var`s and `fn`s, one of them `main()`.
+//! Values are 32-bit integers: arithmetic wraps, comparisons, `/` and `%` are
+//! signed, and `&&`, `||` and `!` treat every value but 0 as true. Statements
+//! are `var`, assignments (to variables or array elements), `if`/`else`,
+//! `while`, `return` and expressions. The built-in functions are:
+//!
+//! * `putc(c)` outputs the byte `c`,
+//! * `getc()` reads a byte, returning -1 at the end of the input,
+//! * `array(n)` maps a segment of `n` zeroed words, `a[i]` indexes it,
+//! * `free(a)` unmaps it.
+//!
+//! A string literal is an array holding its bytes followed by 0, mapped once
+//! when the program starts.
+//!
+//! # Generated code
+//!
+//! The code follows the register conventions of the assembler, which it relies
+//! on for `call`, `ret`, `push` and `pop`, and adds two of its own: `r3` holds
+//! the identifier of the stack segment and `r4` the frame pointer. Expressions
+//! are computed in `r1`, using `r2` and the stack for intermediate values.
+//!
+//! Arguments are pushed from left to right before `call`, so in a function
+//! with `n` parameters the parameter `i` is at `r4 - n - 2 + i`, below the
+//! return offset and the caller's frame pointer, and local variables start at
+//! `r4`. Results are returned in `r1`.
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::assembler::{self, Assembly};
+
+///The number of words of the stack segment.
+pub const STACK_SIZE: u32 = 1 << 16;
+
+//Two-character symbols come first so they are matched before their prefixes
+const SYMBOLS: [&str; 26] = [
+    "&&", "||", "==", "!=", "<=", ">=", "(", ")", "{", "}", "[", "]", ",", ";", "=", "<", ">", "+", "-", "*", "/",
+    "%", "&", "|", "^", "!",
+];
+
+//Binary operators from the loosest to the tightest binding
+const PRECEDENCE: [&[&str]; 9] =
+    [&["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="], &["<", "<=", ">", ">="], &["+", "-"], &["*", "/", "%"]];
+
+const KEYWORDS: [&str; 6] = ["fn", "var", "if", "else", "while", "return"];
+
+//Runtime routines called by the generated code. They take their operands in
+//`r1` and `r2`, return in `r1` and preserve `r3` and `r4`
+const RUNTIME: &str = r#"
+; r1 := r1 < r2, unsigned: the borrow of r1 - r2 computed on halves so that
+; it lands in bit 31
+__ult:
+    push r3
+    push r4
+    loadv r7, 2
+    div r3, r1, r7
+    div r4, r2, r7
+    loadv r7, 1
+    and r1, r1, r7
+    and r2, r2, r7
+    not r1, r1
+    and r1, r1, r2
+    sub r3, r3, r4
+    sub r3, r3, r1
+    li32 r1, 0x80000000
+    div r1, r3, r1
+    pop r4
+    pop r3
+    ret
+
+; r1 := r1 < r2, signed: flipping the sign bits makes it unsigned
+__slt:
+    push r3
+    li32 r3, 0x80000000
+    add r1, r1, r3
+    add r2, r2, r3
+    pop r3
+    jmp __ult
+
+; r1 := r1 / r2, signed, rounding towards 0
+__sdiv:
+    push r3
+    push r4
+    li32 r3, 0x80000000
+    div r4, r1, r3
+    jz r4, __sdiv_positive_dividend
+    not r1, r1
+    loadv r7, 1
+    add r1, r1, r7
+__sdiv_positive_dividend:
+    div r3, r2, r3
+    jz r3, __sdiv_positive_divisor
+    not r2, r2
+    loadv r7, 1
+    add r2, r2, r7
+__sdiv_positive_divisor:
+    add r4, r4, r3
+    loadv r7, 1
+    and r4, r4, r7
+    div r1, r1, r2
+    jz r4, __sdiv_done
+    not r1, r1
+    loadv r7, 1
+    add r1, r1, r7
+__sdiv_done:
+    pop r4
+    pop r3
+    ret
+
+; r1 := r1 % r2, signed, with the sign of r1
+__smod:
+    push r1
+    push r2
+    call __sdiv
+    pop r2
+    mul r1, r1, r2
+    mov r2, r1
+    pop r1
+    sub r1, r1, r2
+    ret
+
+; copies the 0-terminated string at offset r2 of segment 0 into segment r1
+__load_string:
+    push r3
+    push r4
+    loadv r3, 0
+__load_string_loop:
+    sload r4, r0, r2
+    sstore r1, r3, r4
+    jz r4, __load_string_done
+    loadv r7, 1
+    add r2, r2, r7
+    add r3, r3, r7
+    jmp __load_string_loop
+__load_string_done:
+    pop r4
+    pop r3
+    ret
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: CompileError
+///
+///A compilation error and the source line it was found on.
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Token
+///
+///A token and, for the parser, `End` past the last one.
+enum Token {
+    Identifier(String),
+    Number(u32),
+    Text(Vec<u8>),
+    Symbol(&'static str),
+    End,
+}
+
+#[derive(Debug, Clone)]
+///Enum Expr
+///
+///An expression. Variables and calls keep their line for errors found while
+///generating code.
+enum Expr {
+    Number(u32),
+    Text(usize),
+    Variable(String, usize),
+    Index(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>, usize),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+///Enum Stmt
+///
+///A statement.
+enum Stmt {
+    Var(String, Option<Expr>, usize),
+    Assign(Expr, Expr, usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+///Structure: Function
+///
+///A function definition.
+struct Function {
+    name: String,
+    parameters: Vec<String>,
+    body: Vec<Stmt>,
+    line: usize,
+}
+
+///Structure: Parser
+///
+///The tokens of a program, the string literals found so far and what the
+///program declares.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    strings: Vec<Vec<u8>>,
+    globals: Vec<(String, Option<Expr>, usize)>,
+    functions: Vec<Function>,
+}
+
+///Structure: Generator
+///
+///The state of code generation: the assembly written so far and the
+///variables in scope.
+struct Generator {
+    out: String,
+    labels: usize,
+    functions: HashMap<String, usize>,
+    globals: HashMap<String, usize>,
+    scopes: Vec<HashMap<String, i32>>,
+    next_slot: i32,
+    slots: i32,
+    function: String,
+}
+
+///Function: `compile(source: &str) -> Result<String, CompileError>`
+///
+///This function compiles a program to UM assembly.
+pub fn compile(source: &str) -> Result<String, CompileError> {
+    let mut parser = Parser { tokens: lex(source)?, position: 0, strings: Vec::new(), globals: Vec::new(), functions: Vec::new() };
+
+    parser.program()?;
+
+    let mut generator = Generator {
+        out: String::new(),
+        labels: 0,
+        functions: HashMap::new(),
+        globals: HashMap::new(),
+        scopes: Vec::new(),
+        next_slot: 0,
+        slots: 0,
+        function: String::new(),
+    };
+
+    generator.program(&parser)?;
+
+    Ok(generator.out)
+}
+
+///Function: `compile_program(source: &str, name: &str) -> Result<Assembly, CompileError>`
+///
+///This function compiles a program and assembles it, `name` naming the
+///generated assembly in the symbol map.
+pub fn compile_program(source: &str, name: &str) -> Result<Assembly, CompileError> {
+    let assembly = compile(source)?;
+
+    assembler::assemble(&assembly, name)
+        .map_err(|error| CompileError { line: 0, message: format!("the generated assembly is invalid: {}", error) })
+}
+
+///Function: `lex(source: &str) -> Result<Vec<(Token, usize)>, CompileError>`
+///
+///This function splits a program into tokens, each with its line.
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut tokens = Vec::new();
+    let bytes = source.as_bytes();
+    let mut index = 0;
+    let mut line = 1;
+
+    while index < bytes.len() {
+        let c = bytes[index];
+        let error = |message: String| CompileError { line, message };
+
+        if c == b'\n' {
+            line += 1;
+            index += 1;
+        } else if c.is_ascii_whitespace() {
+            index += 1;
+        } else if source[index..].starts_with("//") {
+            while index < bytes.len() && bytes[index] != b'\n' {
+                index += 1;
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = index;
+
+            while index < bytes.len() && (bytes[index].is_ascii_alphanumeric() || bytes[index] == b'_') {
+                index += 1;
+            }
+
+            tokens.push((Token::Identifier(source[start..index].to_string()), line));
+        } else if c.is_ascii_digit() {
+            let start = index;
+
+            while index < bytes.len() && bytes[index].is_ascii_alphanumeric() {
+                index += 1;
+            }
+
+            let text = &source[start..index];
+            let value = match text.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => text.parse::<u32>(),
+            };
+
+            tokens.push((Token::Number(value.map_err(|_| error(format!("invalid number `{}`", text)))?), line));
+        } else if c == b'"' || c == b'\'' {
+            let mut text = Vec::new();
+
+            index += 1;
+
+            loop {
+                let byte = match bytes.get(index) {
+                    None | Some(b'\n') => return Err(error("unterminated literal".to_string())),
+                    Some(byte) if *byte == c => break,
+                    Some(b'\\') => {
+                        index += 1;
+
+                        match bytes.get(index) {
+                            Some(b'n') => b'\n',
+                            Some(b't') => b'\t',
+                            Some(b'r') => b'\r',
+                            Some(b'0') => 0,
+                            Some(escaped @ (b'\\' | b'\'' | b'"')) => *escaped,
+                            _ => return Err(error("invalid escape sequence".to_string())),
+                        }
+                    }
+                    Some(byte) => *byte,
+                };
+
+                text.push(byte);
+                index += 1;
+            }
+
+            index += 1;
+
+            if c == b'"' {
+                tokens.push((Token::Text(text), line));
+            } else if let [byte] = text[..] {
+                tokens.push((Token::Number(byte as u32), line));
+            } else {
+                return Err(error("a character literal holds a single character".to_string()));
+            }
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| source[index..].starts_with(**symbol))
+                .ok_or_else(|| error(format!("unexpected character `{}`", source[index..].chars().next().unwrap())))?;
+
+            tokens.push((Token::Symbol(symbol), line));
+            index += symbol.len();
+        }
+    }
+
+    tokens.push((Token::End, line));
+
+    Ok(tokens)
+}
+
+impl Parser {
+
+    ///Function: `peek(&self) -> &Token`
+    ///
+    ///This function returns the next token without consuming it.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    ///Function: `line(&self) -> usize`
+    ///
+    ///This function returns the line of the next token.
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    ///Function: `next(&mut self) -> Token`
+    ///
+    ///This function consumes the next token.
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+
+        if token != Token::End {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    ///Function: `error(&self, message: String) -> CompileError`
+    ///
+    ///This function builds an error at the next token.
+    fn error(&self, message: String) -> CompileError {
+        CompileError { line: self.line(), message }
+    }
+
+    ///Function: `accept(&mut self, symbol: &str) -> bool`
+    ///
+    ///This function consumes the next token if it is `symbol` or the keyword `symbol`.
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = match self.peek() {
+            Token::Symbol(next) => *next == symbol,
+            Token::Identifier(next) => next == symbol && KEYWORDS.contains(&symbol),
+            _ => false,
+        };
+
+        if found {
+            self.next();
+        }
+
+        found
+    }
+
+    ///Function: `expect(&mut self, symbol: &str) -> Result<(), CompileError>`
+    ///
+    ///This function consumes `symbol`, failing if it is not next.
+    fn expect(&mut self, symbol: &str) -> Result<(), CompileError> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", symbol)))
+        }
+    }
+
+    ///Function: `identifier(&mut self) -> Result<String, CompileError>`
+    ///
+    ///This function consumes a name that is not a keyword.
+    fn identifier(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Token::Identifier(name) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+
+                self.next();
+                Ok(name)
+            }
+            _ => Err(self.error("expected a name".to_string())),
+        }
+    }
+
+    ///Function: `program(&mut self) -> Result<(), CompileError>`
+    ///
+    ///This function parses global variables and functions up to the end.
+    fn program(&mut self) -> Result<(), CompileError> {
+        while *self.peek() != Token::End {
+            let line = self.line();
+
+            if self.accept("var") {
+                let (name, value) = self.var()?;
+
+                self.globals.push((name, value, line));
+            } else if self.accept("fn") {
+                let name = self.identifier()?;
+                let mut parameters = Vec::new();
+
+                self.expect("(")?;
+
+                if !self.accept(")") {
+                    loop {
+                        parameters.push(self.identifier()?);
+
+                        if self.accept(")") {
+                            break;
+                        }
+
+                        self.expect(",")?;
+                    }
+                }
+
+                let body = self.block()?;
+
+                self.functions.push(Function { name, parameters, body, line });
+            } else {
+                return Err(self.error("expected `var` or `fn`".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Function: `var(&mut self) -> Result<(String, Option<Expr>), CompileError>`
+    ///
+    ///This function parses the rest of a `var` declaration.
+    fn var(&mut self) -> Result<(String, Option<Expr>), CompileError> {
+        let name = self.identifier()?;
+        let value = if self.accept("=") { Some(self.expr()?) } else { None };
+
+        self.expect(";")?;
+
+        Ok((name, value))
+    }
+
+    ///Function: `block(&mut self) -> Result<Vec<Stmt>, CompileError>`
+    ///
+    ///This function parses statements between braces.
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        let mut body = Vec::new();
+
+        self.expect("{")?;
+
+        while !self.accept("}") {
+            body.push(self.stmt()?);
+        }
+
+        Ok(body)
+    }
+
+    ///Function: `stmt(&mut self) -> Result<Stmt, CompileError>`
+    ///
+    ///This function parses a single statement.
+    fn stmt(&mut self) -> Result<Stmt, CompileError> {
+        let line = self.line();
+
+        if self.accept("var") {
+            let (name, value) = self.var()?;
+
+            return Ok(Stmt::Var(name, value, line));
+        }
+
+        if self.accept("if") {
+            self.expect("(")?;
+            let condition = self.expr()?;
+            self.expect(")")?;
+            let then = self.block()?;
+
+            let otherwise = if !self.accept("else") {
+                Vec::new()
+            } else if matches!(self.peek(), Token::Identifier(keyword) if keyword == "if") {
+                vec![self.stmt()?]
+            } else {
+                self.block()?
+            };
+
+            return Ok(Stmt::If(condition, then, otherwise));
+        }
+
+        if self.accept("while") {
+            self.expect("(")?;
+            let condition = self.expr()?;
+            self.expect(")")?;
+
+            return Ok(Stmt::While(condition, self.block()?));
+        }
+
+        if self.accept("return") {
+            let value = if self.accept(";") {
+                None
+            } else {
+                let value = self.expr()?;
+                self.expect(";")?;
+                Some(value)
+            };
+
+            return Ok(Stmt::Return(value));
+        }
+
+        let target = self.expr()?;
+
+        if self.accept("=") {
+            let value = self.expr()?;
+            self.expect(";")?;
+
+            return Ok(Stmt::Assign(target, value, line));
+        }
+
+        self.expect(";")?;
+
+        Ok(Stmt::Expr(target))
+    }
+
+    ///Function: `expr(&mut self) -> Result<Expr, CompileError>`
+    ///
+    ///This function parses an expression.
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    ///Function: `binary(&mut self, level: usize) -> Result<Expr, CompileError>`
+    ///
+    ///This function parses operators of precedence `level` and tighter.
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+
+        while let Some(operator) = PRECEDENCE[level].iter().find(|operator| *self.peek() == Token::Symbol(operator)) {
+            self.next();
+            left = Expr::Binary(operator, Box::new(left), Box::new(self.binary(level + 1)?));
+        }
+
+        Ok(left)
+    }
+
+    ///Function: `unary(&mut self) -> Result<Expr, CompileError>`
+    ///
+    ///This function parses `-` and `!` and what they apply to.
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        for operator in ["-", "!"] {
+            if self.accept(operator) {
+                return Ok(Expr::Unary(operator, Box::new(self.unary()?)));
+            }
+        }
+
+        self.postfix()
+    }
+
+    ///Function: `postfix(&mut self) -> Result<Expr, CompileError>`
+    ///
+    ///This function parses a primary expression followed by indexing.
+    fn postfix(&mut self) -> Result<Expr, CompileError> {
+        let mut expr = self.primary()?;
+
+        while self.accept("[") {
+            let index = self.expr()?;
+            self.expect("]")?;
+            expr = Expr::Index(Box::new(expr), Box::new(index));
+        }
+
+        Ok(expr)
+    }
+
+    ///Function: `primary(&mut self) -> Result<Expr, CompileError>`
+    ///
+    ///This function parses literals, variables, calls and parentheses.
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+
+        match self.peek().clone() {
+            Token::Number(value) => {
+                self.next();
+                Ok(Expr::Number(value))
+            }
+            Token::Text(text) => {
+                self.next();
+                self.strings.push(text);
+                Ok(Expr::Text(self.strings.len() - 1))
+            }
+            Token::Symbol("(") => {
+                self.next();
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Identifier(_) => {
+                let name = self.identifier()?;
+
+                if !self.accept("(") {
+                    return Ok(Expr::Variable(name, line));
+                }
+
+                let mut arguments = Vec::new();
+
+                if !self.accept(")") {
+                    loop {
+                        arguments.push(self.expr()?);
+
+                        if self.accept(")") {
+                            break;
+                        }
+
+                        self.expect(",")?;
+                    }
+                }
+
+                Ok(Expr::Call(name, arguments, line))
+            }
+            _ => Err(self.error("expected an expression".to_string())),
+        }
+    }
+}
+
+impl Generator {
+
+    ///Function: `emit(&mut self, line: &str)`
+    ///
+    ///This function appends an instruction to the assembly.
+    fn emit(&mut self, line: &str) {
+        self.out.push_str("    ");
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    ///Function: `label(&mut self, label: &str)`
+    ///
+    ///This function defines a label in the assembly.
+    fn label(&mut self, label: &str) {
+        self.out.push_str(label);
+        self.out.push_str(":\n");
+    }
+
+    ///Function: `fresh(&mut self) -> String`
+    ///
+    ///This function returns a new label, kept out of the symbol map.
+    fn fresh(&mut self) -> String {
+        self.labels += 1;
+        format!(".c{}", self.labels)
+    }
+
+    ///Function: `program(&mut self, parser: &Parser) -> Result<(), CompileError>`
+    ///
+    ///This function generates the start-up code, every function, the runtime
+    ///and the data of the program.
+    fn program(&mut self, parser: &Parser) -> Result<(), CompileError> {
+        for function in &parser.functions {
+            if is_builtin(&function.name) || self.functions.insert(function.name.clone(), function.parameters.len()).is_some() {
+                return Err(CompileError { line: function.line, message: format!("function `{}` is already defined", function.name) });
+            }
+        }
+
+        match self.functions.get("main") {
+            Some(0) => {}
+            Some(_) => return Err(CompileError { line: 0, message: "`main` takes no parameters".to_string() }),
+            None => return Err(CompileError { line: 0, message: "the program has no `main`".to_string() }),
+        }
+
+        self.emit(&format!("initstack {}", STACK_SIZE));
+        self.emit("loadv r3, __stack_id");
+        self.emit("sload r3, r0, r3");
+        self.emit("mov r4, r5");
+
+        for (index, string) in parser.strings.iter().enumerate() {
+            self.emit(&format!("loadv r1, {}", string.len() + 1));
+            self.emit("map r1, r1");
+            self.emit(&format!("loadv r7, s.{}", index));
+            self.emit("sstore r0, r7, r1");
+            self.emit(&format!("loadv r2, s.{}.data", index));
+            self.emit("call __load_string");
+        }
+
+        for (name, value, line) in &parser.globals {
+            if self.globals.insert(name.clone(), *line).is_some() {
+                return Err(CompileError { line: *line, message: format!("variable `{}` is already defined", name) });
+            }
+
+            if let Some(value) = value {
+                self.expr(value)?;
+                self.store(name, *line)?;
+            }
+        }
+
+        self.emit("call f.main");
+        self.emit("halt");
+
+        for function in &parser.functions {
+            self.function(function)?;
+        }
+
+        self.out.push_str(RUNTIME);
+
+        for (name, _, _) in &parser.globals {
+            self.label(&format!("g.{}", name));
+            self.emit(".word 0");
+        }
+
+        for (index, string) in parser.strings.iter().enumerate() {
+            let words: Vec<String> = string.iter().chain(&[0]).map(|byte| byte.to_string()).collect();
+
+            self.label(&format!("s.{}", index));
+            self.emit(".word 0");
+            self.label(&format!("s.{}.data", index));
+            self.emit(&format!(".word {}", words.join(", ")));
+        }
+
+        Ok(())
+    }
+
+    ///Function: `function(&mut self, function: &Function) -> Result<(), CompileError>`
+    ///
+    ///This function generates a function: its frame set-up, its body and the
+    ///return sequence.
+    fn function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let count = function.parameters.len() as i32;
+        let mut parameters = HashMap::new();
+
+        for (index, parameter) in function.parameters.iter().enumerate() {
+            if parameters.insert(parameter.clone(), index as i32 - count - 2).is_some() {
+                return Err(CompileError { line: function.line, message: format!("parameter `{}` is repeated", parameter) });
+            }
+        }
+
+        self.scopes = vec![parameters];
+        self.next_slot = 0;
+        self.slots = 0;
+        self.function = function.name.clone();
+
+        //The body comes first so the size of the frame is known
+        let header = std::mem::take(&mut self.out);
+
+        self.block(&function.body)?;
+
+        let body = std::mem::replace(&mut self.out, header);
+
+        self.label(&format!("f.{}", function.name));
+        self.emit("push r4");
+        self.emit("mov r4, r5");
+
+        if self.slots > 0 {
+            self.emit(&format!("loadv r7, {}", self.slots));
+            self.emit("add r5, r5, r7");
+        }
+
+        self.out.push_str(&body);
+        self.emit("loadv r1, 0");
+        self.label(&format!("f.{}.return", function.name));
+        self.emit("mov r5, r4");
+        self.emit("pop r4");
+        self.emit("ret");
+
+        Ok(())
+    }
+
+    ///Function: `block(&mut self, body: &[Stmt]) -> Result<(), CompileError>`
+    ///
+    ///This function generates statements in a new scope, whose slots are
+    ///reused once it ends.
+    fn block(&mut self, body: &[Stmt]) -> Result<(), CompileError> {
+        let next_slot = self.next_slot;
+
+        self.scopes.push(HashMap::new());
+
+        for stmt in body {
+            self.stmt(stmt)?;
+        }
+
+        self.scopes.pop();
+        self.next_slot = next_slot;
+
+        Ok(())
+    }
+
+    ///Function: `stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError>`
+    ///
+    ///This function generates a statement.
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Var(name, value, line) => {
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => self.emit("loadv r1, 0"),
+                }
+
+                let scope = self.scopes.last_mut().unwrap();
+
+                if scope.insert(name.clone(), self.next_slot).is_some() {
+                    return Err(CompileError { line: *line, message: format!("variable `{}` is already defined", name) });
+                }
+
+                self.next_slot += 1;
+                self.slots = self.slots.max(self.next_slot);
+                self.store(name, *line)?;
+            }
+            Stmt::Assign(Expr::Variable(name, _), value, line) => {
+                self.expr(value)?;
+                self.store(name, *line)?;
+            }
+            Stmt::Assign(Expr::Index(array, index), value, _) => {
+                self.expr(value)?;
+                self.emit("push r1");
+                self.expr(array)?;
+                self.emit("push r1");
+                self.expr(index)?;
+                self.emit("mov r2, r1");
+                self.emit("pop r1");
+                //Pops the value into r7 directly, as `pop` cannot target it
+                self.emit("nand r7, r0, r0");
+                self.emit("add r5, r5, r7");
+                self.emit("sload r7, r3, r5");
+                self.emit("sstore r1, r2, r7");
+            }
+            Stmt::Assign(_, _, line) => {
+                return Err(CompileError { line: *line, message: "only variables and array elements can be assigned".to_string() });
+            }
+            Stmt::If(condition, then, otherwise) => {
+                let (otherwise_label, end) = (self.fresh(), self.fresh());
+
+                self.expr(condition)?;
+                self.emit(&format!("jz r1, {}", otherwise_label));
+                self.block(then)?;
+                self.emit(&format!("jmp {}", end));
+                self.label(&otherwise_label);
+                self.block(otherwise)?;
+                self.label(&end);
+            }
+            Stmt::While(condition, body) => {
+                let (top, end) = (self.fresh(), self.fresh());
+
+                self.label(&top);
+                self.expr(condition)?;
+                self.emit(&format!("jz r1, {}", end));
+                self.block(body)?;
+                self.emit(&format!("jmp {}", top));
+                self.label(&end);
+            }
+            Stmt::Return(value) => {
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => self.emit("loadv r1, 0"),
+                }
+
+                self.emit(&format!("jmp f.{}.return", self.function));
+            }
+            Stmt::Expr(expr) => self.expr(expr)?,
+        }
+
+        Ok(())
+    }
+
+    ///Function: `slot(&self, name: &str) -> Option<i32>`
+    ///
+    ///This function returns the frame slot of a local variable or parameter.
+    fn slot(&self, name: &str) -> Option<i32> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).copied())
+    }
+
+    ///Function: `address(&mut self, name: &str, line: usize) -> Result<bool, CompileError>`
+    ///
+    ///This function puts the address of a variable in `r7`, returning `true`
+    ///for a local variable (in the stack segment) and `false` for a global
+    ///one (in segment 0).
+    fn address(&mut self, name: &str, line: usize) -> Result<bool, CompileError> {
+        if let Some(slot) = self.slot(name) {
+            self.emit(&format!("li32 r7, {}", slot as u32));
+            self.emit("add r7, r4, r7");
+            Ok(true)
+        } else if self.globals.contains_key(name) {
+            self.emit(&format!("loadv r7, g.{}", name));
+            Ok(false)
+        } else {
+            Err(CompileError { line, message: format!("unknown variable `{}`", name) })
+        }
+    }
+
+    ///Function: `load(&mut self, name: &str, line: usize) -> Result<(), CompileError>`
+    ///
+    ///This function loads a variable into `r1`.
+    fn load(&mut self, name: &str, line: usize) -> Result<(), CompileError> {
+        let segment = if self.address(name, line)? { "r3" } else { "r0" };
+
+        self.emit(&format!("sload r1, {}, r7", segment));
+        Ok(())
+    }
+
+    ///Function: `store(&mut self, name: &str, line: usize) -> Result<(), CompileError>`
+    ///
+    ///This function stores `r1` into a variable.
+    fn store(&mut self, name: &str, line: usize) -> Result<(), CompileError> {
+        let segment = if self.address(name, line)? { "r3" } else { "r0" };
+
+        self.emit(&format!("sstore {}, r7, r1", segment));
+        Ok(())
+    }
+
+    ///Function: `expr(&mut self, expr: &Expr) -> Result<(), CompileError>`
+    ///
+    ///This function generates the code computing `expr` into `r1`.
+    fn expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Number(value) => self.emit(&format!("li32 r1, {}", value)),
+            Expr::Text(index) => {
+                self.emit(&format!("loadv r7, s.{}", index));
+                self.emit("sload r1, r0, r7");
+            }
+            Expr::Variable(name, line) => self.load(name, *line)?,
+            Expr::Index(array, index) => {
+                self.expr(array)?;
+                self.emit("push r1");
+                self.expr(index)?;
+                self.emit("mov r2, r1");
+                self.emit("pop r1");
+                self.emit("sload r1, r1, r2");
+            }
+            Expr::Call(name, arguments, line) => self.call(name, arguments, *line)?,
+            Expr::Unary(operator, operand) => {
+                self.expr(operand)?;
+
+                if *operator == "-" {
+                    self.emit("not r1, r1");
+                    self.emit("loadv r7, 1");
+                    self.emit("add r1, r1, r7");
+                } else {
+                    self.emit("loadv r2, 1");
+                    self.emit("cmov r2, r0, r1");
+                    self.emit("mov r1, r2");
+                }
+            }
+            Expr::Binary(operator @ ("&&" | "||"), left, right) => {
+                let (short, end) = (self.fresh(), self.fresh());
+                let jump = if *operator == "&&" { "jz" } else { "jnz" };
+
+                self.expr(left)?;
+                self.emit(&format!("{} r1, {}", jump, short));
+                self.expr(right)?;
+                self.emit(&format!("{} r1, {}", jump, short));
+                self.emit(&format!("loadv r1, {}", (*operator == "&&") as u32));
+                self.emit(&format!("jmp {}", end));
+                self.label(&short);
+                self.emit(&format!("loadv r1, {}", (*operator == "||") as u32));
+                self.label(&end);
+            }
+            Expr::Binary(operator, left, right) => {
+                self.expr(left)?;
+                self.emit("push r1");
+                self.expr(right)?;
+                self.emit("mov r2, r1");
+                self.emit("pop r1");
+                self.operator(operator);
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Function: `operator(&mut self, operator: &str)`
+    ///
+    ///This function generates `r1 := r1 operator r2`.
+    fn operator(&mut self, operator: &str) {
+        let swap = ["mov r7, r1", "mov r1, r2", "mov r2, r7"];
+        let negate = ["loadv r2, 1", "cmov r2, r0, r1", "mov r1, r2"];
+
+        let code: Vec<&str> = match operator {
+            "+" => vec!["add r1, r1, r2"],
+            "-" => vec!["sub r1, r1, r2"],
+            "*" => vec!["mul r1, r1, r2"],
+            "/" => vec!["call __sdiv"],
+            "%" => vec!["call __smod"],
+            "&" => vec!["and r1, r1, r2"],
+            "|" => vec!["or r1, r1, r2"],
+            "^" => vec!["nand r7, r1, r2", "nand r6, r1, r7", "nand r1, r2, r7", "nand r1, r6, r1"],
+            "==" => vec!["sub r1, r1, r2", "loadv r2, 1", "cmov r2, r0, r1", "mov r1, r2"],
+            "!=" => vec!["sub r1, r1, r2", "loadv r2, 1", "cmov r1, r2, r1"],
+            "<" => vec!["call __slt"],
+            ">" => [&swap[..], &["call __slt"]].concat(),
+            "<=" => [&swap[..], &["call __slt"], &negate[..]].concat(),
+            ">=" => [&["call __slt"], &negate[..]].concat(),
+            _ => unreachable!("the parser only builds known operators"),
+        };
+
+        for line in code {
+            self.emit(line);
+        }
+    }
+
+    ///Function: `call(&mut self, name: &str, arguments: &[Expr], line: usize) -> Result<(), CompileError>`
+    ///
+    ///This function generates a call to a built-in or user function.
+    fn call(&mut self, name: &str, arguments: &[Expr], line: usize) -> Result<(), CompileError> {
+        let expected = match name {
+            "getc" => 0,
+            "putc" | "array" | "free" => 1,
+            _ => *self.functions.get(name).ok_or_else(|| CompileError { line, message: format!("unknown function `{}`", name) })?,
+        };
+
+        if arguments.len() != expected {
+            return Err(CompileError {
+                line,
+                message: format!("`{}` takes {} argument(s), {} given", name, expected, arguments.len()),
+            });
+        }
+
+        match name {
+            "getc" => self.emit("in r1"),
+            "putc" => {
+                self.expr(&arguments[0])?;
+                self.emit("out r1");
+            }
+            "array" => {
+                self.expr(&arguments[0])?;
+                self.emit("map r1, r1");
+            }
+            "free" => {
+                self.expr(&arguments[0])?;
+                self.emit("unmap r1");
+                self.emit("loadv r1, 0");
+            }
+            _ => {
+                for argument in arguments {
+                    self.expr(argument)?;
+                    self.emit("push r1");
+                }
+
+                self.emit(&format!("call f.{}", name));
+
+                if !arguments.is_empty() {
+                    self.emit(&format!("li32 r7, {}", (arguments.len() as u32).wrapping_neg()));
+                    self.emit("add r5, r5, r7");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///Function: `is_builtin(name: &str) -> bool`
+///
+///This function tells whether `name` is a built-in function.
+fn is_builtin(name: &str) -> bool {
+    matches!(name, "putc" | "getc" | "array" | "free")
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
@@ -0,0 +1,634 @@
+//! Concolic execution: finding inputs that reach an offset of segment 0.
+//!
+//! A program is run on a concrete input while every value computed from the
+//! bytes read by `Input` is also kept as a 32-bit bit-vector expression over
+//! those bytes (`Add`, `Mul`, `Div` and `Nand` of them, `CMov` choosing
+//! between them). Each `CMov` whose condition depends on the input is a
+//! branch, and the run records which way it went as a path constraint.
+//! Addresses, segment sizes and jump targets depending on the input are pinned
+//! to their concrete value.
+//!
+//! The search negates the branches of each run one at a time (a generational
+//! search), asks the solver for an input taking the other way while keeping
+//! the branches before it, and runs the program on that input, until one
+//! reaches the target. The solver is a small finite-domain bit-vector solver:
+//! the input bytes the negated branch depends on (directly or through earlier
+//! branches) are searched by backtracking, checking each constraint as soon as
+//! its bytes are chosen, while the other bytes keep their value from the run,
+//! which already satisfies every constraint they appear in. Each byte is a
+//! value from 0 to 255 or the end of the input, which `Input` reads as
+//! `u32::MAX`. An equality whose last byte appears once, under additions,
+//! multiplications by odd numbers and negations, is solved for that byte by
+//! undoing them instead. A query running out of budget is abandoned, and the
+//! report then does not claim that every path was explored, nor does it when
+//! a run stopped at the step limit.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use crate::um_instruction::{Instruction, Opcode};
+
+//The value of an input byte past the end of the input
+const EOF: u16 = 256;
+
+//The number of constraint evaluations the solver may spend on one query
+const SOLVER_BUDGET: usize = 1 << 20;
+
+#[derive(Debug, PartialEq, Eq)]
+///Enum Expr
+///
+///A 32-bit bit-vector expression over the input bytes.
+pub enum Expr {
+    Const(u32),
+    ///The `n`th byte read by `Input`, or `u32::MAX` past the end of the input.
+    Input(usize),
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    ///Unsigned division, 0 when dividing by 0.
+    Div(Rc<Expr>, Rc<Expr>),
+    Nand(Rc<Expr>, Rc<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Constraint
+///
+///A path constraint: `expr` is not 0 when `nonzero` holds, and 0 otherwise.
+///Only `branch` constraints, taken by a `CMov`, are negated by the search;
+///the others pin an address or ensure a divisor is not 0.
+pub struct Constraint {
+    pub expr: Rc<Expr>,
+    pub nonzero: bool,
+    pub branch: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Search
+///
+///The parameters of a search: the `target` offset of segment 0, the number of
+///`runs` of the program it may spend, the instructions each run may execute,
+///the number of input bytes treated as symbolic, how many inputs to find and
+///the input of the first run.
+pub struct Search {
+    pub target: usize,
+    pub runs: usize,
+    pub steps: u64,
+    pub max_input: usize,
+    pub solutions: usize,
+    pub seed: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Report
+///
+///The inputs found to reach the target, the number of runs spent, the number
+///of branches the solver gave up on, the number of runs stopped by the step
+///limit, and whether every path the search could reach was explored.
+pub struct Report {
+    pub found: Vec<Vec<u8>>,
+    pub runs: usize,
+    pub abandoned: usize,
+    pub truncated: usize,
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Unsolved
+///
+///Why `solve` found no input.
+pub enum Unsolved {
+    ///No input satisfies the constraints.
+    Unsatisfiable,
+    ///The solver ran out of budget before finding one.
+    OutOfBudget,
+}
+
+#[derive(Debug, Clone)]
+///Structure: Value
+///
+///A register or segment word: its concrete value, and its expression when it
+///depends on the input.
+struct Value {
+    concrete: u32,
+    symbolic: Option<Rc<Expr>>,
+}
+
+///Structure: Run
+///
+///A concrete run of the program on `input`, tracking expressions.
+struct Run<'a> {
+    registers: [Value; 8],
+    segments: HashMap<u32, Vec<u32>>,
+    shadows: HashMap<u32, HashMap<u32, Rc<Expr>>>,
+    free: Vec<u32>,
+    next_id: u32,
+    pc: u32,
+    input: &'a [u8],
+    reads: usize,
+    max_input: usize,
+    constraints: Vec<Constraint>,
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        Search { target: 0, runs: 1000, steps: 10_000_000, max_input: 256, solutions: 1, seed: Vec::new() }
+    }
+}
+
+///Function: `search(program: &[u32], options: &Search) -> Report`
+///
+///This function looks for inputs making `program` execute the instruction at
+///offset `options.target` of segment 0.
+pub fn search(program: &[u32], options: &Search) -> Report {
+    let mut queue = VecDeque::from([(options.seed.clone(), 0)]);
+    let mut seen = HashSet::from([options.seed.clone()]);
+    let mut report = Report { found: Vec::new(), runs: 0, abandoned: 0, truncated: 0, exhausted: false };
+
+    while report.found.len() < options.solutions {
+        let (input, bound) = match queue.pop_front() {
+            Some(candidate) if report.runs < options.runs => candidate,
+            Some(_) => return report,
+            None => {
+                report.exhausted = report.abandoned == 0 && report.truncated == 0;
+                return report;
+            }
+        };
+
+        let mut run = Run::new(program, &input, options.max_input);
+        let reached = run.execute(options.target, options.steps);
+
+        report.runs += 1;
+
+        if reached.is_none() {
+            report.truncated += 1;
+        }
+
+        if reached == Some(true) {
+            report.found.push(input[..run.reads.min(input.len())].to_vec());
+            continue;
+        }
+
+        let current: Vec<u16> = (0..run.reads).map(|index| input.get(index).map_or(EOF, |byte| *byte as u16)).collect();
+        let vars: Vec<BTreeSet<usize>> = run.constraints.iter().map(|constraint| variables(&constraint.expr)).collect();
+        let mut negated = HashSet::new();
+
+        for index in bound..run.constraints.len() {
+            let constraint = &run.constraints[index];
+
+            if !constraint.branch || !negated.insert((Rc::as_ptr(&constraint.expr), constraint.nonzero)) {
+                continue;
+            }
+
+            let goal = Constraint { expr: constraint.expr.clone(), nonzero: !constraint.nonzero, branch: true };
+
+            match solve(&run.constraints[..index], &vars[..index], &goal, &vars[index], &current) {
+                Ok(values) => {
+                    let next: Vec<u8> = values.iter().take_while(|value| **value != EOF).map(|value| *value as u8).collect();
+
+                    if seen.insert(next.clone()) {
+                        queue.push_back((next, index + 1));
+                    }
+                }
+                Err(Unsolved::OutOfBudget) => report.abandoned += 1,
+                Err(Unsolved::Unsatisfiable) => {}
+            }
+        }
+    }
+
+    report
+}
+
+impl Run<'_> {
+
+    ///Function: `new<'a>(program: &[u32], input: &'a [u8], max_input: usize) -> Run<'a>`
+    ///
+    ///This function sets up a run of `program` reading `input`.
+    fn new<'a>(program: &[u32], input: &'a [u8], max_input: usize) -> Run<'a> {
+        Run {
+            registers: std::array::from_fn(|_| Value { concrete: 0, symbolic: None }),
+            segments: HashMap::from([(0, program.to_vec())]),
+            shadows: HashMap::new(),
+            free: Vec::new(),
+            next_id: 1,
+            pc: 0,
+            input,
+            reads: 0,
+            max_input,
+            constraints: Vec::new(),
+        }
+    }
+
+    ///Function: `execute(&mut self, target: usize, steps: u64) -> Option<bool>`
+    ///
+    ///This function runs the program until it reaches `target`, stops or
+    ///executed `steps` instructions, and tells whether it reached `target`,
+    ///or `None` when it ran out of steps first.
+    fn execute(&mut self, target: usize, steps: u64) -> Option<bool> {
+        for _ in 0..steps {
+            if self.pc as usize == target {
+                return Some(true);
+            }
+
+            if self.step().is_none() {
+                return Some(false);
+            }
+        }
+
+        None
+    }
+
+    ///Function: `step(&mut self) -> Option<()>`
+    ///
+    ///This function executes the instruction at `pc`, returning `None` when
+    ///the machine halts or faults.
+    fn step(&mut self) -> Option<()> {
+        let instruction = Instruction::new(*self.segments[&0].get(self.pc as usize)?);
+        let (a, b, c) = (instruction.a as usize, instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+
+        self.pc += 1;
+
+        match instruction.opcode {
+            Opcode::CMov => {
+                if let Some(condition) = &self.registers[c].symbolic {
+                    let nonzero = self.registers[c].concrete != 0;
+
+                    self.constraints.push(Constraint { expr: condition.clone(), nonzero, branch: true });
+                }
+
+                if self.registers[c].concrete != 0 {
+                    self.registers[a] = self.registers[b].clone();
+                }
+            }
+            Opcode::Load => {
+                let (id, offset) = (self.pin(b), self.pin(c));
+                let concrete = *self.segments.get(&id)?.get(offset as usize)?;
+                let symbolic = self.shadows.get(&id).and_then(|shadow| shadow.get(&offset)).cloned();
+
+                self.registers[a] = Value { concrete, symbolic };
+            }
+            Opcode::Store => {
+                let (id, offset) = (self.pin(a), self.pin(b));
+                let value = self.registers[c].clone();
+
+                *self.segments.get_mut(&id)?.get_mut(offset as usize)? = value.concrete;
+
+                match value.symbolic {
+                    Some(expr) => {
+                        self.shadows.entry(id).or_default().insert(offset, expr);
+                    }
+                    None => {
+                        self.shadows.get_mut(&id).map(|shadow| shadow.remove(&offset));
+                    }
+                }
+            }
+            Opcode::Add => self.binary(a, b, c, |x, y| Some(x.wrapping_add(y)), Expr::Add)?,
+            Opcode::Mul => self.binary(a, b, c, |x, y| Some(x.wrapping_mul(y)), Expr::Mul)?,
+            Opcode::Div => {
+                if let Some(divisor) = &self.registers[c].symbolic {
+                    self.constraints.push(Constraint { expr: divisor.clone(), nonzero: true, branch: false });
+                }
+
+                self.binary(a, b, c, |x, y| x.checked_div(y), Expr::Div)?
+            }
+            Opcode::Nand => self.binary(a, b, c, |x, y| Some(!(x & y)), Expr::Nand)?,
+            Opcode::Halt | Opcode::Err => return None,
+            Opcode::MapSegment => {
+                let size = self.pin(c);
+                let id = self.free.pop().unwrap_or_else(|| {
+                    self.next_id += 1;
+                    self.next_id - 1
+                });
+
+                self.segments.insert(id, vec![0; size as usize]);
+                self.shadows.remove(&id);
+                self.registers[b] = Value { concrete: id, symbolic: None };
+            }
+            Opcode::UnmapSegment => {
+                let id = self.pin(c);
+
+                if id == 0 || self.segments.remove(&id).is_none() {
+                    return None;
+                }
+
+                self.shadows.remove(&id);
+                self.free.push(id);
+            }
+            Opcode::Output if self.registers[c].concrete > 255 => return None,
+            Opcode::Output => {}
+            Opcode::Input => {
+                let value = self.input.get(self.reads).map_or(u32::MAX, |byte| *byte as u32);
+                let symbolic = (self.reads < self.max_input).then(|| Rc::new(Expr::Input(self.reads)));
+
+                self.reads += 1;
+                self.registers[c] = Value { concrete: value, symbolic };
+            }
+            Opcode::LoadProgram => {
+                let (id, target) = (self.pin(b), self.pin(c));
+
+                if id != 0 {
+                    let segment = self.segments.get(&id)?.clone();
+                    let shadow = self.shadows.get(&id).cloned();
+
+                    self.segments.insert(0, segment);
+
+                    match shadow {
+                        Some(shadow) => self.shadows.insert(0, shadow),
+                        None => self.shadows.remove(&0),
+                    };
+                }
+
+                self.pc = target;
+            }
+            Opcode::LoadValue => self.registers[a] = Value { concrete: instruction.value.unwrap(), symbolic: None },
+        }
+
+        Some(())
+    }
+
+    ///Function: `binary(&mut self, a: usize, b: usize, c: usize, concrete: impl Fn(u32, u32) -> Option<u32>, symbolic: fn(Rc<Expr>, Rc<Expr>) -> Expr) -> Option<()>`
+    ///
+    ///This function computes `r[a] := r[b] op r[c]`, both concretely and as an
+    ///expression when an operand depends on the input.
+    fn binary(
+        &mut self,
+        a: usize,
+        b: usize,
+        c: usize,
+        concrete: impl Fn(u32, u32) -> Option<u32>,
+        symbolic: fn(Rc<Expr>, Rc<Expr>) -> Expr,
+    ) -> Option<()> {
+        let (left, right) = (&self.registers[b], &self.registers[c]);
+        let value = concrete(left.concrete, right.concrete)?;
+        let expr = match (&left.symbolic, &right.symbolic) {
+            (None, None) => None,
+            _ => Some(Rc::new(symbolic(expression(left), expression(right)))),
+        };
+
+        self.registers[a] = Value { concrete: value, symbolic: expr };
+        Some(())
+    }
+
+    ///Function: `pin(&mut self, register: usize) -> u32`
+    ///
+    ///This function returns the concrete value of a register, constraining
+    ///the path to that value when it depends on the input.
+    fn pin(&mut self, register: usize) -> u32 {
+        let value = self.registers[register].concrete;
+
+        if let Some(expr) = &self.registers[register].symbolic {
+            let difference = Expr::Add(expr.clone(), Rc::new(Expr::Const(value.wrapping_neg())));
+
+            self.constraints.push(Constraint { expr: Rc::new(difference), nonzero: false, branch: false });
+        }
+
+        value
+    }
+}
+
+///Function: `expression(value: &Value) -> Rc<Expr>`
+///
+///This helper function returns the expression of a value, a constant when it
+///does not depend on the input.
+fn expression(value: &Value) -> Rc<Expr> {
+    value.symbolic.clone().unwrap_or_else(|| Rc::new(Expr::Const(value.concrete)))
+}
+
+///Function: `variables(expr: &Rc<Expr>) -> BTreeSet<usize>`
+///
+///This function returns the input bytes an expression depends on.
+pub fn variables(expr: &Rc<Expr>) -> BTreeSet<usize> {
+    let mut found = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut work = vec![expr];
+
+    while let Some(expr) = work.pop() {
+        if !visited.insert(Rc::as_ptr(expr)) {
+            continue;
+        }
+
+        match &**expr {
+            Expr::Const(_) => {}
+            Expr::Input(index) => {
+                found.insert(*index);
+            }
+            Expr::Add(x, y) | Expr::Mul(x, y) | Expr::Div(x, y) | Expr::Nand(x, y) => work.extend([x, y]),
+        }
+    }
+
+    found
+}
+
+///Function: `evaluate(expr: &Rc<Expr>, values: &[u16]) -> u32`
+///
+///This function evaluates an expression with the input bytes `values`.
+pub fn evaluate(expr: &Rc<Expr>, values: &[u16]) -> u32 {
+    evaluate_shared(expr, values, &mut HashMap::new())
+}
+
+///Function: `evaluate_shared(expr: &Rc<Expr>, values: &[u16], memo: &mut HashMap<*const Expr, u32>) -> u32`
+///
+///This helper function evaluates an expression, evaluating each shared
+///subexpression once.
+fn evaluate_shared(expr: &Rc<Expr>, values: &[u16], memo: &mut HashMap<*const Expr, u32>) -> u32 {
+    if let Some(value) = memo.get(&Rc::as_ptr(expr)) {
+        return *value;
+    }
+
+    let mut operands = |x: &Rc<Expr>, y: &Rc<Expr>| (evaluate_shared(x, values, memo), evaluate_shared(y, values, memo));
+    let value = match &**expr {
+        Expr::Const(value) => *value,
+        Expr::Input(index) => match values.get(*index).copied().unwrap_or(EOF) {
+            EOF => u32::MAX,
+            byte => byte as u32,
+        },
+        Expr::Add(x, y) => {
+            let (x, y) = operands(x, y);
+            x.wrapping_add(y)
+        }
+        Expr::Mul(x, y) => {
+            let (x, y) = operands(x, y);
+            x.wrapping_mul(y)
+        }
+        Expr::Div(x, y) => {
+            let (x, y) = operands(x, y);
+            x.checked_div(y).unwrap_or(0)
+        }
+        Expr::Nand(x, y) => {
+            let (x, y) = operands(x, y);
+            !(x & y)
+        }
+    };
+
+    memo.insert(Rc::as_ptr(expr), value);
+    value
+}
+
+///Function: `solve(prefix: &[Constraint], prefix_vars: &[BTreeSet<usize>], goal: &Constraint, goal_vars: &BTreeSet<usize>, current: &[u16]) -> Result<Vec<u16>, Unsolved>`
+///
+///This function looks for input bytes satisfying `goal` and every `prefix`
+///constraint, changing only the bytes `goal` depends on directly or through
+///the `prefix` constraints sharing bytes with it. `current` satisfies every
+///`prefix` constraint. When the last byte chosen for an equality occurs once
+///in it, under additions, multiplications by odd numbers and negations, its
+///value is computed by inverting them instead of being searched.
+pub fn solve(
+    prefix: &[Constraint],
+    prefix_vars: &[BTreeSet<usize>],
+    goal: &Constraint,
+    goal_vars: &BTreeSet<usize>,
+    current: &[u16],
+) -> Result<Vec<u16>, Unsolved> {
+    let mut component = goal_vars.clone();
+    let mut relevant = vec![false; prefix.len()];
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (index, vars) in prefix_vars.iter().enumerate() {
+            if !relevant[index] && !vars.is_disjoint(&component) {
+                relevant[index] = true;
+                component.extend(vars);
+                changed = true;
+            }
+        }
+    }
+
+    //Each constraint is checked once its last byte is chosen
+    let order: Vec<usize> = component.iter().copied().collect();
+    let mut checks: Vec<Vec<(&Constraint, &BTreeSet<usize>)>> = vec![Vec::new(); order.len()];
+    let constraints = prefix.iter().zip(prefix_vars).zip(relevant).filter(|(_, relevant)| *relevant).map(|(pair, _)| pair);
+
+    for (constraint, vars) in constraints.chain([(goal, goal_vars)]) {
+        match vars.iter().max() {
+            Some(last) => checks[order.binary_search(last).unwrap()].push((constraint, vars)),
+            None if (evaluate(&constraint.expr, current) != 0) != constraint.nonzero => return Err(Unsolved::Unsatisfiable),
+            None => {}
+        }
+    }
+
+    let length = current.len().max(order.last().map_or(0, |last| last + 1));
+    let mut values: Vec<u16> = (0..length).map(|index| current.get(index).copied().unwrap_or(EOF)).collect();
+    let mut budget = SOLVER_BUDGET;
+
+    match backtrack(&order, &checks, 0, &mut values, &mut budget) {
+        true => Ok(values),
+        false if budget == 0 => Err(Unsolved::OutOfBudget),
+        false => Err(Unsolved::Unsatisfiable),
+    }
+}
+
+///Function: `backtrack(order: &[usize], checks: &[Vec<(&Constraint, &BTreeSet<usize>)>], position: usize, values: &mut Vec<u16>, budget: &mut usize) -> bool`
+///
+///This helper function chooses a value for the byte `order[position]` and
+///the ones after it, keeping the end of the input a suffix of the bytes.
+fn backtrack(
+    order: &[usize],
+    checks: &[Vec<(&Constraint, &BTreeSet<usize>)>],
+    position: usize,
+    values: &mut Vec<u16>,
+    budget: &mut usize,
+) -> bool {
+    let Some(&var) = order.get(position) else {
+        return true;
+    };
+    let first = values[var];
+    //The value an equality forces, or the current value first, then
+    //printable characters
+    let forced = checks[position]
+        .iter()
+        .filter(|(constraint, _)| !constraint.nonzero)
+        .find_map(|(constraint, _)| invert(&constraint.expr, var, 0, values));
+    let candidates: Vec<u16> = match forced {
+        Some(0..=255) => vec![forced.unwrap() as u16],
+        Some(u32::MAX) => vec![EOF],
+        Some(_) => Vec::new(),
+        None => [first].into_iter().chain((32..127).chain(0..32).chain(127..=EOF).filter(|value| *value != first)).collect(),
+    };
+
+    for value in candidates {
+        //The bytes before `var` are chosen or fixed, and so are the ones
+        //after it outside `order`
+        let consistent = match value {
+            EOF => (var + 1..values.len()).all(|other| values[other] == EOF || order.binary_search(&other).is_ok()),
+            _ => values[..var].iter().all(|other| *other != EOF),
+        };
+
+        if !consistent {
+            continue;
+        }
+
+        values[var] = value;
+
+        let satisfied = checks[position].iter().all(|(constraint, _)| {
+            *budget = budget.saturating_sub(1);
+            (evaluate(&constraint.expr, values) != 0) == constraint.nonzero
+        });
+
+        if *budget == 0 {
+            values[var] = first;
+            return false;
+        }
+
+        if satisfied && backtrack(order, checks, position + 1, values, budget) {
+            return true;
+        }
+    }
+
+    values[var] = first;
+    false
+}
+
+///Function: `invert(expr: &Rc<Expr>, var: usize, target: u32, values: &[u16]) -> Option<u32>`
+///
+///This helper function returns the only value of input byte `var` making
+///`expr` equal `target` with the other bytes of `values`, when `var` occurs
+///once in `expr` under operations that can be undone.
+fn invert(expr: &Rc<Expr>, var: usize, target: u32, values: &[u16]) -> Option<u32> {
+    let side = |x: &Rc<Expr>, y: &Rc<Expr>| match (variables(x).contains(&var), variables(y).contains(&var)) {
+        (true, false) => Some((x.clone(), evaluate(y, values))),
+        (false, true) => Some((y.clone(), evaluate(x, values))),
+        _ => None,
+    };
+
+    match &**expr {
+        Expr::Input(index) if *index == var => Some(target),
+        Expr::Add(x, y) => {
+            let (inner, other) = side(x, y)?;
+            invert(&inner, var, target.wrapping_sub(other), values)
+        }
+        Expr::Mul(x, y) => {
+            let (inner, other) = side(x, y)?;
+            //The inverse of an odd number modulo 2^32, by Newton's method
+            let inverse = (0..5).fold(other, |inverse, _| inverse.wrapping_mul(2u32.wrapping_sub(other.wrapping_mul(inverse))));
+
+            (other % 2 == 1).then_some(())?;
+            invert(&inner, var, target.wrapping_mul(inverse), values)
+        }
+        Expr::Nand(x, y) if Rc::ptr_eq(x, y) => invert(x, var, !target, values),
+        Expr::Nand(x, y) => {
+            let (inner, other) = side(x, y)?;
+
+            (other == u32::MAX).then_some(())?;
+            invert(&inner, var, !target, values)
+        }
+        _ => None,
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for input in &self.found {
+            writeln!(f, "\"{}\"", input.escape_ascii())?;
+        }
+
+        match (self.found.is_empty(), self.exhausted) {
+            (true, true) => write!(f, "no input reaches the target ({} runs, every path explored)", self.runs),
+            (true, false) => write!(
+                f,
+                "no input found reaching the target in {} runs ({} branches abandoned by the solver, {} runs stopped by the step limit)",
+                self.runs, self.abandoned, self.truncated
+            ),
+            (false, _) => write!(f, "{} input(s) found in {} runs", self.found.len(), self.runs),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use crate::disassembler;
+use crate::monitor::Monitor;
+use crate::rum::Rum;
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, Opcode};
+
+#[derive(Debug, Clone)]
+///Structure: Image
+///
+///A distinct program that ran in segment 0: its `words` as they were when it
+///was loaded, how many times it was `loads`ed and how many times each offset
+///was executed (`hits`).
+pub struct Image {
+    pub words: Vec<u32>,
+    pub loads: u64,
+    pub hits: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+///Structure: Coverage
+///
+///A `Monitor` that counts how many times each offset of segment 0 executed.
+///Image 0 is the program the machine started with. Every `LoadProgram` that
+///copies another segment over segment 0 switches to the image with the same
+///contents, creating a new one the first time such contents are loaded.
+pub struct Coverage {
+    images: Vec<Image>,
+    by_hash: HashMap<u64, Vec<usize>>,
+    current: usize,
+}
+
+///Function: `hash_words(words: &[u32]) -> u64`
+///
+///This helper function hashes the contents of a segment.
+fn hash_words(words: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    words.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Coverage {
+
+    ///Function: `new(program: &[u32]) -> Coverage`
+    ///
+    ///This function starts tracking coverage for a machine started with `program`.
+    pub fn new(program: &[u32]) -> Coverage {
+        let mut coverage = Coverage { images: Vec::new(), by_hash: HashMap::new(), current: 0 };
+
+        coverage.current = coverage.image_for(program);
+
+        coverage
+    }
+
+    ///Function: `images(&self) -> &[Image]`
+    ///
+    ///This getter function returns every image seen so far, in load order.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    ///Function: `image_for(&mut self, words: &[u32]) -> usize`
+    ///
+    ///This helper function finds the image with the given contents, creating
+    ///it when it was never loaded, and counts one more load of it.
+    fn image_for(&mut self, words: &[u32]) -> usize {
+        let hash = hash_words(words);
+        let candidates = self.by_hash.entry(hash).or_default();
+
+        let index = match candidates.iter().find(|index| self.images[**index].words == words) {
+            Some(index) => *index,
+            None => {
+                candidates.push(self.images.len());
+                self.images.push(Image { words: words.to_vec(), loads: 0, hits: vec![0; words.len()] });
+                self.images.len() - 1
+            }
+        };
+
+        self.images[index].loads += 1;
+
+        index
+    }
+
+    ///Function: `listing(&self, symbols: Option<&SymbolMap>) -> String`
+    ///
+    ///This function exports the coverage as an annotated disassembly of
+    ///every image. Each line starts with its execution count, or `#####` when
+    ///it never executed. `symbols` only describes the initial program, so it
+    ///is only used for image 0.
+    pub fn listing(&self, symbols: Option<&SymbolMap>) -> String {
+        let mut text = String::new();
+
+        for (number, image) in self.images.iter().enumerate() {
+            let executed = image.hits.iter().filter(|hits| **hits > 0).count();
+
+            text.push_str(&format!(
+                "image {}: {} words, loaded {} time(s), {}/{} offsets executed\n",
+                number, image.words.len(), image.loads, executed, image.words.len()
+            ));
+
+            let symbols = if number == 0 { symbols } else { None };
+
+            for (offset, word) in image.words.iter().enumerate() {
+                let count = match image.hits[offset] {
+                    0 => "#####".to_string(),
+                    hits => hits.to_string(),
+                };
+
+                for line in disassembler::annotate_line(offset as u32, *word, symbols).lines() {
+                    if line.ends_with(':') {
+                        text.push_str(&format!("{:>12}   {}\n", "", line));
+                    } else {
+                        text.push_str(&format!("{:>12} | {}\n", count, line));
+                    }
+                }
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+
+    ///Function: `profile(&self, top: usize, symbols: Option<&SymbolMap>) -> String`
+    ///
+    ///This function reports where the time went: the number of instructions
+    ///executed, then the `top` most executed offsets of every image with their
+    ///share of the total, hottest first. `symbols` is only used for image 0.
+    pub fn profile(&self, top: usize, symbols: Option<&SymbolMap>) -> String {
+        let total: u64 = self.images.iter().flat_map(|image| image.hits.iter()).sum();
+        let mut hottest: Vec<(u64, usize, usize)> = self.images.iter()
+            .enumerate()
+            .flat_map(|(number, image)| image.hits.iter().enumerate().map(move |(offset, hits)| (*hits, number, offset)))
+            .filter(|(hits, _, _)| *hits > 0)
+            .collect();
+
+        hottest.sort_by(|first, second| second.0.cmp(&first.0).then((first.1, first.2).cmp(&(second.1, second.2))));
+
+        let mut text = format!("{} instructions executed in {} image(s)\n", total, self.images.len());
+
+        for (hits, number, offset) in hottest.into_iter().take(top) {
+            let symbols = if number == 0 { symbols } else { None };
+            let labels: Vec<&str> = symbols.map(|symbols| symbols.labels_at(offset as u32).collect()).unwrap_or_default();
+            let location = match labels.is_empty() {
+                true => format!("{}:{}", number, offset),
+                false => format!("{}:{} <{}>", number, offset, labels.join(", ")),
+            };
+
+            text.push_str(&format!(
+                "{:>14} {:>6.2}%  {:<24} {}\n",
+                hits,
+                hits as f64 * 100.0 / total as f64,
+                location,
+                disassembler::disassemble(self.images[number].words[offset])
+            ));
+        }
+
+        text
+    }
+
+    ///Function: `lcov(&self, name: &str, symbols: Option<&SymbolMap>) -> String`
+    ///
+    ///This function exports the coverage in the lcov tracefile format. With
+    ///`symbols`, the counts of image 0 are reported against the assembler
+    ///source lines (a line counts as often as its most executed word).
+    ///Otherwise, and for every other image, each offset is reported as line
+    ///`offset + 1` of a pseudo source file named after `name` and the image.
+    pub fn lcov(&self, name: &str, symbols: Option<&SymbolMap>) -> String {
+        let mut files: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+
+        for (number, image) in self.images.iter().enumerate() {
+            let pseudo = format!("{}#image{}", name, number);
+
+            for (offset, hits) in image.hits.iter().enumerate() {
+                let source = symbols.filter(|_| number == 0).and_then(|symbols| symbols.line(offset as u32));
+                let (file, line) = match source {
+                    Some(source) => (source.file.clone(), source.line),
+                    None => (pseudo.clone(), offset as u32 + 1),
+                };
+                let count = files.entry(file).or_default().entry(line).or_insert(0);
+
+                *count = (*count).max(*hits);
+            }
+        }
+
+        let mut text = String::from("TN:\n");
+
+        for (file, lines) in files {
+            text.push_str(&format!("SF:{}\n", file));
+
+            for (line, hits) in &lines {
+                text.push_str(&format!("DA:{},{}\n", line, hits));
+            }
+
+            text.push_str(&format!("LF:{}\n", lines.len()));
+            text.push_str(&format!("LH:{}\n", lines.values().filter(|hits| **hits > 0).count()));
+            text.push_str("end_of_record\n");
+        }
+
+        text
+    }
+}
+
+impl Monitor for Coverage {
+    fn before_step(&mut self, _machine: &Rum, pc: usize, _instruction: Instruction) {
+        if let Some(hits) = self.images[self.current].hits.get_mut(pc) {
+            *hits += 1;
+        }
+    }
+
+    fn after_step(&mut self, machine: &Rum, _pc: usize, instruction: Instruction) {
+        if instruction.opcode == Opcode::LoadProgram && machine.registers()[instruction.b.unwrap() as usize] != 0 {
+            let program = machine.segments().segment_words(0).unwrap();
+
+            self.current = self.image_for(&program);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::checkpoint::{CheckpointConfig, CheckpointStore};
+use crate::fault::Fault;
+use crate::rum::{Rum, State};
+use crate::um_io::UmIo;
+use crate::watchpoint::{self, WatchHit, Watchpoint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Stop
+///
+///The `enum` tells why the debugger handed control back to the caller.
+pub enum Stop {
+    ///A single instruction was executed (or undone).
+    Step,
+    ///The machine reached an instruction that has a breakpoint.
+    Breakpoint(usize),
+    ///An instruction triggered a watchpoint. Going forward the machine stops
+    ///right after that instruction, going backwards right before it.
+    Watchpoint(WatchHit),
+    ///The machine executed `Halt`.
+    Halted,
+    ///The machine faulted; `pc` still points at the faulting instruction.
+    Faulted(Fault),
+    ///Reverse execution went all the way back to the initial machine.
+    StartOfHistory,
+    ///Going backwards failed, leaving the machine wherever re-execution stopped.
+    Replay(ReplayError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum ReplayError
+///
+///The reasons the debugger cannot rebuild an earlier state.
+pub enum ReplayError {
+    ///The step asked for was never reached.
+    PastFrontier { step: u64, frontier: u64 },
+    ///Re-executing the instruction after `step` did not run on as it did the
+    ///first time, ending with `outcome` instead.
+    Diverged { step: u64, outcome: Result<State, Fault> },
+}
+
+///Structure: Debugger
+///
+///The structure wraps a `Rum` machine and remembers enough of its history to
+///run it backwards. Every byte handed to the `Input` opcode is recorded and
+///the machine is checkpointed periodically, so any earlier step can be
+///rebuilt by restoring the nearest checkpoint and re-executing from there with
+///the recorded input. Output is only forwarded to `io` the first time an
+///instruction runs, never while re-executing.
+pub struct Debugger {
+    machine: Rum,
+    io: Box<dyn UmIo>,
+    steps: u64,
+    frontier: u64,
+    input_log: Vec<Option<u8>>,
+    input_position: usize,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, Watchpoint>,
+    next_watchpoint: usize,
+    hit: Option<WatchHit>,
+    checkpoints: CheckpointStore,
+    stopped: Option<Stop>,
+}
+
+///Structure: DebugIo
+///
+///The I/O handed to the machine while debugging. Input comes from the
+///recorded log when re-executing and from the real `io` otherwise.
+struct DebugIo<'a> {
+    real: &'a mut dyn UmIo,
+    log: &'a mut Vec<Option<u8>>,
+    position: &'a mut usize,
+    echo: bool,
+}
+
+impl UmIo for DebugIo<'_> {
+    fn input(&mut self) -> Option<u8> {
+        if *self.position == self.log.len() {
+            let value = self.real.input();
+            self.log.push(value);
+        }
+
+        let value = self.log[*self.position];
+        *self.position += 1;
+
+        value
+    }
+
+    fn output(&mut self, value: u8) {
+        if self.echo {
+            self.real.output(value);
+        }
+    }
+}
+
+impl Debugger {
+
+    ///Function: `new(machine: Rum, io: Box<dyn UmIo>, config: CheckpointConfig) -> Debugger`
+    ///
+    ///This function starts debugging `machine`, which will talk to `io`
+    ///and be checkpointed according to `config`.
+    pub fn new(machine: Rum, io: Box<dyn UmIo>, config: CheckpointConfig) -> Debugger
+    {
+        Debugger {
+            checkpoints: CheckpointStore::new(config, &machine),
+            machine,
+            io,
+            steps: 0,
+            frontier: 0,
+            input_log: Vec::new(),
+            input_position: 0,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            next_watchpoint: 0,
+            hit: None,
+            stopped: None,
+        }
+    }
+
+    ///Function: `machine(&self) -> &Rum`
+    ///
+    ///This getter function returns the machine being debugged.
+    pub fn machine(&self) -> &Rum
+    {
+        &self.machine
+    }
+
+    ///Function: `steps(&self) -> u64`
+    ///
+    ///This getter function returns how many instructions were executed to
+    ///reach the current state.
+    pub fn steps(&self) -> u64
+    {
+        self.steps
+    }
+
+    ///Function: `checkpoints(&self) -> &CheckpointStore`
+    ///
+    ///This getter function returns the checkpoints taken so far.
+    pub fn checkpoints(&self) -> &CheckpointStore
+    {
+        &self.checkpoints
+    }
+
+    ///Function: `add_breakpoint(&mut self, pc: usize)`
+    ///
+    ///This function stops `continue_execution` and `reverse_continue` whenever
+    ///the machine reaches the instruction at `pc`.
+    pub fn add_breakpoint(&mut self, pc: usize)
+    {
+        self.breakpoints.insert(pc);
+    }
+
+    ///Function: `remove_breakpoint(&mut self, pc: usize) -> bool`
+    ///
+    ///This function removes the breakpoint at `pc` and returns whether there was one.
+    pub fn remove_breakpoint(&mut self, pc: usize) -> bool
+    {
+        self.breakpoints.remove(&pc)
+    }
+
+    ///Function: `add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize`
+    ///
+    ///This function adds `watchpoint` and returns the number identifying it.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize
+    {
+        let number = self.next_watchpoint;
+
+        self.next_watchpoint += 1;
+        self.watchpoints.insert(number, watchpoint);
+
+        number
+    }
+
+    ///Function: `remove_watchpoint(&mut self, number: usize) -> bool`
+    ///
+    ///This function removes the watchpoint `number` and returns whether there was one.
+    pub fn remove_watchpoint(&mut self, number: usize) -> bool
+    {
+        self.watchpoints.remove(&number).is_some()
+    }
+
+    ///Function: `step(&mut self) -> Stop`
+    ///
+    ///This function executes a single instruction.
+    pub fn step(&mut self) -> Stop
+    {
+        if let Some(stop) = self.stopped {
+            return stop;
+        }
+
+        match self.advance() {
+            Ok(State::Running) => match self.hit {
+                Some(hit) => Stop::Watchpoint(hit),
+                None => Stop::Step,
+            },
+            Ok(State::Halted) => self.stop(Stop::Halted),
+            //`DebugIo` waits for its input
+            Ok(State::NeedsInput) => unreachable!("the debugger never suspends on input"),
+            Err(fault) => self.stop(Stop::Faulted(fault)),
+        }
+    }
+
+    ///Function: `continue_execution(&mut self) -> Stop`
+    ///
+    ///This function executes instructions until a breakpoint is reached, a
+    ///watchpoint triggers or the machine halts or faults.
+    pub fn continue_execution(&mut self) -> Stop
+    {
+        loop {
+            let stop = self.step();
+
+            if stop != Stop::Step {
+                return stop;
+            }
+
+            if self.breakpoints.contains(&self.machine.pc()) {
+                return Stop::Breakpoint(self.machine.pc());
+            }
+        }
+    }
+
+    ///Function: `reverse_step(&mut self) -> Stop`
+    ///
+    ///This function undoes the latest instruction, bringing the machine back
+    ///to the state it had right before executing it.
+    pub fn reverse_step(&mut self) -> Stop
+    {
+        if self.steps == 0 {
+            return Stop::StartOfHistory;
+        }
+
+        match self.seek(self.steps - 1) {
+            Ok(()) => Stop::Step,
+            Err(error) => Stop::Replay(error),
+        }
+    }
+
+    ///Function: `reverse_continue(&mut self) -> Stop`
+    ///
+    ///This function runs backwards until the most recent earlier state that
+    ///sits on a breakpoint or is about to trigger a watchpoint, or back to the
+    ///initial machine when there is none.
+    ///Each interval between two checkpoints is re-executed at most once,
+    ///starting with the latest one.
+    pub fn reverse_continue(&mut self) -> Stop
+    {
+        let mut upper = self.steps;
+
+        while let Some(checkpoint) = self.checkpoints.before(upper).cloned() {
+            self.machine = checkpoint.machine;
+            self.steps = checkpoint.step;
+            self.input_position = checkpoint.input_position;
+            self.stopped = None;
+
+            let mut found = None;
+
+            while self.steps < upper {
+                let step = self.steps;
+
+                if self.breakpoints.contains(&self.machine.pc()) {
+                    found = Some((step, Stop::Breakpoint(self.machine.pc())));
+                }
+
+                if let Err(error) = self.replay() {
+                    return Stop::Replay(error);
+                }
+
+                if let Some(hit) = self.hit {
+                    found = Some((step, Stop::Watchpoint(hit)));
+                }
+            }
+
+            if let Some((step, stop)) = found {
+                return match self.seek(step) {
+                    Ok(()) => stop,
+                    Err(error) => Stop::Replay(error),
+                };
+            }
+
+            upper = checkpoint.step;
+        }
+
+        match self.seek(0) {
+            Ok(()) => Stop::StartOfHistory,
+            Err(error) => Stop::Replay(error),
+        }
+    }
+
+    ///Function: `seek(&mut self, step: u64) -> Result<(), ReplayError>`
+    ///
+    ///This function rebuilds the state the machine had after `step`
+    ///instructions, which must not be past the furthest point reached.
+    pub fn seek(&mut self, step: u64) -> Result<(), ReplayError>
+    {
+        if step > self.frontier {
+            return Err(ReplayError::PastFrontier { step, frontier: self.frontier });
+        }
+
+        let checkpoint = self.checkpoints.nearest(step).clone();
+
+        self.machine = checkpoint.machine;
+        self.steps = checkpoint.step;
+        self.input_position = checkpoint.input_position;
+        self.stopped = None;
+
+        while self.steps < step {
+            self.replay()?;
+        }
+
+        Ok(())
+    }
+
+    ///Function: `advance(&mut self) -> Result<State, Fault>`
+    ///
+    ///This helper function executes one instruction through the recording
+    ///I/O and takes a checkpoint when one is due.
+    fn advance(&mut self) -> Result<State, Fault>
+    {
+        let mut io = DebugIo {
+            real: &mut *self.io,
+            log: &mut self.input_log,
+            position: &mut self.input_position,
+            echo: self.steps >= self.frontier,
+        };
+
+        let probe = if self.watchpoints.is_empty() {
+            None
+        } else {
+            watchpoint::probe(&self.watchpoints, &self.machine)
+        };
+
+        self.hit = None;
+
+        let state = self.machine.step(&mut io)?;
+
+        if state == State::Running {
+            self.hit = probe.and_then(|probe| probe.finish(&self.watchpoints, &self.machine));
+            self.steps += 1;
+            self.frontier = self.frontier.max(self.steps);
+            self.checkpoints.record(self.steps, self.input_position, &self.machine);
+        }
+
+        Ok(state)
+    }
+
+    ///Function: `replay(&mut self) -> Result<(), ReplayError>`
+    ///
+    ///This helper function re-executes an instruction that already ran once,
+    ///which must behave exactly as it did the first time.
+    fn replay(&mut self) -> Result<(), ReplayError>
+    {
+        let step = self.steps;
+
+        match self.advance() {
+            Ok(State::Running) => Ok(()),
+            outcome => Err(ReplayError::Diverged { step, outcome }),
+        }
+    }
+
+    ///Function: `stop(&mut self, stop: Stop) -> Stop`
+    ///
+    ///This helper function remembers that the machine cannot go any further.
+    fn stop(&mut self, stop: Stop) -> Stop
+    {
+        self.stopped = Some(stop);
+
+        stop
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::PastFrontier { step, frontier } => write!(f, "step {} was never reached, the furthest is {}", step, frontier),
+            ReplayError::Diverged { step, outcome: Err(fault) } =>
+                write!(f, "re-executing step {} faulted ({}) where it ran the first time", step, fault),
+            ReplayError::Diverged { step, outcome: Ok(state) } =>
+                write!(f, "re-executing step {} ended with {:?} where it ran the first time", step, state),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
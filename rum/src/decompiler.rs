@@ -0,0 +1,1137 @@
+//! Decompilation of segment 0 to pseudo-C.
+//!
+//! Every block found by the control-flow recovery (`cfg::recover`) is lifted
+//! into an SSA-like form: the values its instructions compute are expression
+//! trees over the registers as they were on entering the block, each register
+//! is assigned once, when the block ends, and the values that must be taken
+//! at a given point (loads before a store, input, new segment ids) or grow too
+//! large are kept in temporaries assigned once. Registers whose value is known
+//! on entering a block are replaced by it, so sequences building constants
+//! fold, and the trees are simplified as they are built, turning the `Nand`
+//! idioms back into `~`, `&`, `|`, `^`, negation and subtraction.
+//!
+//! Functions are printed one after the other, their blocks in program order.
+//! A block jumped back to from a later block of the same function starts a
+//! loop (`while`, `do ... while` when it is the last block that jumps back
+//! conditionally), a conditional jump over the blocks that follow becomes an
+//! `if`, or an `if ... else` when those blocks end jumping over more of them,
+//! and every other jump is a `goto`. Registers are only assigned when a later
+//! block may read them. The return offset a call pushes is left out, the call
+//! standing for it, and a jump to an offset loaded from memory is printed as
+//! `return`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use crate::cfg::{self, Block, Cfg};
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, Opcode};
+
+//The largest expression kept in a register before moving it to a temporary
+const MAX_SIZE: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Operator
+///
+///The binary operators of the expressions, with those the `Nand` idioms
+///stand for.
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Xor,
+    Nand,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Expr
+///
+///A value computed by a block, from the registers on entering it.
+pub enum Expr {
+    Const(u32),
+    Register(usize),
+    Temp(usize),
+    ///`seg[id][offset]`
+    Load(Rc<Expr>, Rc<Expr>),
+    Binary(Operator, Rc<Expr>, Rc<Expr>),
+    Not(Rc<Expr>),
+    Neg(Rc<Expr>),
+    ///`condition ? nonzero : zero`, from a `CMov`
+    Select(Rc<Expr>, Rc<Expr>, Rc<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Statement
+///
+///An effect of a block, in the order it happens.
+pub enum Statement {
+    ///`t = value;`
+    Temp(usize, Rc<Expr>),
+    ///`t = getchar();`
+    Input(usize),
+    ///`t = map(size);`
+    Map(usize, Rc<Expr>),
+    ///`seg[id][offset] = value;`
+    Store(Rc<Expr>, Rc<Expr>, Rc<Expr>),
+    Unmap(Rc<Expr>),
+    Output(Rc<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Terminator
+///
+///How a block ends.
+pub enum Terminator {
+    ///The block runs into the one at the given offset.
+    Fall(usize),
+    Goto(usize),
+    ///Goes to the first offset when the condition is not 0, to the second
+    ///otherwise.
+    Branch(Rc<Expr>, usize, usize),
+    ///Calls the function at the first offset, which returns to the second.
+    Call(usize, usize),
+    ///Jumps to an offset only known at runtime.
+    Indirect(Rc<Expr>),
+    ///Loads a copy of a segment other than 0 and jumps to the offset.
+    LoadProgram(Rc<Expr>, Rc<Expr>),
+    Halt,
+    Invalid(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Lifted
+///
+///A block in the SSA-like form: its effects, the value of every register
+///when it ends, and how it ends.
+pub struct Lifted {
+    pub start: usize,
+    pub end: usize,
+    pub statements: Vec<Statement>,
+    pub registers: [Rc<Expr>; 8],
+    pub terminator: Terminator,
+    initial: [Rc<Expr>; 8],
+}
+
+///Function: `lift(words: &[u32], block: &Block, temps: &mut usize) -> Lifted`
+///
+///This function lifts `block` of the program `words`, numbering its
+///temporaries from `temps`.
+pub fn lift(words: &[u32], block: &Block, temps: &mut usize) -> Lifted {
+    let initial: [Rc<Expr>; 8] = std::array::from_fn(|register| {
+        Rc::new(block.registers[register].map_or(Expr::Register(register), Expr::Const))
+    });
+    let mut registers = initial.clone();
+    let mut statements = Vec::new();
+    let mut terminator = Terminator::Fall(block.end);
+    let mut temp = |statements: &mut Vec<Statement>, value: Option<Rc<Expr>>| {
+        *temps += 1;
+
+        if let Some(value) = value {
+            statements.push(Statement::Temp(*temps, value));
+        }
+
+        *temps
+    };
+
+    for word in &words[block.start..block.end] {
+        let instruction = Instruction::new(*word);
+        let (a, b, c) = (instruction.a as usize, instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+        let written = match instruction.opcode {
+            Opcode::CMov => Some(select(registers[c].clone(), registers[b].clone(), registers[a].clone())),
+            Opcode::Load => Some(Rc::new(Expr::Load(registers[b].clone(), registers[c].clone()))),
+            Opcode::Add => Some(binary(Operator::Add, registers[b].clone(), registers[c].clone())),
+            Opcode::Mul => Some(binary(Operator::Mul, registers[b].clone(), registers[c].clone())),
+            Opcode::Div => Some(binary(Operator::Div, registers[b].clone(), registers[c].clone())),
+            Opcode::Nand => Some(binary(Operator::Nand, registers[b].clone(), registers[c].clone())),
+            Opcode::LoadValue => Some(Rc::new(Expr::Const(instruction.value.unwrap()))),
+            _ => None,
+        };
+
+        //Loads must happen before the memory changes
+        if matches!(instruction.opcode, Opcode::Store | Opcode::UnmapSegment) {
+            for register in registers.iter_mut().filter(|register| contains_load(register)) {
+                let number = temp(&mut statements, Some(register.clone()));
+
+                *register = Rc::new(Expr::Temp(number));
+            }
+        }
+
+        match instruction.opcode {
+            Opcode::Store if block.call && *registers[c] == Expr::Const(block.end as u32) => {}
+            Opcode::Store => statements.push(Statement::Store(registers[a].clone(), registers[b].clone(), registers[c].clone())),
+            Opcode::Halt => terminator = Terminator::Halt,
+            Opcode::MapSegment => {
+                let number = temp(&mut statements, None);
+
+                statements.push(Statement::Map(number, registers[c].clone()));
+                registers[b] = Rc::new(Expr::Temp(number));
+            }
+            Opcode::UnmapSegment => statements.push(Statement::Unmap(registers[c].clone())),
+            Opcode::Output => statements.push(Statement::Output(registers[c].clone())),
+            Opcode::Input => {
+                let number = temp(&mut statements, None);
+
+                statements.push(Statement::Input(number));
+                registers[c] = Rc::new(Expr::Temp(number));
+            }
+            Opcode::LoadProgram => {
+                terminator = match (&*registers[b], &*registers[c]) {
+                    (Expr::Const(0), Expr::Const(target)) if block.call => Terminator::Call(*target as usize, block.end),
+                    (Expr::Const(0), Expr::Const(target)) => Terminator::Goto(*target as usize),
+                    (Expr::Const(0), Expr::Select(condition, nonzero, zero)) => match (&**nonzero, &**zero) {
+                        (Expr::Const(nonzero), Expr::Const(zero)) => {
+                            Terminator::Branch(condition.clone(), *nonzero as usize, *zero as usize)
+                        }
+                        _ => Terminator::Indirect(registers[c].clone()),
+                    },
+                    (Expr::Const(0), _) => Terminator::Indirect(registers[c].clone()),
+                    _ => Terminator::LoadProgram(registers[b].clone(), registers[c].clone()),
+                }
+            }
+            Opcode::Err => terminator = Terminator::Invalid(*word),
+            _ => {}
+        }
+
+        if let Some(value) = written {
+            registers[a] = match size(&value, MAX_SIZE + 1) > MAX_SIZE {
+                true => Rc::new(Expr::Temp(temp(&mut statements, Some(value)))),
+                false => value,
+            };
+        }
+    }
+
+    Lifted { start: block.start, end: block.end, statements, registers, terminator, initial }
+}
+
+///Function: `decompile(program: &[u32], symbols: Option<&SymbolMap>) -> String`
+///
+///This function decompiles segment 0 of `program` to pseudo-C, naming
+///functions after `symbols` when available.
+pub fn decompile(program: &[u32], symbols: Option<&SymbolMap>) -> String {
+    let graph = cfg::recover(program, symbols);
+    let mut temps = 0;
+    let lifted: BTreeMap<usize, Lifted> =
+        graph.blocks.iter().map(|block| (block.start, lift(program, block, &mut temps))).collect();
+    let live = liveness(&lifted);
+    let mut printer = Printer { graph: &graph, lifted: &lifted, live, temps, lines: Vec::new(), labels: BTreeSet::new(), tails: HashSet::new(), ended: HashMap::new() };
+
+    //Blocks only reached through computed jumps belong to no function, and
+    //are printed as functions of their own
+    let owned: BTreeSet<usize> = graph.functions.iter().flat_map(|function| function.blocks.iter().copied()).collect();
+    //A function starts with its entry, even when it jumps back to blocks
+    //before it
+    let mut functions: Vec<(String, Vec<usize>)> = graph
+        .functions
+        .iter()
+        .map(|function| {
+            let mut blocks = function.blocks.clone();
+            let entry = blocks.iter().position(|start| *start == function.entry).unwrap_or(0);
+
+            blocks.rotate_left(entry);
+            (function.name.clone(), blocks)
+        })
+        .collect();
+
+    for block in graph.blocks.iter().filter(|block| !owned.contains(&block.start)) {
+        match functions.last_mut() {
+            Some((_, blocks)) if blocks.last().is_some_and(|last| lifted[last].end == block.start && !owned.contains(last)) => {
+                blocks.push(block.start);
+            }
+            _ => functions.push((format!("sub_{}", block.start), vec![block.start])),
+        }
+    }
+
+    for (name, blocks) in &functions {
+        printer.line(0, format!("\nvoid {}(void)\n{{", name));
+        printer.sequence(blocks, 0, blocks.len(), None, 1, &mut Vec::new());
+        printer.line(0, "}".to_string());
+    }
+
+    let mut text = format!("// {} words, {} functions\nuint32_t r0 = 0, r1 = 0, r2 = 0, r3 = 0, r4 = 0, r5 = 0, r6 = 0, r7 = 0;\n", program.len(), functions.len());
+
+    //Every temporary is declared up front, even one no printed line uses
+    if printer.temps > 0 {
+        let names: Vec<String> = (1..=printer.temps).map(|temp| format!("t{}", temp)).collect();
+
+        text.push_str(&format!("uint32_t {};\n", names.join(", ")));
+    }
+
+    for line in &printer.lines {
+        match line {
+            Line::Text(depth, line) => {
+                text.push_str(&"    ".repeat(*depth));
+                text.push_str(line);
+                text.push('\n');
+            }
+            Line::Label(start) if printer.labels.contains(start) => text.push_str(&format!("L{}:\n", start)),
+            Line::Label(_) => {}
+        }
+    }
+
+    text
+}
+
+///Function: `liveness(lifted: &BTreeMap<usize, Lifted>) -> BTreeMap<usize, [bool; 8]>`
+///
+///This function finds the registers each block may leave to a later block
+///reading them before assigning them.
+fn liveness(lifted: &BTreeMap<usize, Lifted>) -> BTreeMap<usize, [bool; 8]> {
+    let mut live_in: BTreeMap<usize, [bool; 8]> = lifted.keys().map(|start| (*start, [false; 8])).collect();
+    let mut live_out = live_in.clone();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (start, block) in lifted.iter().rev() {
+            let mut out = [false; 8];
+
+            match successors(&block.terminator) {
+                Some(successors) => {
+                    for successor in successors {
+                        let entering = live_in.get(&successor).copied().unwrap_or([true; 8]);
+
+                        for register in 0..8 {
+                            out[register] |= entering[register];
+                        }
+                    }
+                }
+                None if matches!(block.terminator, Terminator::Halt | Terminator::Invalid(_)) => {}
+                None => out = [true; 8],
+            }
+
+            let mut entering = [false; 8];
+
+            for register in 0..8 {
+                entering[register] = out[register] && *block.registers[register] == *block.initial[register];
+            }
+
+            for statement in &block.statements {
+                for value in statement_values(statement) {
+                    reads(value, &mut entering);
+                }
+            }
+
+            for register in (0..8).filter(|register| *block.registers[*register] != *block.initial[*register]) {
+                reads(&block.registers[register], &mut entering);
+            }
+
+            for value in terminator_values(&block.terminator) {
+                reads(value, &mut entering);
+            }
+
+            if live_in[start] != entering || live_out[start] != out {
+                live_in.insert(*start, entering);
+                live_out.insert(*start, out);
+                changed = true;
+            }
+        }
+    }
+
+    live_out
+}
+
+///Function: `successors(terminator: &Terminator) -> Option<Vec<usize>>`
+///
+///This helper function returns the blocks a terminator may go to, or `None`
+///when they are unknown.
+fn successors(terminator: &Terminator) -> Option<Vec<usize>> {
+    match terminator {
+        Terminator::Fall(target) | Terminator::Goto(target) => Some(vec![*target]),
+        Terminator::Branch(_, nonzero, zero) => Some(vec![*nonzero, *zero]),
+        Terminator::Call(target, back) => Some(vec![*target, *back]),
+        _ => None,
+    }
+}
+
+///Function: `statement_values(statement: &Statement) -> Vec<&Rc<Expr>>`
+///
+///This helper function returns the expressions a statement reads.
+fn statement_values(statement: &Statement) -> Vec<&Rc<Expr>> {
+    match statement {
+        Statement::Temp(_, value) | Statement::Map(_, value) | Statement::Unmap(value) | Statement::Output(value) => vec![value],
+        Statement::Input(_) => vec![],
+        Statement::Store(id, offset, value) => vec![id, offset, value],
+    }
+}
+
+///Function: `terminator_values(terminator: &Terminator) -> Vec<&Rc<Expr>>`
+///
+///This helper function returns the expressions a terminator reads.
+fn terminator_values(terminator: &Terminator) -> Vec<&Rc<Expr>> {
+    match terminator {
+        Terminator::Branch(condition, _, _) => vec![condition],
+        Terminator::Indirect(target) => vec![target],
+        Terminator::LoadProgram(id, offset) => vec![id, offset],
+        _ => vec![],
+    }
+}
+
+///Function: `reads(expr: &Expr, registers: &mut [bool; 8])`
+///
+///This helper function marks the registers `expr` reads.
+fn reads(expr: &Expr, registers: &mut [bool; 8]) {
+    match expr {
+        Expr::Register(register) => registers[*register] = true,
+        Expr::Const(_) | Expr::Temp(_) => {}
+        Expr::Not(x) | Expr::Neg(x) => reads(x, registers),
+        Expr::Load(x, y) | Expr::Binary(_, x, y) => {
+            reads(x, registers);
+            reads(y, registers);
+        }
+        Expr::Select(x, y, z) => {
+            reads(x, registers);
+            reads(y, registers);
+            reads(z, registers);
+        }
+    }
+}
+
+///Function: `temps(expr: &Expr, used: &mut HashSet<usize>)`
+///
+///This helper function adds the temporaries `expr` reads to `used`.
+fn temps(expr: &Expr, used: &mut HashSet<usize>) {
+    match expr {
+        Expr::Temp(temp) => {
+            used.insert(*temp);
+        }
+        Expr::Const(_) | Expr::Register(_) => {}
+        Expr::Not(x) | Expr::Neg(x) => temps(x, used),
+        Expr::Load(x, y) | Expr::Binary(_, x, y) => {
+            temps(x, used);
+            temps(y, used);
+        }
+        Expr::Select(x, y, z) => {
+            temps(x, used);
+            temps(y, used);
+            temps(z, used);
+        }
+    }
+}
+
+///Function: `contains_load(expr: &Expr) -> bool`
+///
+///This helper function tells whether `expr` reads memory.
+fn contains_load(expr: &Expr) -> bool {
+    match expr {
+        Expr::Load(_, _) => true,
+        Expr::Const(_) | Expr::Register(_) | Expr::Temp(_) => false,
+        Expr::Not(x) | Expr::Neg(x) => contains_load(x),
+        Expr::Binary(_, x, y) => contains_load(x) || contains_load(y),
+        Expr::Select(x, y, z) => contains_load(x) || contains_load(y) || contains_load(z),
+    }
+}
+
+///Function: `size(expr: &Expr, limit: usize) -> usize`
+///
+///This helper function counts the nodes of `expr` as printed, stopping at
+///`limit`.
+fn size(expr: &Expr, limit: usize) -> usize {
+    let children: Vec<&Rc<Expr>> = match expr {
+        Expr::Const(_) | Expr::Register(_) | Expr::Temp(_) => vec![],
+        Expr::Not(x) | Expr::Neg(x) => vec![x],
+        Expr::Load(x, y) | Expr::Binary(_, x, y) => vec![x, y],
+        Expr::Select(x, y, z) => vec![x, y, z],
+    };
+
+    children.into_iter().fold(1, |total, child| match total < limit {
+        true => total + size(child, limit - total),
+        false => total,
+    })
+}
+
+///Function: `substitute(expr: &Rc<Expr>, register: usize, temp: usize) -> Rc<Expr>`
+///
+///This helper function replaces the reads of `register` in `expr` with
+///reads of temporary `temp`.
+fn substitute(expr: &Rc<Expr>, register: usize, temp: usize) -> Rc<Expr> {
+    let map = |x: &Rc<Expr>| substitute(x, register, temp);
+
+    match &**expr {
+        Expr::Register(read) if *read == register => Rc::new(Expr::Temp(temp)),
+        Expr::Const(_) | Expr::Register(_) | Expr::Temp(_) => expr.clone(),
+        Expr::Not(x) => Rc::new(Expr::Not(map(x))),
+        Expr::Neg(x) => Rc::new(Expr::Neg(map(x))),
+        Expr::Load(x, y) => Rc::new(Expr::Load(map(x), map(y))),
+        Expr::Binary(operator, x, y) => Rc::new(Expr::Binary(*operator, map(x), map(y))),
+        Expr::Select(x, y, z) => Rc::new(Expr::Select(map(x), map(y), map(z))),
+    }
+}
+
+///Function: `reuse(expr: &Rc<Expr>, assignments: &[(usize, Rc<Expr>)], assigned: &[bool; 8], conflict: &mut bool) -> Rc<Expr>`
+///
+///This helper function replaces the parts of `expr` equal to the value of
+///one of `assignments` with the register assigned, noting a `conflict` when
+///another part reads an `assigned` register.
+fn reuse(expr: &Rc<Expr>, assignments: &[(usize, Rc<Expr>)], assigned: &[bool; 8], conflict: &mut bool) -> Rc<Expr> {
+    let mut map = |x: &Rc<Expr>| reuse(x, assignments, assigned, conflict);
+
+    if let Some((register, _)) = assignments.iter().find(|(_, value)| value == expr && size(value, 2) > 1) {
+        return Rc::new(Expr::Register(*register));
+    }
+
+    match &**expr {
+        Expr::Register(register) => {
+            *conflict |= assigned[*register];
+            expr.clone()
+        }
+        Expr::Const(_) | Expr::Temp(_) => expr.clone(),
+        Expr::Not(x) => Rc::new(Expr::Not(map(x))),
+        Expr::Neg(x) => Rc::new(Expr::Neg(map(x))),
+        Expr::Load(x, y) => Rc::new(Expr::Load(map(x), map(y))),
+        Expr::Binary(operator, x, y) => Rc::new(Expr::Binary(*operator, map(x), map(y))),
+        Expr::Select(x, y, z) => Rc::new(Expr::Select(map(x), map(y), map(z))),
+    }
+}
+
+///Function: `evaluate(operator: Operator, x: u32, y: u32) -> Option<u32>`
+///
+///This helper function folds an operator over constants.
+fn evaluate(operator: Operator, x: u32, y: u32) -> Option<u32> {
+    match operator {
+        Operator::Add => Some(x.wrapping_add(y)),
+        Operator::Sub => Some(x.wrapping_sub(y)),
+        Operator::Mul => Some(x.wrapping_mul(y)),
+        Operator::Div => x.checked_div(y),
+        Operator::And => Some(x & y),
+        Operator::Or => Some(x | y),
+        Operator::Xor => Some(x ^ y),
+        Operator::Nand => Some(!(x & y)),
+    }
+}
+
+///Function: `binary(operator: Operator, x: Rc<Expr>, y: Rc<Expr>) -> Rc<Expr>`
+///
+///This function builds `x operator y`, folding constants and recognizing
+///the `Nand` idioms: `~x` as `x nand x`, `x & y` as `~(x nand y)`, `x | y` as
+///`~x nand ~y`, `x ^ y` as `(x nand n) nand (y nand n)` with `n = x nand y`,
+///`-y` as `~y + 1` and `x - y` as `x + -y`.
+pub fn binary(operator: Operator, x: Rc<Expr>, y: Rc<Expr>) -> Rc<Expr> {
+    use Expr::{Binary, Const, Neg, Not};
+
+    let commutative = !matches!(operator, Operator::Sub | Operator::Div);
+
+    match (operator, &*x, &*y) {
+        (_, Const(p), Const(q)) if evaluate(operator, *p, *q).is_some() => Rc::new(Const(evaluate(operator, *p, *q).unwrap())),
+        //Constants go to the right
+        (_, Const(_), _) if commutative => binary(operator, y, x),
+        (Operator::Add | Operator::Sub | Operator::Or | Operator::Xor, _, Const(0)) => x,
+        (Operator::Mul | Operator::Div, _, Const(1)) | (Operator::And, _, Const(u32::MAX)) => x,
+        (Operator::Add, Binary(Operator::Add, inner, constant), Const(_)) if matches!(**constant, Const(_)) => {
+            binary(Operator::Add, inner.clone(), binary(Operator::Add, constant.clone(), y.clone()))
+        }
+        (Operator::Add, Not(inner), Const(1)) => Rc::new(Neg(inner.clone())),
+        (Operator::Add, Binary(Operator::Add, u, v), Const(1)) => match (&**u, &**v) {
+            (_, Not(v)) => binary(Operator::Sub, u.clone(), v.clone()),
+            (Not(u), _) => binary(Operator::Sub, v.clone(), u.clone()),
+            _ => Rc::new(Binary(operator, x.clone(), y.clone())),
+        },
+        (Operator::Add, _, Neg(v)) => binary(Operator::Sub, x.clone(), v.clone()),
+        (Operator::Add, Neg(v), _) => binary(Operator::Sub, y.clone(), v.clone()),
+        (Operator::Nand, _, _) if x == y => not(x),
+        (Operator::Nand, _, Const(u32::MAX)) => not(x),
+        (Operator::Nand, Not(u), Not(v)) => binary(Operator::Or, u.clone(), v.clone()),
+        (Operator::Nand, Binary(Operator::Nand, _, _), Binary(Operator::Nand, _, _)) => match xor(&x, &y) {
+            Some((u, v)) => binary(Operator::Xor, u, v),
+            None => Rc::new(Binary(operator, x.clone(), y.clone())),
+        },
+        _ => Rc::new(Binary(operator, x.clone(), y.clone())),
+    }
+}
+
+///Function: `xor(x: &Expr, y: &Expr) -> Option<(Rc<Expr>, Rc<Expr>)>`
+///
+///This helper function recognizes `x nand y` as `u ^ v`, when `x` is
+///`u nand n` and `y` is `v nand n` with `n = u nand v`, in any order.
+fn xor(x: &Expr, y: &Expr) -> Option<(Rc<Expr>, Rc<Expr>)> {
+    let (Expr::Binary(Operator::Nand, x1, x2), Expr::Binary(Operator::Nand, y1, y2)) = (x, y) else {
+        return None;
+    };
+
+    for (u, n) in [(x1, x2), (x2, x1)] {
+        for (v, m) in [(y1, y2), (y2, y1)] {
+            let shared = match &**n {
+                Expr::Binary(Operator::Nand, p, q) => (p == u && q == v) || (p == v && q == u),
+                _ => false,
+            };
+
+            if n == m && shared {
+                return Some((u.clone(), v.clone()));
+            }
+        }
+    }
+
+    None
+}
+
+///Function: `not(x: Rc<Expr>) -> Rc<Expr>`
+///
+///This function builds `~x`, turning `~(u nand v)` into `u & v`.
+pub fn not(x: Rc<Expr>) -> Rc<Expr> {
+    match &*x {
+        Expr::Const(value) => Rc::new(Expr::Const(!value)),
+        Expr::Not(inner) => inner.clone(),
+        Expr::Binary(Operator::Nand, u, v) => binary(Operator::And, u.clone(), v.clone()),
+        Expr::Binary(Operator::And, u, v) => Rc::new(Expr::Binary(Operator::Nand, u.clone(), v.clone())),
+        _ => Rc::new(Expr::Not(x)),
+    }
+}
+
+///Function: `select(condition: Rc<Expr>, nonzero: Rc<Expr>, zero: Rc<Expr>) -> Rc<Expr>`
+///
+///This function builds the value a `CMov` leaves, picking it when the
+///condition is known.
+pub fn select(condition: Rc<Expr>, nonzero: Rc<Expr>, zero: Rc<Expr>) -> Rc<Expr> {
+    match &*condition {
+        Expr::Const(0) => zero,
+        Expr::Const(_) => nonzero,
+        _ if nonzero == zero => zero,
+        _ => Rc::new(Expr::Select(condition, nonzero, zero)),
+    }
+}
+
+impl Expr {
+
+    ///Function: `precedence(&self) -> u8`
+    ///
+    ///This helper function returns how tightly the C rendering of the
+    ///expression binds.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Select(_, nonzero, zero) if **nonzero == Expr::Const(0) && **zero == Expr::Const(1) => 8,
+            Expr::Select(_, _, _) => 1,
+            Expr::Binary(Operator::Or, _, _) => 2,
+            Expr::Binary(Operator::Xor, _, _) => 3,
+            Expr::Binary(Operator::And, _, _) => 4,
+            Expr::Binary(Operator::Add | Operator::Sub, _, _) => 6,
+            Expr::Binary(Operator::Mul | Operator::Div, _, _) => 7,
+            Expr::Binary(Operator::Nand, _, _) | Expr::Not(_) | Expr::Neg(_) => 8,
+            Expr::Const(_) | Expr::Register(_) | Expr::Temp(_) | Expr::Load(_, _) => 9,
+        }
+    }
+
+    ///Function: `write(&self, f: &mut fmt::Formatter, least: u8) -> fmt::Result`
+    ///
+    ///This helper function writes the expression, in parentheses when it
+    ///binds less tightly than `least`.
+    fn write(&self, f: &mut fmt::Formatter, least: u8) -> fmt::Result {
+        let precedence = self.precedence();
+
+        if precedence < least {
+            write!(f, "(")?;
+        }
+
+        match self {
+            Expr::Const(value) => write!(f, "{}", number(*value))?,
+            Expr::Register(register) => write!(f, "r{}", register)?,
+            Expr::Temp(temp) => write!(f, "t{}", temp)?,
+            Expr::Load(id, offset) => {
+                write!(f, "seg[")?;
+                id.write(f, 0)?;
+                write!(f, "][")?;
+                offset.write(f, 0)?;
+                write!(f, "]")?;
+            }
+            //Adding a large constant is subtracting its opposite
+            Expr::Binary(Operator::Add, x, y) if matches!(**y, Expr::Const(value) if value > 0x8000_0000) => {
+                x.write(f, precedence)?;
+                write!(f, " - ")?;
+                Expr::Const(0u32.wrapping_sub(constant(y))).write(f, precedence + 1)?;
+            }
+            Expr::Binary(Operator::Nand, x, y) => {
+                write!(f, "~(")?;
+                x.write(f, 4)?;
+                write!(f, " & ")?;
+                y.write(f, 5)?;
+                write!(f, ")")?;
+            }
+            Expr::Binary(operator, x, y) => {
+                let symbol = match operator {
+                    Operator::Add => "+",
+                    Operator::Sub => "-",
+                    Operator::Mul => "*",
+                    Operator::Div => "/",
+                    Operator::And => "&",
+                    Operator::Or => "|",
+                    Operator::Xor => "^",
+                    Operator::Nand => unreachable!(),
+                };
+
+                x.write(f, precedence)?;
+                write!(f, " {} ", symbol)?;
+                y.write(f, precedence + 1)?;
+            }
+            Expr::Not(x) => {
+                write!(f, "~")?;
+                x.write(f, precedence)?;
+            }
+            Expr::Neg(x) => {
+                write!(f, "-")?;
+                x.write(f, precedence)?;
+            }
+            Expr::Select(condition, nonzero, zero) if **nonzero == Expr::Const(0) && **zero == Expr::Const(1) => {
+                write!(f, "!")?;
+                condition.write(f, 8)?;
+            }
+            Expr::Select(condition, nonzero, zero) => {
+                condition.write(f, 2)?;
+                write!(f, " ? ")?;
+                nonzero.write(f, 2)?;
+                write!(f, " : ")?;
+                zero.write(f, 1)?;
+            }
+        }
+
+        if precedence < least {
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+///Function: `constant(expr: &Expr) -> u32`
+///
+///This helper function returns the value of a constant expression.
+fn constant(expr: &Expr) -> u32 {
+    match expr {
+        Expr::Const(value) => *value,
+        _ => unreachable!(),
+    }
+}
+
+///Function: `number(value: u32) -> String`
+///
+///This helper function formats a constant, in hexadecimal when large.
+fn number(value: u32) -> String {
+    match value {
+        0..=0xffff => value.to_string(),
+        _ => format!("{:#x}", value),
+    }
+}
+
+///Function: `condition(expr: &Expr, nonzero: bool) -> String`
+///
+///This helper function writes the C condition testing whether `expr` is not
+///0, or is 0 when `nonzero` is false. Differences, and sums with a
+///constant, become comparisons.
+fn condition(expr: &Expr, nonzero: bool) -> String {
+    let operator = if nonzero { "!=" } else { "==" };
+    let operand = |expr: &Expr| {
+        let text = expr.to_string();
+
+        match expr.precedence() < 6 {
+            true => format!("({})", text),
+            false => text,
+        }
+    };
+
+    //Selecting 0 or another constant is a test
+    if let Expr::Select(test, nonzero_value, zero_value) = expr {
+        match (&**nonzero_value, &**zero_value) {
+            (Expr::Const(0), Expr::Const(_)) => return condition(test, !nonzero),
+            (Expr::Const(_), Expr::Const(0)) => return condition(test, nonzero),
+            (Expr::Const(_), _) if zero_value == test => return condition(test, nonzero),
+            _ => {}
+        }
+    }
+
+    match expr {
+        Expr::Binary(Operator::Sub | Operator::Xor, x, y) => format!("{} {} {}", operand(x), operator, operand(y)),
+        Expr::Binary(Operator::Add, x, y) if matches!(**y, Expr::Const(_)) => {
+            format!("{} {} {}", operand(x), operator, number(0u32.wrapping_sub(constant(y))))
+        }
+        _ if nonzero => expr.to_string(),
+        _ if expr.precedence() >= 8 => format!("!{}", expr),
+        _ => format!("!({})", expr),
+    }
+}
+
+///Enum Line
+///
+///A line of the output: text at a depth, or the label of a block, printed
+///only when some `goto` uses it.
+enum Line {
+    Text(usize, String),
+    Label(usize),
+}
+
+///Structure: Printer
+///
+///The state of printing the lifted blocks as structured pseudo-C: the loops
+///being printed are `(header, exit, continue allowed)`, and `tails` are the
+///blocks ending a `do ... while`, whose jump is the loop condition.
+struct Printer<'a> {
+    graph: &'a Cfg,
+    lifted: &'a BTreeMap<usize, Lifted>,
+    live: BTreeMap<usize, [bool; 8]>,
+    temps: usize,
+    lines: Vec<Line>,
+    labels: BTreeSet<usize>,
+    tails: HashSet<usize>,
+    ended: HashMap<usize, Terminator>,
+}
+
+type Loops = Vec<(usize, Option<usize>, bool)>;
+
+impl Printer<'_> {
+
+    ///Function: `line(&mut self, depth: usize, text: String)`
+    ///
+    ///This helper function adds a line of text at `depth`.
+    fn line(&mut self, depth: usize, text: String) {
+        self.lines.push(Line::Text(depth, text));
+    }
+
+    ///Function: `sequence(&mut self, order: &[usize], pos: usize, end: usize, follow: Option<usize>, depth: usize, loops: &mut Loops)`
+    ///
+    ///This function prints the blocks `order[pos..end]`, after which
+    ///execution goes on with the block at `follow`.
+    fn sequence(&mut self, order: &[usize], mut pos: usize, end: usize, follow: Option<usize>, depth: usize, loops: &mut Loops) {
+        while pos < end {
+            let start = order[pos];
+            let next = if pos + 1 < end { Some(order[pos + 1]) } else { follow };
+            let position = |target: usize, from: usize| match order[from..end].iter().position(|start| *start == target) {
+                Some(index) => Some(from + index),
+                None if Some(target) == follow => Some(end),
+                None => None,
+            };
+
+            //A later block jumping back here makes a loop
+            if !loops.iter().any(|(header, _, _)| *header == start) {
+                let last = (pos..end).rev().find(|index| {
+                    match &self.lifted[&order[*index]].terminator {
+                        Terminator::Fall(target) | Terminator::Goto(target) | Terminator::Call(_, target) => *target == start,
+                        Terminator::Branch(_, nonzero, zero) => *nonzero == start || *zero == start,
+                        _ => false,
+                    }
+                });
+
+                if let Some(last) = last {
+                    let exit = if last + 1 < end { Some(order[last + 1]) } else { follow };
+
+                    self.looped(order, pos, last, exit, depth, loops);
+                    pos = last + 1;
+                    continue;
+                }
+            }
+
+            let terminator = self.block(start, depth);
+
+            if self.tails.contains(&start) {
+                self.ended.insert(start, terminator);
+                pos += 1;
+                continue;
+            }
+
+            match terminator {
+                Terminator::Branch(test, nonzero, zero) => {
+                    //Jumping over the blocks that follow makes an `if`
+                    let inner = match next {
+                        Some(next) if pos + 1 < end && nonzero == next => Some((true, zero)),
+                        Some(next) if pos + 1 < end && zero == next => Some((false, nonzero)),
+                        _ => None,
+                    };
+                    let over = inner.and_then(|(taken, other)| Some((taken, other, position(other, pos + 2)?)));
+
+                    if let Some((taken, other, skip)) = over {
+                        //Ending the `if` part jumping further makes an `else`
+                        let joined = match &self.lifted[&order[skip - 1]].terminator {
+                            Terminator::Goto(join) if skip < end => position(*join, skip + 1).map(|after| (*join, after)),
+                            _ => None,
+                        };
+
+                        self.line(depth, format!("if ({}) {{", condition(&test, taken)));
+
+                        match joined {
+                            Some((join, after)) => {
+                                self.sequence(order, pos + 1, skip, Some(join), depth + 1, loops);
+                                self.line(depth, "} else {".to_string());
+                                self.sequence(order, skip, after, Some(join), depth + 1, loops);
+                                self.line(depth, "}".to_string());
+                                pos = after;
+                            }
+                            None => {
+                                self.sequence(order, pos + 1, skip, Some(other), depth + 1, loops);
+                                self.line(depth, "}".to_string());
+                                pos = skip;
+                            }
+                        }
+
+                        continue;
+                    }
+
+                    let (first, second, taken) = match next == Some(nonzero) {
+                        true => (zero, nonzero, false),
+                        false => (nonzero, zero, true),
+                    };
+
+                    if let Some(jump) = self.jump(first, next, loops) {
+                        self.line(depth, format!("if ({}) {}", condition(&test, taken), jump));
+                    }
+
+                    if let Some(jump) = self.jump(second, next, loops) {
+                        self.line(depth, jump);
+                    }
+                }
+                Terminator::Fall(target) | Terminator::Goto(target) | Terminator::Call(_, target) => {
+                    if let Some(jump) = self.jump(target, next, loops) {
+                        self.line(depth, jump);
+                    }
+                }
+                _ => {}
+            }
+
+            pos += 1;
+        }
+    }
+
+    ///Function: `looped(&mut self, order: &[usize], pos: usize, last: usize, exit: Option<usize>, depth: usize, loops: &mut Loops)`
+    ///
+    ///This function prints the loop made of the blocks `order[pos..=last]`,
+    ///left for the block at `exit`.
+    fn looped(&mut self, order: &[usize], pos: usize, last: usize, exit: Option<usize>, depth: usize, loops: &mut Loops) {
+        let header = order[pos];
+
+        match (&self.lifted[&order[last]].terminator, &self.lifted[&header].terminator) {
+            //The last block jumping back conditionally
+            (Terminator::Branch(_, nonzero, zero), _) if (*nonzero == header && Some(*zero) == exit) || (*zero == header && Some(*nonzero) == exit) => {
+                self.line(depth, "do {".to_string());
+                self.tails.insert(order[last]);
+                loops.push((header, exit, false));
+                self.sequence(order, pos, last + 1, Some(header), depth + 1, loops);
+                loops.pop();
+
+                //The condition as the last block left it
+                let test = match self.ended.remove(&order[last]) {
+                    Some(Terminator::Branch(test, nonzero, _)) => condition(&test, nonzero == header),
+                    _ => unreachable!(),
+                };
+
+                self.line(depth, format!("}} while ({});", test));
+            }
+            //The first block only testing whether to leave
+            (_, Terminator::Branch(test, nonzero, zero))
+                if pos < last && self.is_empty(header) && ((*nonzero == order[pos + 1] && Some(*zero) == exit) || (*zero == order[pos + 1] && Some(*nonzero) == exit)) =>
+            {
+                let test = condition(test, *nonzero == order[pos + 1]);
+
+                self.lines.push(Line::Label(header));
+                self.line(depth, format!("while ({}) {{", test));
+                loops.push((header, exit, true));
+                self.sequence(order, pos + 1, last + 1, Some(header), depth + 1, loops);
+                loops.pop();
+                self.line(depth, "}".to_string());
+            }
+            _ => {
+                self.line(depth, "while (1) {".to_string());
+                loops.push((header, exit, true));
+                self.sequence(order, pos, last + 1, Some(header), depth + 1, loops);
+                loops.pop();
+                self.line(depth, "}".to_string());
+            }
+        }
+    }
+
+    ///Function: `jump(&mut self, target: usize, next: Option<usize>, loops: &Loops) -> Option<String>`
+    ///
+    ///This function returns the statement going to the block at `target`,
+    ///or `None` when it is the block execution goes on with anyway.
+    fn jump(&mut self, target: usize, next: Option<usize>, loops: &Loops) -> Option<String> {
+        match loops.last() {
+            _ if Some(target) == next => None,
+            Some((header, _, true)) if *header == target => Some("continue;".to_string()),
+            Some((_, exit, _)) if *exit == Some(target) => Some("break;".to_string()),
+            _ => {
+                self.labels.insert(target);
+                Some(format!("goto L{};", target))
+            }
+        }
+    }
+
+    ///Function: `assignments(&self, start: usize) -> Vec<(usize, Rc<Expr>)>`
+    ///
+    ///This helper function returns the registers the block at `start`
+    ///changes that a later block may read.
+    fn assignments(&self, start: usize) -> Vec<(usize, Rc<Expr>)> {
+        let block = &self.lifted[&start];
+
+        (0..8)
+            .filter(|register| self.live[&start][*register] && *block.registers[*register] != *block.initial[*register])
+            .map(|register| (register, block.registers[register].clone()))
+            .collect()
+    }
+
+    ///Function: `is_empty(&self, start: usize) -> bool`
+    ///
+    ///This helper function tells whether the block at `start` prints nothing
+    ///but its jump.
+    fn is_empty(&self, start: usize) -> bool {
+        self.lifted[&start].statements.is_empty() && self.assignments(start).is_empty()
+    }
+
+    ///Function: `block(&mut self, start: usize, depth: usize) -> Terminator`
+    ///
+    ///This function prints the statements of the block at `start`, its
+    ///register assignments and the end of the block other than jumps, and
+    ///returns its terminator reading the values as they are once printed.
+    fn block(&mut self, start: usize, depth: usize) -> Terminator {
+        let block = &self.lifted[&start];
+        let mut pending = self.assignments(start);
+        let mut terminator = block.terminator.clone();
+        let mut assigned = [false; 8];
+
+        //A jump to an offset loaded from memory returns, and the register
+        //holding the offset is left out
+        let returning = matches!(&terminator, Terminator::Indirect(target) if contains_load(target));
+
+        if let Terminator::Indirect(target) = &terminator {
+            pending.retain(|(_, value)| !returning || value != target);
+        }
+
+        for (register, _) in &pending {
+            assigned[*register] = true;
+        }
+
+        //The terminator reads the registers just assigned the values it
+        //needs, or saves them when it needs a register before it changes
+        let mut conflict = false;
+        let reused = match &terminator {
+            _ if returning => terminator.clone(),
+            Terminator::Branch(test, nonzero, zero) => Terminator::Branch(reuse(test, &pending, &assigned, &mut conflict), *nonzero, *zero),
+            Terminator::Indirect(target) => Terminator::Indirect(reuse(target, &pending, &assigned, &mut conflict)),
+            Terminator::LoadProgram(id, offset) => Terminator::LoadProgram(
+                reuse(id, &pending, &assigned, &mut conflict),
+                reuse(offset, &pending, &assigned, &mut conflict),
+            ),
+            terminator => terminator.clone(),
+        };
+
+        if !conflict {
+            terminator = reused;
+        }
+
+        //Temporaries nothing reads are left out
+        let mut used = HashSet::new();
+
+        for value in pending.iter().map(|(_, value)| value).chain(terminator_values(&terminator).into_iter().filter(|_| !returning)) {
+            temps(value, &mut used);
+        }
+
+        let mut statements = Vec::new();
+
+        for statement in block.statements.iter().rev() {
+            match statement {
+                Statement::Temp(temp, _) if !used.contains(temp) => continue,
+                _ => {}
+            }
+
+            for value in statement_values(statement) {
+                temps(value, &mut used);
+            }
+
+            statements.push(statement);
+        }
+
+        self.lines.push(Line::Label(start));
+
+        for statement in statements.into_iter().rev() {
+            let text = match statement {
+                Statement::Temp(temp, value) => format!("t{} = {};", temp, value),
+                Statement::Input(temp) => format!("t{} = getchar();", temp),
+                Statement::Map(temp, size) => format!("t{} = map({});", temp, size),
+                Statement::Store(id, offset, value) => format!("seg[{}][{}] = {};", id, offset, value),
+                Statement::Unmap(id) => format!("unmap({});", id),
+                Statement::Output(value) if matches!(**value, Expr::Const(32..=126 | 10)) => {
+                    format!("putchar('{}');", (constant(value) as u8).escape_ascii())
+                }
+                Statement::Output(value) => format!("putchar({});", value),
+            };
+
+            self.line(depth, text);
+        }
+
+        if conflict {
+            terminator = self.saved(terminator, depth);
+        }
+
+        //The registers are assigned together: a register read by the
+        //assignments still to print is saved first when they all read one
+        while !pending.is_empty() {
+            let ready = (0..pending.len()).find(|index| {
+                pending.iter().enumerate().all(|(other, (_, value))| {
+                    let mut read = [false; 8];
+
+                    reads(value, &mut read);
+                    other == *index || !read[pending[*index].0]
+                })
+            });
+
+            match ready {
+                Some(index) => {
+                    let (register, value) = pending.remove(index);
+
+                    self.line(depth, format!("r{} = {};", register, value));
+                }
+                None => {
+                    let register = pending[0].0;
+
+                    self.temps += 1;
+                    self.line(depth, format!("t{} = r{};", self.temps, register));
+
+                    for (_, value) in pending.iter_mut() {
+                        *value = substitute(value, register, self.temps);
+                    }
+                }
+            }
+        }
+
+        match &terminator {
+            Terminator::Call(target, _) => {
+                let name = match self.graph.functions.iter().find(|function| function.entry == *target) {
+                    Some(function) => function.name.clone(),
+                    None => format!("sub_{}", target),
+                };
+
+                self.line(depth, format!("{}();", name));
+            }
+            Terminator::Indirect(_) if returning => self.line(depth, "return;".to_string()),
+            Terminator::Indirect(target) => self.line(depth, format!("goto *({});", target)),
+            Terminator::LoadProgram(id, offset) => self.line(depth, format!("load_program({}, {});", id, offset)),
+            Terminator::Halt => self.line(depth, "halt();".to_string()),
+            Terminator::Invalid(word) => self.line(depth, format!("invalid({:#010x});", word)),
+            Terminator::Fall(target) if *target >= self.graph.words.len() => self.line(depth, "// runs past the end of the program".to_string()),
+            _ => {}
+        }
+
+        terminator
+    }
+
+    ///Function: `saved(&mut self, terminator: Terminator, depth: usize) -> Terminator`
+    ///
+    ///This helper function moves the values a terminator reads to
+    ///temporaries, before the registers they read are assigned.
+    fn saved(&mut self, terminator: Terminator, depth: usize) -> Terminator {
+        let mut save = |value: Rc<Expr>| {
+            self.temps += 1;
+            self.lines.push(Line::Text(depth, format!("t{} = {};", self.temps, value)));
+            Rc::new(Expr::Temp(self.temps))
+        };
+
+        match terminator {
+            Terminator::Branch(test, nonzero, zero) => Terminator::Branch(save(test), nonzero, zero),
+            Terminator::Indirect(target) if !contains_load(&target) => Terminator::Indirect(save(target)),
+            Terminator::LoadProgram(id, offset) => Terminator::LoadProgram(save(id), save(offset)),
+            terminator => terminator,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use crate::disassembler;
+use crate::fault::Fault;
+use crate::reference::ReferenceMachine;
+use crate::rum::{Mode, Rum, State};
+use crate::um_io::BufferIo;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Mismatch
+///
+///The first difference found between `Rum` and the `ReferenceMachine`.
+pub enum Mismatch {
+    Outcome { rum: Result<State, Fault>, reference: Result<State, Fault> },
+    Pc { rum: usize, reference: u32 },
+    Register { index: usize, rum: u32, reference: u32 },
+    Word { id: u32, offset: u32, rum: Option<u32>, reference: u32 },
+    SegmentSize { id: u32, rum: Option<usize>, reference: usize },
+    Output { rum: Vec<u8>, reference: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Divergence
+///
+///Where the two machines first disagreed: after `step` instructions, while
+///executing the `word` at `pc`.
+pub struct Divergence {
+    pub step: u64,
+    pub pc: usize,
+    pub word: u32,
+    pub mismatch: Mismatch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum Verdict
+///
+///The result of a differential run. Every verdict but `Diverged` means both
+///machines agreed on every instruction they executed.
+pub enum Verdict {
+    Halted { steps: u64, output: Vec<u8> },
+    Faulted { steps: u64, fault: Fault },
+    LimitReached { steps: u64 },
+    Diverged(Divergence),
+}
+
+///Function: `run(program: &[u32], input: &[u8], limit: Option<u64>) -> Verdict`
+///
+///This function executes `program` on `Rum` and on the `ReferenceMachine` in
+///lock-step, both reading `input`. After every instruction it compares the
+///outcome, pc, registers, the segment word written or the segment mapped, and
+///the output so far, stopping at the first divergence. `limit` bounds the
+///number of instructions executed. `Rum` runs in `Strict` mode, since the
+///reference faults on every misuse of segments, unmapping segment 0 included.
+pub fn run(program: &[u32], input: &[u8], limit: Option<u64>) -> Verdict {
+    let mut rum = Rum::new(program);
+    rum.set_mode(Mode::Strict);
+    let mut reference = ReferenceMachine::new(program);
+    let mut rum_io = BufferIo::new(input);
+    let mut reference_io = BufferIo::new(input);
+    let mut steps = 0;
+
+    while limit.is_none_or(|limit| steps < limit) {
+        let pc = rum.pc();
+        let word = rum.segments().fetch(pc).unwrap_or(0);
+        let diverged = move |mismatch| Verdict::Diverged(Divergence { step: steps, pc, word, mismatch });
+
+        let rum_result = rum.step(&mut rum_io);
+        let reference_result = reference.step(&mut reference_io);
+
+        let effect = match (rum_result, reference_result) {
+            (Ok(State::Running), Ok((State::Running, effect))) => effect,
+            (Ok(State::Halted), Ok((State::Halted, _))) => {
+                return Verdict::Halted { steps: steps + 1, output: rum_io.output };
+            }
+            (Err(rum_fault), Err(reference_fault)) if rum_fault == reference_fault => {
+                return Verdict::Faulted { steps, fault: rum_fault };
+            }
+            (rum_result, reference_result) => {
+                return diverged(Mismatch::Outcome { rum: rum_result, reference: reference_result.map(|(state, _)| state) });
+            }
+        };
+
+        steps += 1;
+
+        if rum.pc() != reference.pc as usize {
+            return diverged(Mismatch::Pc { rum: rum.pc(), reference: reference.pc });
+        }
+
+        for (index, (rum_value, reference_value)) in rum.registers().iter().zip(reference.registers).enumerate() {
+            if *rum_value != reference_value {
+                return diverged(Mismatch::Register { index, rum: *rum_value, reference: reference_value });
+            }
+        }
+
+        if let Some((id, offset, value)) = effect.write {
+            let stored = rum.segments().get_segment_value(id as usize, offset as usize);
+
+            if stored != Some(value) {
+                return diverged(Mismatch::Word { id, offset, rum: stored, reference: value });
+            }
+        }
+
+        if let Some(id) = effect.mapped {
+            let size = rum.segments().segment_len(id as usize);
+            let expected = reference.segments[&id].len();
+
+            if size != Some(expected) {
+                return diverged(Mismatch::SegmentSize { id, rum: size, reference: expected });
+            }
+        }
+
+        if rum_io.output.len() != reference_io.output.len() || rum_io.output.last() != reference_io.output.last() {
+            return diverged(Mismatch::Output { rum: rum_io.output, reference: reference_io.output });
+        }
+    }
+
+    Verdict::LimitReached { steps }
+}
+
+///Function: `compare(first: &[u32], second: &[u32], input: &[u8], limit: Option<u64>) -> Verdict`
+///
+///This function executes two images meant to behave the same, like a program
+///and its optimized version, on two `Rum`s in lock-step, both reading
+///`input`. After every instruction it compares the outcome, the pc and the
+///output so far; registers and segments may differ. In a `Mismatch`, `rum`
+///describes the `first` image and `reference` the `second`.
+pub fn compare(first: &[u32], second: &[u32], input: &[u8], limit: Option<u64>) -> Verdict {
+    let mut machines = (Rum::new(first), Rum::new(second));
+    let mut ios = (BufferIo::new(input), BufferIo::new(input));
+    let mut steps = 0;
+
+    while limit.is_none_or(|limit| steps < limit) {
+        let pc = machines.0.pc();
+        let word = machines.0.segments().fetch(pc).unwrap_or(0);
+        let diverged = move |mismatch| Verdict::Diverged(Divergence { step: steps, pc, word, mismatch });
+
+        match (machines.0.step(&mut ios.0), machines.1.step(&mut ios.1)) {
+            (Ok(State::Running), Ok(State::Running)) => {}
+            (Ok(State::Halted), Ok(State::Halted)) if ios.0.output == ios.1.output => {
+                return Verdict::Halted { steps: steps + 1, output: ios.0.output };
+            }
+            (Err(first_fault), Err(second_fault)) if first_fault == second_fault => {
+                return Verdict::Faulted { steps, fault: first_fault };
+            }
+            (first_result, second_result) if first_result != second_result => {
+                return diverged(Mismatch::Outcome { rum: first_result, reference: second_result });
+            }
+            _ => {}
+        }
+
+        steps += 1;
+
+        if machines.0.pc() != machines.1.pc() {
+            return diverged(Mismatch::Pc { rum: machines.0.pc(), reference: machines.1.pc() as u32 });
+        }
+
+        if ios.0.output != ios.1.output {
+            return diverged(Mismatch::Output { rum: ios.0.output, reference: ios.1.output });
+        }
+    }
+
+    Verdict::LimitReached { steps }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Outcome { rum, reference } => write!(f, "rum gave {:?}, the reference gave {:?}", rum, reference),
+            Mismatch::Pc { rum, reference } => write!(f, "pc is {} on rum, {} on the reference", rum, reference),
+            Mismatch::Register { index, rum, reference } =>
+                write!(f, "r{} is {} on rum, {} on the reference", index, rum, reference),
+            Mismatch::Word { id, offset, rum, reference } =>
+                write!(f, "word {} of segment {} is {:?} on rum, {} on the reference", offset, id, rum, reference),
+            Mismatch::SegmentSize { id, rum, reference } =>
+                write!(f, "segment {} has {:?} words on rum, {} on the reference", id, rum, reference),
+            Mismatch::Output { rum, reference } => {
+                let offset = rum.iter().zip(reference).position(|(first, second)| first != second).unwrap_or(rum.len().min(reference.len()));
+                let byte = |output: &[u8]| output.get(offset).map_or("nothing".to_string(), |value| format!("{:#04x}", value));
+
+                write!(f, "output byte {} is {} on rum, {} on the reference", offset, byte(rum), byte(reference))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diverged after {} instructions at pc {} ({}): {}",
+            self.step, self.pc, disassembler::disassemble(self.word), self.mismatch
+        )
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Halted { steps, .. } => write!(f, "both machines halted after {} instructions", steps),
+            Verdict::Faulted { steps, fault } => write!(f, "both machines faulted after {} instructions: {}", steps, fault),
+            Verdict::LimitReached { steps } => write!(f, "both machines agreed for {} instructions", steps),
+            Verdict::Diverged(divergence) => divergence.fmt(f),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::optimizer::{self, Registers};
+use crate::symbols::SymbolMap;
+use crate::um_instruction::{Instruction, Opcode};
+
+//The longest string recovered from a string-printing loop
+const MAX_STRING: usize = 256;
+
+///Function: `mnemonic(opcode: Opcode) -> &'static str`
+///
+///This function returns the assembly mnemonic of `opcode`.
+pub fn mnemonic(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::CMov => "cmov",
+        Opcode::Load => "sload",
+        Opcode::Store => "sstore",
+        Opcode::Add => "add",
+        Opcode::Mul => "mul",
+        Opcode::Div => "div",
+        Opcode::Nand => "nand",
+        Opcode::Halt => "halt",
+        Opcode::MapSegment => "map",
+        Opcode::UnmapSegment => "unmap",
+        Opcode::Output => "out",
+        Opcode::Input => "in",
+        Opcode::LoadProgram => "loadp",
+        Opcode::LoadValue => "loadv",
+        Opcode::Err => ".word",
+    }
+}
+
+///Function: `disassemble(word: u32) -> String`
+///
+///This function turns a single instruction `word` back into the assembly
+///text accepted by the assembler. Only the registers an opcode uses are
+///printed, and words with an unknown opcode become a `.word` directive.
+pub fn disassemble(word: u32) -> String {
+    let instruction = Instruction::new(word);
+    let name = mnemonic(instruction.opcode);
+    let (a, b, c) = (instruction.a, instruction.b.unwrap_or(0), instruction.c.unwrap_or(0));
+
+    match instruction.opcode {
+        Opcode::CMov | Opcode::Load | Opcode::Store | Opcode::Add | Opcode::Mul | Opcode::Div | Opcode::Nand =>
+            format!("{} r{}, r{}, r{}", name, a, b, c),
+        Opcode::Halt => name.to_string(),
+        Opcode::MapSegment | Opcode::LoadProgram => format!("{} r{}, r{}", name, b, c),
+        Opcode::UnmapSegment | Opcode::Output | Opcode::Input => format!("{} r{}", name, c),
+        Opcode::LoadValue => format!("{} r{}, {}", name, a, instruction.value.unwrap()),
+        Opcode::Err => format!("{} {:#010x}", name, word),
+    }
+}
+
+///Function: `listing(words: &[u32], symbols: Option<&SymbolMap>) -> String`
+///
+///This function disassembles a whole program, one instruction per line with
+///its offset and raw word. Labels and source lines from `symbols` are shown
+///when a symbol map is available.
+pub fn listing(words: &[u32], symbols: Option<&SymbolMap>) -> String {
+    let mut text = String::new();
+
+    for (offset, word) in words.iter().enumerate() {
+        text.push_str(&annotate_line(offset as u32, *word, symbols));
+        text.push('\n');
+    }
+
+    text
+}
+
+///Function: `annotate_line(offset: u32, word: u32, symbols: Option<&SymbolMap>) -> String`
+///
+///This function formats a single line of a listing, preceded by the labels
+///defined at `offset`.
+pub fn annotate_line(offset: u32, word: u32, symbols: Option<&SymbolMap>) -> String {
+    let mut text = String::new();
+    let mut source = String::new();
+
+    if let Some(symbols) = symbols {
+        for label in symbols.labels_at(offset) {
+            text.push_str(&format!("{}:\n", label));
+        }
+
+        if let Some(line) = symbols.line(offset) {
+            source = format!("  ; {}:{}", line.file, line.line);
+        }
+    }
+
+    text.push_str(&format!("{:>8}: {:08x}  {:<24}{}", offset, word, disassemble(word), source));
+
+    text.trim_end().to_string()
+}
+
+///Function: `annotated_listing(words: &[u32], symbols: Option<&SymbolMap>) -> String`
+///
+///This function disassembles a whole program like `listing`, followed on
+///each line by what `annotations` recovered about the instruction.
+pub fn annotated_listing(words: &[u32], symbols: Option<&SymbolMap>) -> String {
+    let mut text = String::new();
+
+    for ((offset, word), notes) in words.iter().enumerate().zip(annotations(words, symbols)) {
+        text.push_str(&annotate_line(offset as u32, *word, symbols));
+
+        if !notes.is_empty() {
+            text.push_str(&format!("  ; {}", notes.join(", ")));
+        }
+
+        text.push('\n');
+    }
+
+    text
+}
+
+///Function: `annotations(words: &[u32], symbols: Option<&SymbolMap>) -> Vec<Vec<String>>`
+///
+///This function runs a dataflow pass over each reachable basic block of a
+///program, starting from the register values `optimizer::analyze` knows when
+///the block starts, and returns for every word the notes it recovered: the
+///value an instruction leaves in a register when known, the target of a jump,
+///the character an `Output` prints and the string a run of them prints, and
+///the string a loop printing a segment one word at a time goes through.
+///Words outside reachable blocks get no notes.
+pub fn annotations(words: &[u32], symbols: Option<&SymbolMap>) -> Vec<Vec<String>> {
+    let instructions: Vec<Instruction> = words.iter().map(|word| Instruction::new(*word)).collect();
+    let mut notes = vec![Vec::new(); words.len()];
+    let mut states: Vec<Option<Registers>> = vec![None; words.len()];
+
+    for block in optimizer::analyze(words).blocks {
+        let mut registers = block.registers;
+        let mut run: Vec<(usize, u8)> = Vec::new();
+
+        for (index, instruction) in instructions.iter().enumerate().take(block.end).skip(block.start) {
+            states[index] = Some(registers);
+
+            match instruction.opcode {
+                Opcode::Output => match registers[instruction.c.unwrap_or(0) as usize] {
+                    Some(value) if value < 256 => {
+                        notes[index].push(format!("'{}'", (value as u8).escape_ascii()));
+                        run.push((index, value as u8));
+                    }
+                    _ => print_run(&mut notes, &mut run),
+                },
+                Opcode::LoadProgram => {
+                    for target in targets(&instructions, &states, index) {
+                        let name = symbols.and_then(|symbols| symbols.labels_at(target).next());
+
+                        notes[index].push(format!("-> {}", name.map_or(target.to_string(), str::to_string)));
+                    }
+                }
+                _ => {}
+            }
+
+            optimizer::transfer(&mut registers, instruction);
+
+            if let Some(register) = optimizer::written_register(instruction).filter(|_| instruction.opcode != Opcode::LoadValue) {
+                if let Some(value) = registers[register] {
+                    notes[index].push(format!("r{} = {}", register, number(value)));
+                }
+            }
+        }
+
+        print_run(&mut notes, &mut run);
+    }
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        if instruction.opcode != Opcode::LoadProgram || states[index].is_none() {
+            continue;
+        }
+
+        for start in targets(&instructions, &states, index).into_iter().map(|target| target as usize).filter(|start| *start <= index) {
+            if let Some(note) = string_loop(words, &instructions, &states, start, index) {
+                if !notes[start].contains(&note) {
+                    notes[start].push(note);
+                }
+            }
+        }
+    }
+
+    notes
+}
+
+///Function: `targets(instructions: &[Instruction], states: &[Option<Registers>], index: usize) -> Vec<u32>`
+///
+///This helper function returns the known targets of the `LoadProgram` at
+///`index` in segment 0: one, or two for the conditional jumps choosing their
+///target with a `CMov` right before.
+fn targets(instructions: &[Instruction], states: &[Option<Registers>], index: usize) -> Vec<u32> {
+    let (b, c) = (instructions[index].b.unwrap_or(0) as usize, instructions[index].c.unwrap_or(0));
+    let state = match &states[index] {
+        Some(state) if state[b].is_none_or(|id| id == 0) => state,
+        _ => return Vec::new(),
+    };
+
+    if let Some(target) = state[c as usize] {
+        return vec![target];
+    }
+
+    match index.checked_sub(1).map(|previous| (&states[previous], &instructions[previous])) {
+        Some((Some(state), choice)) if choice.opcode == Opcode::CMov && choice.a == c => {
+            match (state[choice.a as usize], state[choice.b.unwrap_or(0) as usize]) {
+                (Some(kept), Some(moved)) => vec![kept, moved],
+                _ => Vec::new(),
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+///Function: `string_loop(words: &[u32], instructions: &[Instruction], states: &[Option<Registers>], start: usize, end: usize) -> Option<String>`
+///
+///This helper function recognizes the loop from `start` to the jump back at
+///`end` as printing a string when it loads `r[x] := r[s][r[i]]`, outputs
+///`r[x]` and adds 1 to `r[i]`. When segment `s` is segment 0 and `r[i]` is
+///known on entering the loop, the string is recovered from `words`.
+fn string_loop(words: &[u32], instructions: &[Instruction], states: &[Option<Registers>], start: usize, end: usize) -> Option<String> {
+    let body = &instructions[start..=end];
+    let writes = |register: u32| {
+        move |instruction: &&Instruction| optimizer::written_register(instruction) == Some(register as usize)
+    };
+    //Every write to `r[i]` adds 1 to it
+    let increments = |i: u32| {
+        let mut additions = body.iter().enumerate().filter(|(_, instruction)| writes(i)(instruction)).peekable();
+
+        additions.peek().is_some() && additions.all(|(offset, instruction)| {
+            let operands = [instruction.b.unwrap_or(0), instruction.c.unwrap_or(0)];
+            let known = |register: u32| states[start + offset].and_then(|state| state[register as usize]);
+
+            instruction.opcode == Opcode::Add
+                && ((operands[0] == i && known(operands[1]) == Some(1)) || (operands[1] == i && known(operands[0]) == Some(1)))
+        })
+    };
+    let (s, i) = body.iter().enumerate().find_map(|(load, instruction)| {
+        let (x, s, i) = (instruction.a, instruction.b?, instruction.c?);
+        let printed = body[load + 1..]
+            .iter()
+            .take_while(|instruction| !writes(x)(instruction))
+            .any(|instruction| instruction.opcode == Opcode::Output && instruction.c == Some(x));
+
+        (instruction.opcode == Opcode::Load && printed && increments(i)).then_some((s, i))
+    })?;
+
+    //The registers on entering the loop, falling from the instruction before
+    let entering = start.checked_sub(1).filter(|previous| !optimizer::ends_block(&instructions[*previous])).and_then(|previous| {
+        let mut registers = states[previous]?;
+
+        optimizer::transfer(&mut registers, &instructions[previous]);
+        Some(registers)
+    });
+
+    match entering.map(|registers| (registers[s as usize], registers[i as usize])) {
+        Some((Some(0), Some(offset))) => {
+            let bytes: Vec<u8> = words
+                .iter()
+                .skip(offset as usize)
+                .take(MAX_STRING)
+                .take_while(|word| **word != 0 && **word < 256)
+                .map(|word| *word as u8)
+                .collect();
+
+            Some(format!("prints \"{}\" from r{}[r{}]", bytes.escape_ascii(), s, i))
+        }
+        _ => Some(format!("prints the string at r{}[r{}]", s, i)),
+    }
+}
+
+///Function: `print_run(notes: &mut [Vec<String>], run: &mut Vec<(usize, u8)>)`
+///
+///This helper function ends a run of `Output`s of known characters, noting
+///the whole string on its last one when it printed more than one.
+fn print_run(notes: &mut [Vec<String>], run: &mut Vec<(usize, u8)>) {
+    if let [.., (last, _)] = run[..] {
+        if run.len() > 1 {
+            let bytes: Vec<u8> = run.iter().map(|(_, byte)| *byte).collect();
+
+            notes[last].push(format!("prints \"{}\"", bytes.escape_ascii()));
+        }
+    }
+
+    run.clear();
+}
+
+///Function: `number(value: u32) -> String`
+///
+///This helper function formats a register value, in hexadecimal when large.
+fn number(value: u32) -> String {
+    match value {
+        0..=0xffff => value.to_string(),
+        _ => format!("{:#x}", value),
+    }
+}
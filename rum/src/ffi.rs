@@ -0,0 +1,621 @@
+//! The C interface of the machine, for embedding it in programs written in
+//! other languages. The crate is also built as a `cdylib` exporting the
+//! `rum_*` functions below, and `include/rum.h` declares them for C.
+//!
+//! A machine is an opaque pointer owned by the caller from `rum_new` until
+//! `rum_free`. The `Input` and `Output` opcodes call back into the host through
+//! the functions given to `rum_set_io`; an input function with no data yet
+//! suspends the machine until the host calls `rum_provide_input` or has data.
+//! Stepping and running report one of the `RUM_*` states, while accessors
+//! report `RUM_OK` or `RUM_ERROR`.
+//!
+//! A machine and its snapshots share the pages of their segments through
+//! reference counts that are not atomic, so a machine and every snapshot
+//! taken from it (or restored into it) must only be used from one thread.
+//!
+//! The header is generated from this file by `header` (`rum header`), so the
+//! declarations and the doc comments of the exported items are the source of
+//! truth for both sides.
+
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::slice;
+use std::task::Poll;
+
+use crate::fault::Fault;
+use crate::rum::{Rum, State};
+use crate::um_io::UmIo;
+
+///The machine has not halted yet and can keep running.
+pub const RUM_RUNNING: c_int = 0;
+///The machine executed a `Halt` instruction.
+pub const RUM_HALTED: c_int = 1;
+///The machine faulted on the instruction at its pc; `rum_fault` says why.
+pub const RUM_FAULTED: c_int = 2;
+///The machine stopped at an `Input` instruction its input function had no
+///data for; it executes it once resumed after `rum_provide_input` or once
+///the input function has data.
+pub const RUM_NEEDS_INPUT: c_int = 3;
+///Returned by an input function that has no data yet.
+pub const RUM_NO_INPUT: c_int = -2;
+///An accessor succeeded.
+pub const RUM_OK: c_int = 0;
+///A call was given a null machine, a register past 7, or a segment word
+///that is not mapped.
+pub const RUM_ERROR: c_int = -1;
+
+///Returns the next byte of input (0 to 255), -1 at the end of input, or
+///`RUM_NO_INPUT` when there is none yet.
+pub type InputFn = Option<unsafe extern "C" fn(context: *mut c_void) -> c_int>;
+///Receives a single byte of output.
+pub type OutputFn = Option<unsafe extern "C" fn(context: *mut c_void, value: u8)>;
+
+//The preamble of the header, before the generated constants and functions
+const PREAMBLE: &str = "/* The C interface of the rum Universal Machine.
+ *
+ * Generated by `rum header` from src/ffi.rs; do not edit. */
+#ifndef RUM_H
+#define RUM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+/* A machine, created by rum_new and released by rum_free.
+ *
+ * A machine and its snapshots share the pages of their segments through
+ * reference counts that are not atomic: a machine and every snapshot taken
+ * from it or restored into it must only be used from one thread. Separate
+ * machines can run on separate threads. */
+typedef struct rum_machine rum_machine;
+
+/* A copy of a machine, taken by rum_snapshot_take and released by
+ * rum_snapshot_free. */
+typedef struct rum_snapshot rum_snapshot;
+
+/* Returns the next byte of input (0 to 255), -1 at the end of input, or
+ * RUM_NO_INPUT when there is none yet. */
+typedef int (*rum_input_fn)(void *context);
+
+/* Receives a single byte of output. */
+typedef void (*rum_output_fn)(void *context, uint8_t value);
+";
+
+const POSTAMBLE: &str = "
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUM_H */
+";
+
+///Structure: Callbacks
+///
+///The I/O of a machine embedded through the C interface. Without an input
+///function the program sees the end of input, and without an output function
+///its output is dropped.
+struct Callbacks {
+    input: InputFn,
+    output: OutputFn,
+    context: *mut c_void,
+}
+
+impl UmIo for Callbacks {
+    fn input(&mut self) -> Option<u8> {
+        let value = self.input.map_or(-1, |input| unsafe { input(self.context) });
+
+        u8::try_from(value).ok()
+    }
+
+    fn try_input(&mut self) -> Poll<Option<u8>> {
+        match self.input.map_or(-1, |input| unsafe { input(self.context) }) {
+            RUM_NO_INPUT => Poll::Pending,
+            value => Poll::Ready(u8::try_from(value).ok()),
+        }
+    }
+
+    fn output(&mut self, value: u8) {
+        if let Some(output) = self.output {
+            unsafe { output(self.context, value) }
+        }
+    }
+}
+
+///Structure: Machine
+///
+///The machine behind a `rum_machine` pointer: the `Rum` itself, its I/O
+///callbacks and the fault of the last `rum_step` or `rum_run`.
+pub struct Machine {
+    rum: Rum,
+    io: Callbacks,
+    fault: Option<Fault>,
+}
+
+///Structure: Snapshot
+///
+///The copy of a machine behind a `rum_snapshot` pointer. The I/O callbacks
+///are not part of it.
+pub struct Snapshot {
+    rum: Rum,
+}
+
+///Function: `rum_new(words: *const u32, length: usize) -> *mut Machine`
+///
+///Creates a machine running the `length` instruction words at `words`,
+///which are copied. Returns NULL when `words` is NULL but `length` is not 0.
+///
+///# Safety
+///
+///`words` must point to `length` readable words.
+#[no_mangle]
+pub unsafe extern "C" fn rum_new(words: *const u32, length: usize) -> *mut Machine {
+    if words.is_null() && length != 0 {
+        return ptr::null_mut();
+    }
+
+    let words = if length == 0 { &[] } else { slice::from_raw_parts(words, length) };
+
+    Box::into_raw(Box::new(Machine {
+        rum: Rum::new(words),
+        io: Callbacks { input: None, output: None, context: ptr::null_mut() },
+        fault: None,
+    }))
+}
+
+///Function: `rum_new_from_bytes(bytes: *const u8, length: usize) -> *mut Machine`
+///
+///Creates a machine from the contents of a `.um` file: `length` bytes
+///holding big-endian words. Returns NULL when `bytes` is NULL or `length`
+///is not a multiple of 4.
+///
+///# Safety
+///
+///`bytes` must point to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rum_new_from_bytes(bytes: *const u8, length: usize) -> *mut Machine {
+    if (bytes.is_null() && length != 0) || !length.is_multiple_of(4) {
+        return ptr::null_mut();
+    }
+
+    let bytes = if length == 0 { &[] } else { slice::from_raw_parts(bytes, length) };
+    let words: Vec<u32> = bytes.chunks_exact(4).map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]])).collect();
+
+    rum_new(words.as_ptr(), words.len())
+}
+
+///Function: `rum_free(machine: *mut Machine)`
+///
+///Releases a machine. Does nothing when `machine` is NULL.
+///
+///# Safety
+///
+///`machine` must come from `rum_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rum_free(machine: *mut Machine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+///Function: `rum_set_io(machine: *mut Machine, input: InputFn, output: OutputFn, context: *mut c_void) -> c_int`
+///
+///Sets the functions the `Input` and `Output` opcodes call, both given
+///`context`. Either may be NULL: the program then sees the end of input, or
+///its output is dropped.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and the functions must be safe
+///to call with `context` for as long as the machine runs.
+#[no_mangle]
+pub unsafe extern "C" fn rum_set_io(machine: *mut Machine, input: InputFn, output: OutputFn, context: *mut c_void) -> c_int {
+    match machine.as_mut() {
+        Some(machine) => {
+            machine.io = Callbacks { input, output, context };
+            RUM_OK
+        }
+        None => RUM_ERROR,
+    }
+}
+
+///Function: `rum_step(machine: *mut Machine) -> c_int`
+///
+///Executes a single instruction and returns `RUM_RUNNING`, `RUM_HALTED`,
+///`RUM_FAULTED` or `RUM_NEEDS_INPUT`. A faulting instruction, or an `Input`
+///without input, leaves the machine untouched.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn rum_step(machine: *mut Machine) -> c_int {
+    rum_run(machine, 1, ptr::null_mut())
+}
+
+///Function: `rum_run(machine: *mut Machine, budget: u64, executed: *mut u64) -> c_int`
+///
+///Executes at most `budget` instructions and returns `RUM_RUNNING` when
+///the budget ran out, `RUM_HALTED`, `RUM_FAULTED` or `RUM_NEEDS_INPUT`. The number of
+///instructions executed, counting the `Halt`, is stored in `executed`
+///unless it is NULL.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and `executed` NULL or
+///writable.
+#[no_mangle]
+pub unsafe extern "C" fn rum_run(machine: *mut Machine, budget: u64, executed: *mut u64) -> c_int {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return RUM_ERROR,
+    };
+    let start = machine.rum.executed();
+
+    machine.fault = None;
+
+    let status = match machine.rum.run_for(&mut machine.io, budget) {
+        Ok(State::Running) => RUM_RUNNING,
+        Ok(State::Halted) => RUM_HALTED,
+        Ok(State::NeedsInput) => RUM_NEEDS_INPUT,
+        Err(fault) => {
+            machine.fault = Some(fault);
+            RUM_FAULTED
+        }
+    };
+
+    if let Some(executed) = executed.as_mut() {
+        *executed = machine.rum.executed() - start;
+    }
+
+    status
+}
+
+///Function: `rum_provide_input(machine: *mut Machine, value: c_int) -> c_int`
+///
+///Gives `value` (0 to 255, or -1 for the end of input) to the next `Input`
+///instruction instead of calling the input function, typically after
+///`RUM_NEEDS_INPUT`. Returns `RUM_ERROR` for any other `value`.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn rum_provide_input(machine: *mut Machine, value: c_int) -> c_int {
+    let input = match value {
+        -1 => None,
+        _ => match u8::try_from(value) {
+            Ok(value) => Some(value),
+            Err(_) => return RUM_ERROR,
+        },
+    };
+
+    match machine.as_mut() {
+        Some(machine) => {
+            machine.rum.provide_input(input);
+            RUM_OK
+        }
+        None => RUM_ERROR,
+    }
+}
+
+///Function: `rum_fault(machine: *const Machine, buffer: *mut c_char, size: usize) -> usize`
+///
+///Describes the fault of the last `rum_step` or `rum_run`, writing at most
+///`size` bytes to `buffer`, NUL included. Returns the length of the whole
+///description, or 0 when there was no fault.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and `buffer` must have room for
+///`size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rum_fault(machine: *const Machine, buffer: *mut c_char, size: usize) -> usize {
+    let message = match machine.as_ref().and_then(|machine| machine.fault) {
+        Some(fault) => fault.to_string(),
+        None => String::new(),
+    };
+
+    if !buffer.is_null() && size != 0 {
+        let length = message.len().min(size - 1);
+
+        ptr::copy_nonoverlapping(message.as_ptr(), buffer.cast::<u8>(), length);
+        *buffer.add(length) = 0;
+    }
+
+    message.len()
+}
+
+///Function: `rum_pc(machine: *const Machine) -> usize`
+///
+///Returns the offset in segment 0 of the next instruction, or 0 when
+///`machine` is NULL.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn rum_pc(machine: *const Machine) -> usize {
+    machine.as_ref().map_or(0, |machine| machine.rum.pc())
+}
+
+///Function: `rum_get_register(machine: *const Machine, index: u32, value: *mut u32) -> c_int`
+///
+///Stores the value of register `index` (0 to 7) in `value`.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and `value` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn rum_get_register(machine: *const Machine, index: u32, value: *mut u32) -> c_int {
+    match (machine.as_ref(), value.as_mut()) {
+        (Some(machine), Some(value)) if index < 8 => {
+            *value = machine.rum.registers()[index as usize];
+            RUM_OK
+        }
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_set_register(machine: *mut Machine, index: u32, value: u32) -> c_int`
+///
+///Overwrites register `index` (0 to 7) with `value`.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn rum_set_register(machine: *mut Machine, index: u32, value: u32) -> c_int {
+    match machine.as_mut() {
+        Some(machine) if index < 8 => {
+            machine.rum.set_register(index as usize, value);
+            RUM_OK
+        }
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_segment_length(machine: *const Machine, segment: u32, length: *mut usize) -> c_int`
+///
+///Stores the number of words of the mapped `segment` in `length`.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and `length` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn rum_segment_length(machine: *const Machine, segment: u32, length: *mut usize) -> c_int {
+    match (machine.as_ref().and_then(|machine| mapped(machine, segment)), length.as_mut()) {
+        (Some(mapped), Some(length)) => {
+            *length = mapped;
+            RUM_OK
+        }
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_read_word(machine: *const Machine, segment: u32, offset: u32, value: *mut u32) -> c_int`
+///
+///Stores the word at `offset` in the mapped `segment` in `value`.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and `value` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn rum_read_word(machine: *const Machine, segment: u32, offset: u32, value: *mut u32) -> c_int {
+    let word = machine.as_ref().filter(|machine| mapped(machine, segment).is_some())
+        .and_then(|machine| machine.rum.segments().get_segment_value(segment as usize, offset as usize));
+
+    match (word, value.as_mut()) {
+        (Some(word), Some(value)) => {
+            *value = word;
+            RUM_OK
+        }
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_write_word(machine: *mut Machine, segment: u32, offset: u32, value: u32) -> c_int`
+///
+///Overwrites the word at `offset` in the mapped `segment` with `value`.
+///Writing to segment 0 changes the running program.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine.
+#[no_mangle]
+pub unsafe extern "C" fn rum_write_word(machine: *mut Machine, segment: u32, offset: u32, value: u32) -> c_int {
+    match machine.as_mut() {
+        Some(machine) if mapped(machine, segment).is_some() => match machine.rum.set_word(segment as usize, offset as usize, value) {
+            Ok(()) => RUM_OK,
+            Err(_) => RUM_ERROR,
+        },
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_snapshot_take(machine: *const Machine) -> *mut Snapshot`
+///
+///Copies the registers, segments and pc of a machine. Returns NULL when
+///`machine` is NULL. The snapshot shares the segments of the machine until
+///either changes them.
+///
+///# Safety
+///
+///`machine` must be NULL or a live machine, and the snapshot must only be
+///used from the thread of `machine`.
+#[no_mangle]
+pub unsafe extern "C" fn rum_snapshot_take(machine: *const Machine) -> *mut Snapshot {
+    match machine.as_ref() {
+        Some(machine) => Box::into_raw(Box::new(Snapshot { rum: machine.rum.clone() })),
+        None => ptr::null_mut(),
+    }
+}
+
+///Function: `rum_snapshot_restore(machine: *mut Machine, snapshot: *const Snapshot) -> c_int`
+///
+///Puts a machine back in the state of `snapshot`, which can be restored
+///again later. The I/O functions of the machine are kept.
+///
+///# Safety
+///
+///`machine` and `snapshot` must each be NULL or live, and be used from
+///the same thread.
+#[no_mangle]
+pub unsafe extern "C" fn rum_snapshot_restore(machine: *mut Machine, snapshot: *const Snapshot) -> c_int {
+    match (machine.as_mut(), snapshot.as_ref()) {
+        (Some(machine), Some(snapshot)) => {
+            machine.rum = snapshot.rum.clone();
+            machine.fault = None;
+            RUM_OK
+        }
+        _ => RUM_ERROR,
+    }
+}
+
+///Function: `rum_snapshot_free(snapshot: *mut Snapshot)`
+///
+///Releases a snapshot. Does nothing when `snapshot` is NULL.
+///
+///# Safety
+///
+///`snapshot` must come from `rum_snapshot_take` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rum_snapshot_free(snapshot: *mut Snapshot) {
+    if !snapshot.is_null() {
+        drop(Box::from_raw(snapshot));
+    }
+}
+
+///Function: `mapped(machine: &Machine, segment: u32) -> Option<usize>`
+///
+///This helper function returns the number of words of `segment` when it is
+///mapped.
+fn mapped(machine: &Machine, segment: u32) -> Option<usize> {
+    let segments = machine.rum.segments();
+
+    segments.allocation(segment as usize)?;
+    segments.segment_len(segment as usize)
+}
+
+///Function: `header() -> String`
+///
+///This function generates `include/rum.h` from the source of this module:
+///every `RUM_*` constant becomes a `#define` and every exported function a
+///prototype, each preceded by its doc comment.
+pub fn header() -> String {
+    let mut text = String::from(PREAMBLE);
+    let mut doc: Vec<&str> = Vec::new();
+
+    for line in include_str!("ffi.rs").lines() {
+        let line = line.trim();
+
+        if let Some(comment) = line.strip_prefix("///") {
+            doc.push(comment);
+            continue;
+        }
+
+        if let Some(constant) = line.strip_prefix("pub const RUM_") {
+            let (name, value) = constant.split_once(": c_int = ").unwrap();
+
+            text.push_str(&format!("\n{}#define RUM_{} {}\n", comment(&doc), name, match value.trim_end_matches(';') {
+                value if value.starts_with('-') => format!("({})", value),
+                value => value.to_string(),
+            }));
+        } else if let Some(function) = line.strip_prefix("pub unsafe extern \"C\" fn ") {
+            text.push_str(&format!("\n{}{};\n", comment(&doc), prototype(function)));
+        }
+
+        if line != "#[no_mangle]" {
+            doc.clear();
+        }
+    }
+
+    text.push_str(POSTAMBLE);
+    text
+}
+
+///Function: `comment(doc: &[&str]) -> String`
+///
+///This helper function turns the doc comment of an exported item into a C
+///comment, leaving out the `Function:` line and the `# Safety` heading.
+fn comment(doc: &[&str]) -> String {
+    let lines: Vec<&str> = match doc.first() {
+        Some(first) if first.starts_with("Function:") => doc[2..].to_vec(),
+        _ => doc.to_vec(),
+    };
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| match line.replace("# Safety", "Safety:").replace('`', "") {
+            line if line.is_empty() => " *".to_string(),
+            line => format!(" * {}", line),
+        })
+        .collect();
+
+    format!("/*{} */\n", lines.join("\n").trim_start_matches(" *"))
+}
+
+///Function: `prototype(function: &str) -> String`
+///
+///This helper function turns the signature of an exported function, after
+///`fn `, into a C prototype.
+fn prototype(function: &str) -> String {
+    let (name, rest) = function.split_once('(').unwrap();
+    let (parameters, rest) = rest.split_once(')').unwrap();
+    let result = rest.trim().trim_end_matches('{').trim().strip_prefix("-> ").unwrap_or("void");
+    let parameters: Vec<String> = parameters
+        .split(", ")
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| {
+            let (name, kind) = parameter.split_once(": ").unwrap();
+
+            declaration(&c_type(kind), name)
+        })
+        .collect();
+    let parameters = if parameters.is_empty() { "void".to_string() } else { parameters.join(", ") };
+
+    format!("{}({})", declaration(&c_type(result), name), parameters)
+}
+
+///Function: `declaration(kind: &str, name: &str) -> String`
+///
+///This helper function declares `name` with the C type `kind`, keeping the
+///`*` of a pointer next to the name.
+fn declaration(kind: &str, name: &str) -> String {
+    if kind.ends_with('*') {
+        format!("{}{}", kind, name)
+    } else {
+        format!("{} {}", kind, name)
+    }
+}
+
+///Function: `c_type(kind: &str) -> String`
+///
+///This helper function translates a Rust type used by the exported
+///functions into C.
+fn c_type(kind: &str) -> String {
+    if let Some(target) = kind.strip_prefix("*const ") {
+        return format!("const {} *", c_type(target));
+    }
+
+    if let Some(target) = kind.strip_prefix("*mut ") {
+        return format!("{} *", c_type(target));
+    }
+
+    match kind {
+        "Machine" => "rum_machine",
+        "Snapshot" => "rum_snapshot",
+        "InputFn" => "rum_input_fn",
+        "OutputFn" => "rum_output_fn",
+        "c_int" => "int",
+        "c_char" => "char",
+        "c_void" | "void" => "void",
+        "u8" => "uint8_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        _ => panic!("no C type for `{}`", kind),
+    }
+    .to_string()
+}
@@ -0,0 +1,85 @@
+use std::convert::TryInto;
+pub use rum_core::{rum, segment, register, um_instruction, fault, monitor, host_call};
+pub mod um_io;
+pub mod checkpoint;
+pub mod debugger;
+pub mod watchpoint;
+pub mod disassembler;
+pub mod symbols;
+pub mod coverage;
+pub mod lifetime;
+pub mod reference;
+pub mod differential;
+pub mod builder;
+pub mod assembler;
+pub mod compiler;
+pub mod object;
+pub mod stdlib;
+pub mod optimizer;
+pub mod cfg;
+pub mod concolic;
+pub mod decompiler;
+pub mod ffi;
+pub mod scheduler;
+pub mod server;
+pub mod trace;
+pub mod stats;
+
+//function take from past lab
+pub fn load_instruction(input: Option<&str>) -> Vec<u32> 
+{
+    let mut raw_reader: Box<dyn std::io::BufRead> = match input {
+        None => Box::new(std::io::BufReader::new(std::io::stdin())),
+        Some(filename) => Box::new(std::io::BufReader::new(
+            std::fs::File::open(filename).unwrap(),
+        )),
+    };
+
+    let mut buf = Vec::<u8>::new(); 
+    raw_reader.read_to_end(&mut buf).unwrap();
+
+    let instructions: Vec<u32> = buf
+        .chunks_exact(4)
+        .map(|x| u32::from_be_bytes(x.try_into().unwrap())) 
+        .collect();
+    
+    instructions
+}
+
+#[derive(Debug)]
+///Enum LoadError
+///
+///The reasons `load_program` can fail.
+pub enum LoadError {
+    ///The file could not be read.
+    Io(std::io::Error),
+    ///The file does not hold a whole number of 32-bit words.
+    Truncated { length: usize },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Truncated { length } => write!(f, "{} bytes is not a whole number of 32-bit words", length),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+///Function: `load_program(path: &str) -> Result<Vec<u32>, LoadError>`
+///
+///This function reads the big-endian words of the UM image at `path`. Unlike
+///`load_instruction`, it reports a missing file or trailing bytes instead of
+///panicking or ignoring them.
+pub fn load_program(path: &str) -> Result<Vec<u32>, LoadError>
+{
+    let bytes = std::fs::read(path).map_err(LoadError::Io)?;
+
+    if !bytes.len().is_multiple_of(4) {
+        return Err(LoadError::Truncated { length: bytes.len() });
+    }
+
+    Ok(bytes.chunks_exact(4).map(|word| u32::from_be_bytes(word.try_into().unwrap())).collect())
+}
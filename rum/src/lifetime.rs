@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::monitor::Monitor;
+use crate::rum::Rum;
+use crate::um_instruction::{Instruction, Opcode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Structure: SegmentLife
+///
+///What is known about a mapped segment: its `size`, the `allocation`
+///sequence number of the `MapSegment` that created it, the `map_pc` of that
+///instruction and the pc of the last instruction that accessed it.
+pub struct SegmentLife {
+    pub id: u32,
+    pub size: usize,
+    pub allocation: u64,
+    pub map_pc: usize,
+    pub last_access_pc: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+///Structure: LifetimeTracker
+///
+///A `Monitor` that follows every segment from the `MapSegment` that creates
+///it to the `UnmapSegment` that frees it, along with aggregate statistics.
+///Segment 0 holds the program and is not counted as a live segment.
+pub struct LifetimeTracker {
+    live: HashMap<u32, SegmentLife>,
+    live_words: usize,
+    pub maps: u64,
+    pub unmaps: u64,
+    pub peak_segments: usize,
+    pub peak_words: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: LeakReport
+///
+///Every segment still mapped when the program halted, in allocation order,
+///and the statistics of the whole run.
+pub struct LeakReport {
+    pub leaks: Vec<SegmentLife>,
+    pub maps: u64,
+    pub unmaps: u64,
+    pub peak_segments: usize,
+    pub peak_words: usize,
+}
+
+impl LifetimeTracker {
+
+    ///Function: `new() -> LifetimeTracker`
+    ///
+    ///This function creates a tracker for a machine that has no segment mapped yet.
+    pub fn new() -> LifetimeTracker {
+        LifetimeTracker::default()
+    }
+
+    ///Function: `report(&self, machine: &Rum) -> LeakReport`
+    ///
+    ///This function lists the segments `machine` still has mapped. The
+    ///machine is the authority on what is mapped; the tracker only adds the
+    ///history of each segment.
+    pub fn report(&self, machine: &Rum) -> LeakReport {
+        let segments = machine.segments();
+        let mut leaks: Vec<SegmentLife> = segments
+            .mapped()
+            .filter(|(id, _)| *id != 0)
+            .map(|(id, allocation)| match self.live.get(&(id as u32)) {
+                Some(life) if life.allocation == allocation => *life,
+                _ => SegmentLife {
+                    id: id as u32,
+                    size: segments.segment_len(id).unwrap_or(0),
+                    allocation,
+                    map_pc: 0,
+                    last_access_pc: None,
+                },
+            })
+            .collect();
+
+        leaks.sort_by_key(|life| life.allocation);
+
+        LeakReport {
+            leaks,
+            maps: self.maps,
+            unmaps: self.unmaps,
+            peak_segments: self.peak_segments,
+            peak_words: self.peak_words,
+        }
+    }
+
+    ///Function: `touch(&mut self, id: u32, pc: usize)`
+    ///
+    ///This helper function records that the instruction at `pc` accessed segment `id`.
+    fn touch(&mut self, id: u32, pc: usize) {
+        if let Some(life) = self.live.get_mut(&id) {
+            life.last_access_pc = Some(pc);
+        }
+    }
+}
+
+impl Monitor for LifetimeTracker {
+    fn before_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        let registers = machine.registers();
+
+        match instruction.opcode {
+            Opcode::Load => self.touch(registers[instruction.b.unwrap() as usize], pc),
+            Opcode::Store => self.touch(registers[instruction.a as usize], pc),
+            Opcode::LoadProgram => self.touch(registers[instruction.b.unwrap() as usize], pc),
+            _ => {}
+        }
+    }
+
+    fn after_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        match instruction.opcode {
+            Opcode::MapSegment => {
+                let id = machine.registers()[instruction.b.unwrap() as usize];
+                let segments = machine.segments();
+                let size = segments.segment_len(id as usize).unwrap_or(0);
+                let allocation = segments.allocation(id as usize).unwrap_or(0);
+
+                self.maps += 1;
+                self.live_words += size;
+                self.live.insert(id, SegmentLife { id, size, allocation, map_pc: pc, last_access_pc: None });
+                self.peak_segments = self.peak_segments.max(self.live.len());
+                self.peak_words = self.peak_words.max(self.live_words);
+            }
+            Opcode::UnmapSegment => {
+                self.unmaps += 1;
+
+                if let Some(life) = self.live.remove(&machine.registers()[instruction.c.unwrap() as usize]) {
+                    self.live_words -= life.size;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words: usize = self.leaks.iter().map(|life| life.size).sum();
+
+        writeln!(f, "leak report: {} segment(s) still mapped, {} word(s)", self.leaks.len(), words)?;
+
+        for life in &self.leaks {
+            let last_access = match life.last_access_pc {
+                Some(pc) => format!("pc {}", pc),
+                None => "never".to_string(),
+            };
+
+            writeln!(
+                f,
+                "  segment {}: {} word(s), allocation #{}, mapped at pc {}, last accessed at {}",
+                life.id, life.size, life.allocation, life.map_pc, last_access
+            )?;
+        }
+
+        write!(
+            f,
+            "maps: {}, unmaps: {}, peak live segments: {}, peak live words: {}",
+            self.maps, self.unmaps, self.peak_segments, self.peak_words
+        )
+    }
+}
@@ -1,74 +1,1054 @@
 use std::env;
+use std::fs;
+use std::io::{self, stderr, stdin, stdout, BufReader, BufWriter, ErrorKind, Read, StdoutLock, Write};
+use std::net::TcpListener;
 use std::process;
-use std::convert::TryInto;
-pub mod rum;
-pub mod segment;
-pub mod register;
-pub mod um_instruction;
-
-//function take from past lab
-pub fn load_instruction(input: Option<&str>) -> Vec<u32> 
-{
-    let mut raw_reader: Box<dyn std::io::BufRead> = match input {
-        None => Box::new(std::io::BufReader::new(std::io::stdin())),
-        Some(filename) => Box::new(std::io::BufReader::new(
-            std::fs::File::open(filename).unwrap(),
-        )),
-    };
+use std::time::Duration;
+use rum::{assembler, cfg, compiler, concolic, decompiler, disassembler, ffi, coverage::Coverage, object, optimizer, server, stdlib, differential, lifetime::LifetimeTracker, load_program, rum::{Mode, Rum, State}, fault::Fault, monitor::Monitor, stats::StatsMonitor, symbols::SymbolMap, trace::Tracer, um_io::{BufferIo, StreamIo, UmIo}};
+use rum::checkpoint::CheckpointConfig;
+use rum::debugger::{Debugger, Stop};
+use rum::watchpoint::{Access, WatchTarget, Watchpoint};
+
+//The exit status of a program that halted, faulted, was misused, could not
+//be loaded or ran out of steps, of a search of `reach` that found no input
+//and of an optimization `opt --verify` found to change the behavior
+const EXIT_HALTED: i32 = 0;
+const EXIT_FAULTED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_LOAD_ERROR: i32 = 3;
+const EXIT_LIMIT: i32 = 4;
+const EXIT_NO_SOLUTION: i32 = 5;
+const EXIT_DIVERGED: i32 = 6;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const USAGE: &str = "usage: rum [run] program.um [options] [--coverage out.lcov] [--listing out.lst] [--leak-report] [--differential] [--stats] [--stats-json stats.json]
+       rum debug program.um [options]
+       rum trace program.um [options] [-o trace.txt]
+       rum profile program.um [options] [--top n]
+       rum test program.um cases/ [options]
+       rum asm program.s [-o program.um] [--map program.map]
+       rum asm -c module.s [-o module.o]
+       rum ld main.o module.o... [--stdlib] [-o program.um] [--map program.map]
+       rum cc program.sm [-o program.um] [-S program.s]
+       rum opt program.um [-o optimized.um] [--verify] [--input file]
+       rum cfg program.um [--dot program.dot] [--listing program.lst] [--symbols program.map]
+       rum disasm program.um [--symbols program.map] [--plain]
+       rum decompile program.um [--symbols program.map] [-o program.c]
+       rum header [-o rum.h]
+       rum reach program.um target [--symbols program.map] [--runs n] [--steps n] [--solutions n] [--seed input]
+       rum serve program.um --port n [--bind address] [--max-sessions n] [--steps n] [--memory words] [--timeout seconds]
+       rum --help | --version
+
+options: [--input file] [--output file] [--steps n] [--strict] [--symbols program.map]
+exit status: 0 halted, 1 faulted or rejected source, 2 usage error, 3 file error, 4 step limit reached,
+             5 no input reaches the target, 6 optimized image diverged";
+
+const DEBUG_HELP: &str = "step [n], continue, reverse-step, reverse-continue
+break <pc|label>, delete <pc|label>
+watch <segment> <start> [end], unwatch <n>
+regs, x <segment> <offset> [count], list, help, quit";
+
+//Assembles `program.s` into `program.um` (or the `-o` file), optionally
+//writing the symbol map of the program. With `-c`, assembles a module into
+//an object file instead
+fn assemble(mut command_line: impl Iterator<Item = String>)
+{
+    let mut source_file = None;
+    let mut output_file = None;
+    let mut map_file = None;
+    let mut module = false;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "-o" => output_file = Some(value(&mut command_line)),
+            "--map" => map_file = Some(value(&mut command_line)),
+            "-c" => module = true,
+            _ => source_file = Some(argument),
+        }
+    }
 
-    let mut buf = Vec::<u8>::new(); 
-    raw_reader.read_to_end(&mut buf).unwrap();
+    let source_file = source_file.unwrap_or_else(|| usage());
+    let output_file = output_file.unwrap_or_else(|| {
+        let extension = if module { "o" } else { "um" };
+
+        std::path::Path::new(&source_file).with_extension(extension).to_string_lossy().into_owned()
+    });
+
+    let source = fs::read_to_string(&source_file).unwrap_or_else(|error| load_error(&source_file, error));
+
+    if module {
+        let unit = assembler::assemble_object(&source, &source_file).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(EXIT_FAULTED);
+        });
+
+        write_file(&output_file, object::write(&unit));
+        return;
+    }
+
+    let assembly = assembler::assemble(&source, &source_file).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(EXIT_FAULTED);
+    });
+
+    write_file(&output_file, assembler::to_bytes(&assembly.words));
+
+    if let Some(file) = map_file {
+        write_file(&file, assembly.symbols.to_string());
+    }
+}
 
-    let instructions: Vec<u32> = buf
-        .chunks_exact(4)
-        .map(|x| u32::from_be_bytes(x.try_into().unwrap())) 
+//Links object files into `program.um` (or the `-o` file), optionally writing
+//the symbol map of the program. `--stdlib` links the standard library last
+fn link(mut command_line: impl Iterator<Item = String>)
+{
+    let mut object_files = Vec::new();
+    let mut output_file = None;
+    let mut map_file = None;
+    let mut with_stdlib = false;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "-o" => output_file = Some(value(&mut command_line)),
+            "--map" => map_file = Some(value(&mut command_line)),
+            "--stdlib" => with_stdlib = true,
+            _ => object_files.push(argument),
+        }
+    }
+
+    if object_files.is_empty() {
+        usage();
+    }
+
+    let output_file = output_file.unwrap_or_else(|| {
+        std::path::Path::new(&object_files[0]).with_extension("um").to_string_lossy().into_owned()
+    });
+
+    let mut objects: Vec<(String, assembler::Unit)> = object_files
+        .into_iter()
+        .map(|file| {
+            let unit = fs::read(&file)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| object::read(&bytes))
+                .unwrap_or_else(|error| load_error(&file, error));
+
+            (file, unit)
+        })
         .collect();
-    
-    instructions
+
+    if with_stdlib {
+        objects.push((stdlib::NAME.to_string(), stdlib::unit()));
+    }
+
+    let assembly = object::link(&objects).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(EXIT_FAULTED);
+    });
+
+    write_file(&output_file, assembler::to_bytes(&assembly.words));
+
+    if let Some(file) = map_file {
+        write_file(&file, assembly.symbols.to_string());
+    }
 }
 
-fn main() 
+//Compiles `program.sm` and runs it, or writes the image to the `-o` file
+//and the generated assembly to the `-S` file
+fn compile(mut command_line: impl Iterator<Item = String>)
 {
-    //Getting arguments from the command line
-    let command_line: Vec<String> = env::args().collect();
+    let mut source_file = None;
+    let mut output_file = None;
+    let mut assembly_file = None;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "-o" => output_file = Some(value(&mut command_line)),
+            "-S" => assembly_file = Some(value(&mut command_line)),
+            _ => source_file = Some(argument),
+        }
+    }
+
+    let source_file = source_file.unwrap_or_else(|| usage());
+    let fail = |error: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}:{}", source_file, error);
+        process::exit(EXIT_FAULTED);
+    };
+
+    let source = fs::read_to_string(&source_file).unwrap_or_else(|error| load_error(&source_file, error));
+    let assembly = compiler::compile(&source).unwrap_or_else(|error| fail(&error));
+
+    if let Some(file) = assembly_file {
+        write_file(&file, &assembly);
+    }
+
+    let name = std::path::Path::new(&source_file).with_extension("s").to_string_lossy().into_owned();
+    let program = assembler::assemble(&assembly, &name).unwrap_or_else(|error| fail(&format!(" {}", error)));
+
+    if let Some(file) = output_file {
+        write_file(&file, assembler::to_bytes(&program.words));
+        return;
+    }
+
+    let mut rum = Rum::new(&program.words);
+
+    let mut io = StreamIo::new(Box::new(stdin().lock()), Box::new(Stdout(stdout().lock())));
+    let result = rum.run(&mut io);
+
+    finish(&rum, &mut io, result);
+}
+
+//Optimizes `program.um`, writing the result to the `-o` file. With
+//`--verify`, first runs the original and optimized images side by side on
+//the `--input` file (the standard input by default) and writes nothing if
+//they behave differently
+fn optimize(mut command_line: impl Iterator<Item = String>)
+{
+    let mut program_file = None;
+    let mut output_file = None;
+    let mut input_file = None;
+    let mut verify = false;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "-o" => output_file = Some(value(&mut command_line)),
+            "--input" | "-i" => input_file = Some(value(&mut command_line)),
+            "--verify" => verify = true,
+            _ => program_file = Some(argument),
+        }
+    }
+
+    let program_file = program_file.unwrap_or_else(|| usage());
+
+    let program = load(&program_file);
+    let optimized = optimizer::optimize(&program);
+
+    eprintln!("{}", optimized);
+
+    if verify {
+        let input = match &input_file {
+            Some(file) => fs::read(file).unwrap_or_else(|error| load_error(file, error)),
+            None => {
+                let mut input = Vec::new();
+                stdin().read_to_end(&mut input).unwrap_or_else(|error| load_error("input", error));
+                input
+            }
+        };
+
+        let verdict = differential::compare(&program, &optimized.words, &input, None);
+
+        eprintln!("{}", verdict);
+
+        if matches!(verdict, differential::Verdict::Diverged(_)) {
+            process::exit(EXIT_DIVERGED);
+        }
+    }
+
+    if let Some(file) = output_file {
+        write_file(&file, assembler::to_bytes(&optimized.words));
+    }
+}
+
+//Recovers the control-flow graph of `program.um`, writing it as a DOT file
+//and as a listing with block boundaries. The listing goes to the standard
+//output when neither file is asked for
+fn graph(mut command_line: impl Iterator<Item = String>)
+{
+    let mut program_file = None;
+    let mut dot_file = None;
+    let mut listing_file = None;
+    let mut symbols_file = None;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "--dot" => dot_file = Some(value(&mut command_line)),
+            "--listing" => listing_file = Some(value(&mut command_line)),
+            "--symbols" => symbols_file = Some(value(&mut command_line)),
+            _ => program_file = Some(argument),
+        }
+    }
+
+    let program_file = program_file.unwrap_or_else(|| usage());
+
+    let symbols = load_symbols(symbols_file);
+    let graph = cfg::recover(&load(&program_file), symbols.as_ref());
+
+    eprintln!("{} blocks, {} functions", graph.blocks.len(), graph.functions.len());
+
+    if let Some(file) = &dot_file {
+        write_file(file, graph.dot());
+    }
+
+    match listing_file {
+        Some(file) => write_file(&file, graph.listing()),
+        None if dot_file.is_none() => print!("{}", graph.listing()),
+        None => {}
+    }
+}
+
+//Prints the disassembly of `program.um`, annotated with the constants,
+//jump targets and strings recovered from it unless `--plain` is given
+fn disassemble(mut command_line: impl Iterator<Item = String>)
+{
+    let mut program_file = None;
+    let mut symbols_file = None;
+    let mut plain = false;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "--symbols" => symbols_file = Some(value(&mut command_line)),
+            "--plain" => plain = true,
+            _ => program_file = Some(argument),
+        }
+    }
+
+    let program_file = program_file.unwrap_or_else(|| usage());
+
+    let symbols = load_symbols(symbols_file);
+    let program = load(&program_file);
+
+    match plain {
+        true => print!("{}", disassembler::listing(&program, symbols.as_ref())),
+        false => print!("{}", disassembler::annotated_listing(&program, symbols.as_ref())),
+    }
+}
+
+//Prints `program.um` decompiled to pseudo-C, or writes it to the `-o` file
+fn decompile(mut command_line: impl Iterator<Item = String>)
+{
+    let mut program_file = None;
+    let mut symbols_file = None;
+    let mut output_file = None;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "--symbols" => symbols_file = Some(value(&mut command_line)),
+            "-o" => output_file = Some(value(&mut command_line)),
+            _ => program_file = Some(argument),
+        }
+    }
+
+    let program_file = program_file.unwrap_or_else(|| usage());
+
+    let symbols = load_symbols(symbols_file);
+    let text = decompiler::decompile(&load(&program_file), symbols.as_ref());
+
+    match output_file {
+        Some(file) => write_file(&file, text),
+        None => print!("{}", text),
+    }
+}
+
+//Prints the C header of the embedding interface, or writes it to the `-o`
+//file
+fn header(mut command_line: impl Iterator<Item = String>)
+{
+    let mut output_file = None;
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "-o" => output_file = Some(value(&mut command_line)),
+            _ => usage(),
+        }
+    }
+
+    match output_file {
+        Some(file) => write_file(&file, ffi::header()),
+        None => print!("{}", ffi::header()),
+    }
+}
+
+//Looks for inputs making `program.um` reach the `target` offset (or label,
+//with a symbol map) and prints them
+fn reach(mut command_line: impl Iterator<Item = String>)
+{
+    let mut arguments = Vec::new();
+    let mut symbols_file = None;
+    let mut options = concolic::Search::default();
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "--symbols" => symbols_file = Some(value(&mut command_line)),
+            "--runs" => options.runs = number(command_line.next()),
+            "--steps" => options.steps = number(command_line.next()),
+            "--solutions" => options.solutions = number(command_line.next()),
+            "--seed" => options.seed = value(&mut command_line).into_bytes(),
+            _ => arguments.push(argument),
+        }
+    }
+
+    let (program_file, target) = match &arguments[..] {
+        [program_file, target] => (program_file, target),
+        _ => usage(),
+    };
+
+    let symbols = load_symbols(symbols_file);
+
+    options.target = match (target.parse(), symbols.as_ref().and_then(|symbols| symbols.address_of(target))) {
+        (Ok(offset), _) => offset,
+        (Err(_), Some(offset)) => offset as usize,
+        (Err(_), None) => {
+            eprintln!("unknown target `{}`", target);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let report = concolic::search(&load(program_file), &options);
+
+    println!("{}", report);
+
+    if report.found.is_empty() {
+        process::exit(EXIT_NO_SOLUTION);
+    }
+}
+
+//Parses the value of a numeric option, which must fit its type, exiting with
+//the usage when it is missing or does not
+fn number<T: std::str::FromStr>(value: Option<String>) -> T
+{
+    value.and_then(|value| value.parse().ok()).unwrap_or_else(|| usage())
+}
+
+//Takes the value of an option, exiting with the usage when it is missing
+fn value(command_line: &mut impl Iterator<Item = String>) -> String
+{
+    command_line.next().unwrap_or_else(|| usage())
+}
+
+//Serves `program.um` on a TCP port, one machine per connection, logging the
+//sessions to stderr
+fn serve(mut command_line: impl Iterator<Item = String>)
+{
+    let mut program_file = None;
+    let mut port = None;
+    let mut bind = "0.0.0.0".to_string();
+    let mut config = server::ServeConfig::default();
+
+    while let Some(argument) = command_line.next() {
+        match argument.as_str() {
+            "--port" => port = Some(number::<u16>(command_line.next())),
+            "--bind" => bind = value(&mut command_line),
+            "--max-sessions" => config.max_sessions = number(command_line.next()),
+            "--steps" => config.max_instructions = number(command_line.next()),
+            "--memory" => config.max_words = number(command_line.next()),
+            "--timeout" => config.idle_timeout = Duration::from_secs(number(command_line.next())),
+            _ => program_file = Some(argument),
+        }
+    }
+
+    let (program_file, port) = match (program_file, port) {
+        (Some(program_file), Some(port)) => (program_file, port),
+        _ => usage(),
+    };
+
+    let program = load(&program_file);
+    let listener = TcpListener::bind((bind.as_str(), port)).unwrap_or_else(|error| {
+        eprintln!("{}:{}: {}", bind, port, error);
+        process::exit(1);
+    });
+    let mut server = server::Server::new(listener, &program, config).unwrap_or_else(|error| {
+        eprintln!("{}:{}: {}", bind, port, error);
+        process::exit(1);
+    });
+
+    match server.local_addr() {
+        Ok(address) => eprintln!("serving {} on {}", program_file, address),
+        Err(_) => eprintln!("serving {} on {}:{}", program_file, bind, port),
+    }
+
+    if let Err(error) = server.run(&mut stderr()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+//The options shared by the subcommands running a program: where its input
+//comes from and its output goes (the standard streams by default), how many
+//instructions it may execute, whether to run in `Strict` mode, and its
+//symbol map
+#[derive(Default)]
+struct RunOptions {
+    program_file: Option<String>,
+    input_file: Option<String>,
+    output_file: Option<String>,
+    steps: Option<u64>,
+    strict: bool,
+    symbols_file: Option<String>,
+}
+
+impl RunOptions {
+    //Takes `argument`, and its value from `command_line`, when it is one of
+    //the options, the first other argument being the program. Returns
+    //whether `argument` was taken
+    fn parse(&mut self, argument: &str, command_line: &mut impl Iterator<Item = String>) -> bool
+    {
+        match argument {
+            "--input" | "-i" => self.input_file = Some(value(command_line)),
+            "--output" => self.output_file = Some(value(command_line)),
+            "--steps" => self.steps = Some(number(command_line.next())),
+            "--strict" => self.strict = true,
+            "--symbols" => self.symbols_file = Some(value(command_line)),
+            _ if argument.starts_with('-') => return false,
+            _ if self.program_file.is_none() => self.program_file = Some(argument.to_string()),
+            _ => return false,
+        }
+
+        true
+    }
+
+    //Returns the program file, which is required
+    fn program_file(&self) -> &str
+    {
+        self.program_file.as_deref().unwrap_or_else(|| usage())
+    }
+
+    //Creates the machine running the program
+    fn machine(&self, program: &[u32]) -> Rum
+    {
+        let mut rum = Rum::new(program);
+
+        if self.strict {
+            rum.set_mode(Mode::Strict);
+        }
+
+        rum
+    }
+
+    //Opens the input of the program
+    fn input(&self) -> Box<dyn Read>
+    {
+        match &self.input_file {
+            Some(file) => Box::new(BufReader::new(fs::File::open(file).unwrap_or_else(|error| load_error(file, error)))),
+            None => Box::new(stdin().lock()),
+        }
+    }
+
+    //Opens the I/O of the program
+    fn io(&self) -> StreamIo
+    {
+        let output: Box<dyn Write> = match &self.output_file {
+            Some(file) => Box::new(BufWriter::new(fs::File::create(file).unwrap_or_else(|error| load_error(file, error)))),
+            None => Box::new(Stdout(stdout().lock())),
+        };
+
+        StreamIo::new(self.input(), output)
+    }
+
+    //Runs `rum` within the step limit, letting `monitor` observe it
+    fn run(&self, rum: &mut Rum, io: &mut StreamIo, monitor: Option<&mut dyn Monitor>) -> Result<State, Fault>
+    {
+        let budget = self.steps.unwrap_or(u64::MAX);
+
+        match monitor {
+            Some(monitor) => rum.run_monitored_for(io, monitor, budget),
+            None if self.steps.is_none() => rum.run(io),
+            None => rum.run_for(io, budget),
+        }
+    }
+}
+
+//The standard output of a program. Once its reader is gone, as with
+//`rum program.um | head`, nothing the program prints can be seen anymore and
+//rum exits quietly instead of running it on
+struct Stdout(StdoutLock<'static>);
+
+impl Write for Stdout {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize>
+    {
+        self.0.write(buffer).map_err(closed)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.0.flush().map_err(closed)
+    }
+}
+
+//Exits when `error` tells that the reader of the standard output is gone
+fn closed(error: io::Error) -> io::Error
+{
+    if error.kind() == ErrorKind::BrokenPipe {
+        process::exit(EXIT_HALTED);
+    }
+
+    error
+}
+
+//Prints the usage and exits
+fn usage() -> !
+{
+    eprintln!("{}", USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+//Reports a file that could not be read, created or written and exits
+fn load_error(file: &str, error: impl std::fmt::Display) -> !
+{
+    eprintln!("{}: {}", file, error);
+    process::exit(EXIT_LOAD_ERROR);
+}
+
+//Loads the UM image in `file`
+fn load(file: &str) -> Vec<u32>
+{
+    load_program(file).unwrap_or_else(|error| load_error(file, error))
+}
+
+//Loads the symbol map in `file`, if any
+fn load_symbols(file: Option<String>) -> Option<SymbolMap>
+{
+    file.map(|file| SymbolMap::load(&file).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(EXIT_LOAD_ERROR);
+    }))
+}
+
+//Reports how a run ended and exits with the matching status, once the
+//output is flushed
+fn finish(rum: &Rum, io: &mut StreamIo, result: Result<State, Fault>) -> !
+{
+    if let Err(error) = io.flush() {
+        load_error("output", error);
+    }
+
+    match result {
+        Ok(State::Halted) => process::exit(EXIT_HALTED),
+        Ok(State::Running) => {
+            eprintln!("Step limit reached at pc {}", rum.pc());
+            process::exit(EXIT_LIMIT);
+        }
+        Ok(State::NeedsInput) => unreachable!("a stream never runs out of input without ending"),
+        Err(fault) => {
+            eprintln!("Fault at pc {}: {}", rum.pc(), fault);
+            process::exit(EXIT_FAULTED);
+        }
+    }
+}
+
+//Runs `program.um`, optionally exporting its coverage, reporting the segments
+//it leaked, or checking it against the reference interpreter
+fn run(mut command_line: impl Iterator<Item = String>)
+{
+    let mut options = RunOptions::default();
+    let mut coverage_file = None;
+    let mut listing_file = None;
+    let mut leak_report = false;
+    let mut differential = false;
+    let mut stats = false;
+    let mut stats_file = None;
+
+    while let Some(argument) = command_line.next() {
+        if options.parse(&argument, &mut command_line) {
+            continue;
+        }
+
+        match argument.as_str() {
+            "--coverage" => coverage_file = Some(value(&mut command_line)),
+            "--listing" => listing_file = Some(value(&mut command_line)),
+            "--leak-report" => leak_report = true,
+            "--stats" => stats = true,
+            "--stats-json" => stats_file = Some(value(&mut command_line)),
+            "--differential" => differential = true,
+            _ => usage(),
+        }
+    }
 
     //File that will be used during runtime
-    let command_file = &command_line[1];
+    let command_file = options.program_file().to_string();
 
     //Getting the u32bit instruction word
-    let runtime_instruction = load_instruction(Some(command_file));
+    let runtime_instruction = load(&command_file);
+
+    //Running on both `Rum` and the reference interpreter, which needs
+    //the whole input up front
+    if differential {
+        let mut input = Vec::new();
+        options.input().read_to_end(&mut input).unwrap_or_else(|error| load_error("input", error));
+
+        let verdict = differential::run(&runtime_instruction, &input, options.steps);
+
+        if let differential::Verdict::Halted { output, .. } = &verdict {
+            let mut io = options.io();
+            output.iter().for_each(|value| io.output(*value));
+            if let Err(error) = io.flush() {
+                load_error("output", error);
+            }
+        }
+
+        eprintln!("{}", verdict);
+        process::exit(match verdict {
+            differential::Verdict::Halted { .. } => EXIT_HALTED,
+            differential::Verdict::Faulted { .. } => EXIT_FAULTED,
+            differential::Verdict::Diverged(_) => EXIT_FAULTED,
+            differential::Verdict::LimitReached { .. } => EXIT_LIMIT,
+        });
+    }
 
-    //Initializing a 'rum' object to begin the insturction that 
+    //Initializing a 'rum' object to begin the insturction that
     //is supposed to be emulated
-    let mut rum = rum::Rum::new(&runtime_instruction);
+    let mut rum = options.machine(&runtime_instruction);
+    let mut io = options.io();
+
+    let coverage = (coverage_file.is_some() || listing_file.is_some()).then(|| Coverage::new(&runtime_instruction));
+    let lifetimes = leak_report.then(LifetimeTracker::new);
+    let statistics = (stats || stats_file.is_some()).then(StatsMonitor::new);
+    let mut monitors = ((coverage, lifetimes), statistics);
+
+    let result = match monitors {
+        ((None, None), None) => options.run(&mut rum, &mut io, None),
+        _ => options.run(&mut rum, &mut io, Some(&mut monitors)),
+    };
+
+    let ((coverage, lifetimes), statistics) = monitors;
+
+    if let Some(coverage) = coverage {
+        let symbols = load_symbols(options.symbols_file.clone());
+
+        if let Some(file) = coverage_file {
+            write_file(&file, coverage.lcov(&command_file, symbols.as_ref()));
+        }
 
-    let mut instruction_count = 0;
+        if let Some(file) = listing_file {
+            write_file(&file, coverage.listing(symbols.as_ref()));
+        }
+    }
+
+    if let (Some(lifetimes), Ok(State::Halted)) = (lifetimes, result) {
+        eprintln!("{}", lifetimes.report(&rum));
+    }
+
+    if let Some(statistics) = statistics {
+        let report = statistics.report();
+
+        if stats {
+            eprintln!("{}", report);
+        }
+
+        if let Some(file) = stats_file {
+            write_file(&file, report.json());
+        }
+    }
+
+    finish(&rum, &mut io, result);
+}
+
+//Runs `program.um`, writing a line per instruction executed to stderr or
+//the `-o` file
+fn trace(mut command_line: impl Iterator<Item = String>)
+{
+    let mut options = RunOptions::default();
+    let mut trace_file = None;
+
+    while let Some(argument) = command_line.next() {
+        if options.parse(&argument, &mut command_line) {
+            continue;
+        }
+
+        match argument.as_str() {
+            "-o" => trace_file = Some(value(&mut command_line)),
+            _ => usage(),
+        }
+    }
+
+    let program = load(options.program_file());
+    let symbols = load_symbols(options.symbols_file.clone());
+    let out: Box<dyn Write> = match &trace_file {
+        Some(file) => Box::new(BufWriter::new(fs::File::create(file).unwrap_or_else(|error| load_error(file, error)))),
+        None => Box::new(BufWriter::new(stderr())),
+    };
+
+    let mut rum = options.machine(&program);
+    let mut io = options.io();
+    let mut tracer = Tracer::new(out, symbols.as_ref());
+
+    let result = options.run(&mut rum, &mut io, Some(&mut tracer));
+
+    tracer.finish().unwrap_or_else(|error| load_error(trace_file.as_deref().unwrap_or("trace"), error));
+    finish(&rum, &mut io, result);
+}
+
+//Resolves a breakpoint location, either an offset or a label of `symbols`
+fn location(argument: &str, symbols: Option<&SymbolMap>) -> Option<usize>
+{
+    argument.parse().ok().or_else(|| symbols.and_then(|symbols| symbols.address_of(argument)).map(|offset| offset as usize))
+}
+
+//Debugs `program.um` interactively, reading commands from stdin. The program
+//reads the `--input` file, since stdin is taken by the commands
+fn debug(mut command_line: impl Iterator<Item = String>)
+{
+    let mut options = RunOptions::default();
+
+    while let Some(argument) = command_line.next() {
+        if !options.parse(&argument, &mut command_line) {
+            usage();
+        }
+    }
+
+    let program = load(options.program_file());
+    let symbols = load_symbols(options.symbols_file.clone());
+    let input: Box<dyn Read> = match &options.input_file {
+        Some(_) => options.input(),
+        None => Box::new(std::io::empty()),
+    };
+
+    let io = StreamIo::new(input, Box::new(stdout()));
+    let mut debugger = Debugger::new(options.machine(&program), Box::new(io), CheckpointConfig::default());
+    let mut commands = stdin().lines();
 
     loop {
-        let this_instruction = rum.get_instruction(instruction_count);
-        instruction_count += 1;
-
-        match this_instruction.opcode {
-            um_instruction::Opcode::CMov => rum.conditional_move(this_instruction),
-            um_instruction::Opcode::Load => rum.segment_load(this_instruction),
-            um_instruction::Opcode::Store => rum.segment_store(this_instruction),
-            um_instruction::Opcode::Add => rum.addition(this_instruction),
-            um_instruction::Opcode::Mul => rum.multiplication(this_instruction),
-            um_instruction::Opcode::Div => rum.division(this_instruction),
-            um_instruction::Opcode::Nand => rum.bit_nand(this_instruction),
-            um_instruction::Opcode::Halt => {
-                //println!("The total number of instructions: {}", instruction_count);
-                process::exit(0);
+        eprint!("(rum) ");
+
+        let line = match commands.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |index: usize| words.get(index).and_then(|word| word.parse::<u64>().ok());
+
+        let stop = match words.as_slice() {
+            [] => continue,
+            ["step" | "s", ..] => {
+                let mut stop = Stop::Step;
+                for _ in 0..number(1).unwrap_or(1) {
+                    stop = debugger.step();
+                    if stop != Stop::Step {
+                        break;
+                    }
+                }
+                Some(stop)
+            }
+            ["continue" | "c"] => Some(debugger.continue_execution()),
+            ["reverse-step" | "rs"] => Some(debugger.reverse_step()),
+            ["reverse-continue" | "rc"] => Some(debugger.reverse_continue()),
+            ["break" | "b", target] => {
+                match location(target, symbols.as_ref()) {
+                    Some(pc) => debugger.add_breakpoint(pc),
+                    None => eprintln!("unknown location {}", target),
+                }
+                None
+            }
+            ["delete" | "d", target] => {
+                if !location(target, symbols.as_ref()).is_some_and(|pc| debugger.remove_breakpoint(pc)) {
+                    eprintln!("no breakpoint at {}", target);
+                }
+                None
             }
-            um_instruction::Opcode::MapSegment => rum.map_segment(this_instruction),
-            um_instruction::Opcode::UnmapSegment => rum.unmap_segment(this_instruction),
-            um_instruction::Opcode::Output => rum.output_program(this_instruction),
-            um_instruction::Opcode::Input => rum.user_input(this_instruction),
-            um_instruction::Opcode::LoadProgram => {
-                instruction_count = rum.load_program(this_instruction);
+            ["watch" | "w", id, start, ..] => {
+                let end = match words.get(3) {
+                    Some(end) => end.parse().ok(),
+                    None => start.parse::<u32>().ok().and_then(|start| start.checked_add(1)),
+                };
+
+                match (id.parse(), start.parse::<u32>(), end) {
+                    (Ok(id), Ok(start), Some(end)) => {
+                        let watchpoint = Watchpoint::Words { target: WatchTarget::Segment(id), start, end, access: Access::ReadWrite };
+                        eprintln!("watchpoint {}", debugger.add_watchpoint(watchpoint));
+                    }
+                    _ => eprintln!("usage: watch segment start [end]"),
+                }
+                None
+            }
+            ["unwatch", watchpoint] => {
+                if !watchpoint.parse().is_ok_and(|watchpoint| debugger.remove_watchpoint(watchpoint)) {
+                    eprintln!("no watchpoint {}", watchpoint);
+                }
+                None
+            }
+            ["regs" | "r"] => {
+                for (register, value) in debugger.machine().registers().iter().enumerate() {
+                    eprintln!("r{} = {:#010x} ({})", register, value, value);
+                }
+                None
+            }
+            ["x", id, offset, ..] => {
+                let segments = debugger.machine().segments();
+                let end = offset.parse::<usize>().ok().and_then(|offset| offset.checked_add(number(3).unwrap_or(1) as usize));
+
+                match (id.parse(), offset.parse::<usize>(), end) {
+                    (Ok(id), Ok(offset), Some(end)) => {
+                        for index in offset..end {
+                            match segments.get_segment_value(id, index) {
+                                Some(value) => eprintln!("[{}][{}] = {:#010x} ({})", id, index, value, value),
+                                None => {
+                                    eprintln!("[{}][{}] is not mapped", id, index);
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    _ => eprintln!("usage: x segment offset [count]"),
+                }
+                None
+            }
+            ["list" | "l"] => {
+                let pc = debugger.machine().pc();
+                for offset in pc.saturating_sub(5)..pc + 6 {
+                    if let Some(word) = debugger.machine().segments().fetch(offset) {
+                        let marker = if offset == pc { "=>" } else { "  " };
+                        eprintln!("{} {:>8}: {}", marker, offset, disassembler::disassemble(word));
+                    }
+                }
+                None
+            }
+            ["help" | "h"] => {
+                eprintln!("{}", DEBUG_HELP);
+                None
+            }
+            ["quit" | "q"] => break,
+            _ => {
+                eprintln!("unknown command, try help");
+                None
+            }
+        };
+
+        if let Some(stop) = stop {
+            let pc = debugger.machine().pc();
+            let word = debugger.machine().segments().fetch(pc).unwrap_or(0);
+            eprintln!("{:?} at step {}, pc {}: {}", stop, debugger.steps(), pc, disassembler::disassemble(word));
+        }
+    }
+}
+
+//Runs `program.um` and prints its most executed instructions to stderr
+fn profile(mut command_line: impl Iterator<Item = String>)
+{
+    let mut options = RunOptions::default();
+    let mut top = 20;
+
+    while let Some(argument) = command_line.next() {
+        if options.parse(&argument, &mut command_line) {
+            continue;
+        }
+
+        match argument.as_str() {
+            "--top" => top = number(command_line.next()),
+            _ => usage(),
+        }
+    }
+
+    let program = load(options.program_file());
+    let symbols = load_symbols(options.symbols_file.clone());
+
+    let mut rum = options.machine(&program);
+    let mut io = options.io();
+    let mut coverage = Coverage::new(&program);
+
+    let result = options.run(&mut rum, &mut io, Some(&mut coverage));
+
+    if let Err(error) = io.flush() {
+        load_error("output", error);
+    }
+
+    eprint!("{}", coverage.profile(top, symbols.as_ref()));
+    finish(&rum, &mut io, result);
+}
+
+//Runs `program.um` on every test case of a directory: `name.out` holds the
+//expected output of the case and `name.in`, if any, its input
+fn test(mut command_line: impl Iterator<Item = String>)
+{
+    let mut options = RunOptions::default();
+    let mut directory = None;
+
+    while let Some(argument) = command_line.next() {
+        if options.parse(&argument, &mut command_line) {
+            continue;
+        }
+
+        match argument.as_str() {
+            _ if directory.is_none() && !argument.starts_with('-') => directory = Some(argument),
+            _ => usage(),
+        }
+    }
+
+    let program = load(options.program_file());
+    let directory = directory.unwrap_or_else(|| usage());
+    let mut cases: Vec<std::path::PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|error| load_error(&directory, error))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "out"))
+        .collect();
+
+    cases.sort();
+
+    if cases.is_empty() {
+        load_error(&directory, "no test cases (name.out files)");
+    }
+
+    let mut failed = 0;
+
+    for case in &cases {
+        let name = case.file_stem().unwrap_or_default().to_string_lossy();
+        let expected = fs::read(case).unwrap_or_else(|error| load_error(&case.to_string_lossy(), error));
+        let input = fs::read(case.with_extension("in")).unwrap_or_default();
+
+        let mut rum = options.machine(&program);
+        let mut io = BufferIo::new(&input);
+        let result = rum.run_for(&mut io, options.steps.unwrap_or(u64::MAX));
+
+        let verdict = match result {
+            Ok(State::Halted) if io.output == expected => None,
+            Ok(State::Halted) => Some(format!("output differs from {}", case.display())),
+            Ok(_) => Some(format!("step limit reached at pc {}", rum.pc())),
+            Err(fault) => Some(format!("fault at pc {}: {}", rum.pc(), fault)),
+        };
+
+        match verdict {
+            None => println!("ok   {}", name),
+            Some(reason) => {
+                failed += 1;
+                println!("FAIL {}: {}", name, reason);
             }
-            um_instruction::Opcode::LoadValue => rum.load_value(this_instruction),
-            um_instruction::Opcode::Err => panic!("Unknown opcode for instruction {:?}", this_instruction),
         }
     }
-}
\ No newline at end of file
+
+    println!("{} passed, {} failed", cases.len() - failed, failed);
+
+    if failed > 0 {
+        process::exit(EXIT_FAULTED);
+    }
+}
+
+fn main()
+{
+    //Getting arguments from the command line
+    let mut command_line = env::args().skip(1).peekable();
+
+    let subcommand: fn(_) = match command_line.peek().map(String::as_str) {
+        Some("--help" | "-h") => {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        Some("--version" | "-V") => {
+            println!("rum {}", VERSION);
+            process::exit(0);
+        }
+        Some("asm") => assemble,
+        Some("ld") => link,
+        Some("cc") => compile,
+        Some("opt") => optimize,
+        Some("cfg") => graph,
+        Some("disasm") => disassemble,
+        Some("decompile") => decompile,
+        Some("header") => header,
+        Some("reach") => reach,
+        Some("serve") => serve,
+        Some("debug") => debug,
+        Some("trace") => trace,
+        Some("profile") => profile,
+        Some("test") => test,
+        Some("run") => run,
+        //Running the program is the default
+        _ => return run(command_line),
+    };
+
+    command_line.next();
+    subcommand(command_line);
+}
+
+//Writes `contents` to `file`, exiting when it cannot be written
+fn write_file(file: &str, contents: impl AsRef<[u8]>)
+{
+    fs::write(file, contents).unwrap_or_else(|error| load_error(file, error));
+}
@@ -0,0 +1,310 @@
+//! Relocatable objects and the linker.
+//!
+//! An object is an assembled `Unit` saved to a file: its words, the fixups
+//! still waiting for a label (the relocations), its labels, the labels it
+//! exports and imports, and the source line of every word. Every number is a
+//! big-endian `u32` and every string a length followed by UTF-8 bytes:
+//!
+//! ```text
+//! "UMOB" 1
+//! words:   count, word...
+//! labels:  count, (name, offset)...
+//! fixups:  count, (offset, kind, label)...     kind: 0 LoadValue, 1 Word
+//! exports: count, name...
+//! imports: count, name...
+//! files:   count, name...
+//! lines:   (file + 1 or 0, line) for every word
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use crate::assembler::{patch, Assembly, Fixup, FixupKind, Unit};
+use crate::symbols::SymbolMap;
+
+const MAGIC: &[u8; 4] = b"UMOB";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Enum LinkError
+///
+///The reasons `link` can fail. Objects are named by the name given to `link`.
+pub enum LinkError {
+    ///Two objects export the same label.
+    DuplicateSymbol { symbol: String, first: String, second: String },
+    ///An object uses a label no object exports.
+    UndefinedSymbol { symbol: String, object: String },
+    ///A label ends up past `MAX_VALUE`, so `LoadValue` cannot hold its offset.
+    OutOfRange { symbol: String, object: String },
+    ///A label or relocation lies past the end of the words of its object.
+    InvalidOffset { symbol: String, offset: u32, object: String },
+}
+
+///Function: `write(unit: &Unit) -> Vec<u8>`
+///
+///This function serializes an assembled unit into an object file.
+pub fn write(unit: &Unit) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    let mut files: Vec<&str> = Vec::new();
+
+    for (file, _) in unit.lines.iter().flatten() {
+        if !files.contains(&file.as_str()) {
+            files.push(file);
+        }
+    }
+
+    put(&mut out, VERSION);
+    put(&mut out, unit.words.len() as u32);
+    unit.words.iter().for_each(|word| put(&mut out, *word));
+
+    put(&mut out, unit.labels.len() as u32);
+    for (name, offset) in &unit.labels {
+        put_string(&mut out, name);
+        put(&mut out, *offset);
+    }
+
+    put(&mut out, unit.fixups.len() as u32);
+    for fixup in &unit.fixups {
+        put(&mut out, fixup.offset);
+        put(&mut out, (fixup.kind == FixupKind::Word) as u32);
+        put_string(&mut out, &fixup.label);
+    }
+
+    for names in [&unit.exports, &unit.imports] {
+        put(&mut out, names.len() as u32);
+        names.iter().for_each(|name| put_string(&mut out, name));
+    }
+
+    put(&mut out, files.len() as u32);
+    files.iter().for_each(|file| put_string(&mut out, file));
+
+    for line in &unit.lines {
+        match line {
+            Some((file, line)) => {
+                put(&mut out, files.iter().position(|name| name == file).unwrap() as u32 + 1);
+                put(&mut out, *line);
+            }
+            None => {
+                put(&mut out, 0);
+                put(&mut out, 0);
+            }
+        }
+    }
+
+    out
+}
+
+///Function: `read(bytes: &[u8]) -> Result<Unit, String>`
+///
+///This function reads back an object file written by `write`.
+pub fn read(bytes: &[u8]) -> Result<Unit, String> {
+    let mut reader = Reader { bytes, position: 0 };
+
+    if bytes.get(..4) != Some(&MAGIC[..]) {
+        return Err("not a UM object file".to_string());
+    }
+
+    reader.position = 4;
+
+    let version = reader.word()?;
+    if version != VERSION {
+        return Err(format!("unsupported object version {}", version));
+    }
+
+    let mut unit = Unit::default();
+
+    for _ in 0..reader.word()? {
+        unit.words.push(reader.word()?);
+    }
+
+    for _ in 0..reader.word()? {
+        let name = reader.string()?;
+        let offset = reader.word()?;
+
+        if offset as usize > unit.words.len() {
+            return Err(format!("label `{}` at offset {} is past the end of the code", name, offset));
+        }
+
+        unit.labels.push((name, offset));
+    }
+
+    for _ in 0..reader.word()? {
+        let offset = reader.word()?;
+        let kind = if reader.word()? == 0 { FixupKind::LoadValue } else { FixupKind::Word };
+
+        if offset as usize >= unit.words.len() {
+            return Err(format!("relocation at offset {} is past the end of the code", offset));
+        }
+
+        unit.fixups.push(Fixup { offset, kind, label: reader.string()? });
+    }
+
+    for _ in 0..reader.word()? {
+        unit.exports.push(reader.string()?);
+    }
+
+    for _ in 0..reader.word()? {
+        unit.imports.push(reader.string()?);
+    }
+
+    let files = (0..reader.word()?).map(|_| reader.string()).collect::<Result<Vec<String>, String>>()?;
+
+    for _ in 0..unit.words.len() {
+        let (file, line) = (reader.word()?, reader.word()?);
+
+        unit.lines.push(match file {
+            0 => None,
+            file => Some((files.get(file as usize - 1).ok_or("invalid source file index")?.clone(), line)),
+        });
+    }
+
+    Ok(unit)
+}
+
+///Function: `link(objects: &[(String, Unit)]) -> Result<Assembly, LinkError>`
+///
+///This function lays the named `objects` out one after the other, the first
+///one at offset 0 where execution starts, and patches every fixup with the
+///offset of its label: a label of the same object, or one exported by
+///another object when the label is imported.
+pub fn link(objects: &[(String, Unit)]) -> Result<Assembly, LinkError> {
+    let mut bases = Vec::new();
+    let mut exports: HashMap<&str, (u32, &str)> = HashMap::new();
+    let mut base = 0;
+
+    for (name, unit) in objects {
+        let length = unit.words.len() as u32;
+        let invalid = |symbol: &String, offset: u32| LinkError::InvalidOffset { symbol: symbol.clone(), offset, object: name.clone() };
+
+        //`read` checks this, but objects can also be built in memory
+        if let Some((label, offset)) = unit.labels.iter().find(|(_, offset)| *offset > length) {
+            return Err(invalid(label, *offset));
+        }
+
+        if let Some(fixup) = unit.fixups.iter().find(|fixup| fixup.offset >= length) {
+            return Err(invalid(&fixup.label, fixup.offset));
+        }
+
+        bases.push(base);
+
+        for export in &unit.exports {
+            let offset = match unit.labels.iter().find(|(label, _)| label == export) {
+                Some((_, offset)) => base + offset,
+                None => return Err(LinkError::UndefinedSymbol { symbol: export.clone(), object: name.clone() }),
+            };
+
+            if let Some((_, first)) = exports.insert(export, (offset, name)) {
+                return Err(LinkError::DuplicateSymbol { symbol: export.clone(), first: first.to_string(), second: name.clone() });
+            }
+        }
+
+        base += length;
+    }
+
+    let mut words = Vec::new();
+    let mut symbols = SymbolMap::new();
+
+    for ((name, unit), base) in objects.iter().zip(bases) {
+        let labels: HashMap<&str, u32> = unit.labels.iter().map(|(label, offset)| (label.as_str(), base + offset)).collect();
+        let start = words.len();
+
+        words.extend(&unit.words);
+
+        for fixup in &unit.fixups {
+            let label = fixup.label.as_str();
+            let undefined = || LinkError::UndefinedSymbol { symbol: fixup.label.clone(), object: name.clone() };
+            let value = match labels.get(label) {
+                Some(offset) => *offset,
+                None if unit.imports.iter().any(|import| import == label) => exports.get(label).ok_or_else(undefined)?.0,
+                None => return Err(undefined()),
+            };
+
+            patch(&mut words[start + fixup.offset as usize], fixup.kind, value)
+                .ok_or_else(|| LinkError::OutOfRange { symbol: fixup.label.clone(), object: name.clone() })?;
+        }
+
+        for (label, offset) in &unit.labels {
+            if !label.starts_with('.') {
+                symbols.add_label(base + offset, label);
+            }
+        }
+
+        for (offset, line) in unit.lines.iter().enumerate() {
+            if let Some((file, line)) = line {
+                symbols.add_line(base + offset as u32, file, *line);
+            }
+        }
+    }
+
+    Ok(Assembly { words, symbols })
+}
+
+///Function: `put(out: &mut Vec<u8>, word: u32)`
+///
+///This helper function appends a big-endian word.
+fn put(out: &mut Vec<u8>, word: u32) {
+    out.extend(word.to_be_bytes());
+}
+
+///Function: `put_string(out: &mut Vec<u8>, text: &str)`
+///
+///This helper function appends the length and bytes of `text`.
+fn put_string(out: &mut Vec<u8>, text: &str) {
+    put(out, text.len() as u32);
+    out.extend(text.as_bytes());
+}
+
+///Structure: Reader
+///
+///A cursor over the bytes of an object file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+
+    ///Function: `take(&mut self, count: usize) -> Result<&[u8], String>`
+    ///
+    ///This function consumes `count` bytes.
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.saturating_add(count))
+            .ok_or("truncated object file")?;
+
+        self.position += count;
+        Ok(bytes)
+    }
+
+    ///Function: `word(&mut self) -> Result<u32, String>`
+    ///
+    ///This function consumes a big-endian word.
+    fn word(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    ///Function: `string(&mut self) -> Result<String, String>`
+    ///
+    ///This function consumes a string.
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.word()? as usize;
+
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| "invalid string in object file".to_string())
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::DuplicateSymbol { symbol, first, second } =>
+                write!(f, "`{}` is exported by both {} and {}", symbol, first, second),
+            LinkError::UndefinedSymbol { symbol, object } => write!(f, "{}: `{}` is not defined by any object", object, symbol),
+            LinkError::OutOfRange { symbol, object } => write!(f, "{}: `{}` is past the reach of `loadv`", object, symbol),
+            LinkError::InvalidOffset { symbol, offset, object } =>
+                write!(f, "{}: `{}` at offset {} is past the end of the object", object, symbol, offset),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
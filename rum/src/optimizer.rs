@@ -0,0 +1,596 @@
+//! A peephole optimizer for UM images.
+//!
+//! Jumps (`LoadProgram`) go to offsets computed at runtime, so the optimizer
+//! never moves an instruction: an instruction it removes becomes the no-op
+//! `cmov r0, r0, r0` and every jump target stays where it was. It splits the
+//! reachable part of segment 0 into basic blocks and, within each block,
+//!
+//! * folds arithmetic on registers of known value into a `LoadValue`,
+//! * removes a `CMov` whose condition is known to be 0 (or that moves a
+//!   register into itself),
+//! * removes `Nand` pairs that cancel out (`nand rA, rA, rA` twice, or a
+//!   double negation turned into a move),
+//! * removes the instructions without side effects (`LoadValue`, `CMov`,
+//!   `Add`, `Mul`, `Nand`) whose result is overwritten before being read.
+//!
+//! A block starts at every offset a jump may reach: every jump target computed
+//! from known register values and, when some jump has a target only known at
+//! runtime, every `LoadValue` value or data word that is an offset of segment
+//! 0 (a potential target). Known register values flow from block to block, along
+//! the fall-throughs and the jumps to known targets. A jump to a target only
+//! known at runtime (like `ret`) may enter any block starting at a potential
+//! target, with only the registers no instruction writes known (still 0,
+//! unless the program also runs code from other segments).
+//!
+//! A jump to a target only known at runtime may also land past these potential
+//! targets, in the middle of a block (a computed jump into a table), with any
+//! register values. Once the program makes such a jump, the optimizer only
+//! keeps the rewrites that hold wherever execution enters a block: no
+//! instruction is folded using register values or rewritten together with the
+//! next one, and only dead instructions are removed.
+//!
+//! Code is only rewritten once known to be reachable: from offset 0, through
+//! fall-throughs and jumps to known targets and, once the program jumps to
+//! targets only known at runtime, through the constants it stores that point
+//! just past a `LoadProgram` (like the return offsets `call` pushes).
+//!
+//! Like any binary rewriter, the optimizer relies on the program not reading
+//! or writing the instructions it executes through addresses only known at
+//! runtime. Reads and writes to constant offsets of segment 0 are detected:
+//! the words read are left alone, and an image writing its own instructions is
+//! left untouched.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use crate::um_instruction::{Instruction, Opcode, MAX_VALUE};
+
+///The word an instruction is replaced by to remove it: `cmov r0, r0, r0`.
+pub const NOP: u32 = 0;
+
+//Rewriting passes are repeated until nothing changes, up to this many times
+const MAX_PASSES: usize = 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+///Structure: Stats
+///
+///The number of instructions rewritten, by kind of rewrite.
+pub struct Stats {
+    pub dead: usize,
+    pub conditional_moves: usize,
+    pub nands: usize,
+    pub folded: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Optimized
+///
+///The optimized image, what was rewritten, the number of basic blocks and of
+///instructions found reachable, and why the image was left untouched if it
+///was.
+pub struct Optimized {
+    pub words: Vec<u32>,
+    pub stats: Stats,
+    pub blocks: usize,
+    pub reachable: usize,
+    pub skipped: Option<String>,
+}
+
+///Known register values: `None` when the value is unknown.
+pub type Registers = [Option<u32>; 8];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Block
+///
+///A reachable basic block `start..end` of segment 0: the register values
+///known whenever it starts, the offsets of the blocks it may continue with,
+///and whether it ends jumping to an offset only known at runtime.
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub registers: Registers,
+    pub successors: Vec<usize>,
+    pub unknown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: Analysis
+///
+///The reachable blocks of an image, in order, the offsets of segment 0 it
+///reads and writes at known addresses, and the offsets just past a
+///`LoadProgram` it stores (like the return offsets `call` pushes).
+pub struct Analysis {
+    pub blocks: Vec<Block>,
+    pub reads: HashSet<usize>,
+    pub writes: HashSet<usize>,
+    pub returns: BTreeSet<usize>,
+}
+
+///Function: `optimize(program: &[u32]) -> Optimized`
+///
+///This function optimizes a UM image.
+pub fn optimize(program: &[u32]) -> Optimized {
+    let mut words = program.to_vec();
+    let Analysis { blocks, reads, writes, .. } = analyze(program);
+    let reachable: usize = blocks.iter().map(|block| block.end - block.start).sum();
+    let mut optimized = Optimized { words: words.clone(), stats: Stats::default(), blocks: blocks.len(), reachable, skipped: None };
+
+    if let Some(offset) = writes.iter().find(|offset| blocks.iter().any(|block| (block.start..block.end).contains(*offset))) {
+        optimized.skipped = Some(format!("the program writes its own code at offset {}", offset));
+        return optimized;
+    }
+
+    let computed = blocks.iter().any(|block| block.unknown);
+
+    for _ in 0..MAX_PASSES {
+        let before = optimized.stats;
+
+        for block in &blocks {
+            if computed {
+                for index in block.start..block.end {
+                    forward(&mut words, index, index + 1, [None; 8], &reads, &mut optimized.stats);
+                }
+            } else {
+                forward(&mut words, block.start, block.end, block.registers, &reads, &mut optimized.stats);
+            }
+
+            backward(&mut words, block.start, block.end, &reads, &mut optimized.stats);
+        }
+
+        if optimized.stats == before {
+            break;
+        }
+    }
+
+    optimized.words = words;
+    optimized
+}
+
+///Function: `analyze(program: &[u32]) -> Analysis`
+///
+///This function finds the reachable basic blocks of `program`, the register
+///values known when each starts and where each may jump, and the offsets of
+///segment 0 the program reads and writes at known addresses.
+pub fn analyze(program: &[u32]) -> Analysis {
+    if program.is_empty() {
+        return Analysis { blocks: Vec::new(), reads: HashSet::new(), writes: HashSet::new(), returns: BTreeSet::new() };
+    }
+
+    let instructions: Vec<Instruction> = program.iter().map(|word| Instruction::new(*word)).collect();
+    let mut written = [false; 8];
+    let mut code = HashSet::new();
+
+    //Data words decode as instructions too, so the registers never written
+    //are those no reachable block writes, found by assuming none is written
+    //and exploring again until every written register is accounted for.
+    //Likewise, the words of reachable blocks are not potential targets
+    loop {
+        let pointers = pointers(program, &instructions, &code);
+        let analysis = explore(program, &instructions, entry(&instructions, written), &pointers);
+        let (mut reached, mut covered) = (written, code.clone());
+
+        for block in &analysis.blocks {
+            for register in instructions[block.start..block.end].iter().filter_map(written_register) {
+                reached[register] = true;
+            }
+
+            covered.extend(block.start..block.end);
+        }
+
+        if reached == written && covered == code {
+            return analysis;
+        }
+
+        (written, code) = (reached, covered);
+    }
+}
+
+///Function: `pointers(program: &[u32], instructions: &[Instruction], code: &HashSet<usize>) -> BTreeSet<usize>`
+///
+///This function returns the offsets a jump to an unknown target may go to:
+///the values loaded into a register stored before it changes, like return
+///offsets, when they follow an instruction ending a block, and the words
+///outside `code` that are offsets.
+fn pointers(program: &[u32], instructions: &[Instruction], code: &HashSet<usize>) -> BTreeSet<usize> {
+    let mut pointers = BTreeSet::new();
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        if let (Opcode::LoadValue, Some(value)) = (instruction.opcode, instruction.value) {
+            let register = instruction.a;
+            let stored = instructions[index + 1..]
+                .iter()
+                .take_while(|next| !ends_block(next))
+                .find(|next| next.opcode == Opcode::Store || written_register(next) == Some(register as usize))
+                .is_some_and(|next| next.opcode == Opcode::Store && next.c == Some(register));
+
+            if stored && (value == 0 || instructions.get(value as usize - 1).is_some_and(ends_block)) {
+                pointers.insert(value as usize);
+            }
+        }
+
+        if !code.contains(&index) {
+            pointers.insert(program[index] as usize);
+        }
+    }
+
+    pointers.retain(|pointer| *pointer < program.len());
+    pointers
+}
+
+///Function: `explore(program: &[u32], instructions: &[Instruction], entry: Registers, pointers: &BTreeSet<usize>) -> Analysis`
+///
+///This function does the work of `analyze`, entering the blocks reached
+///from jumps to unknown targets at `pointers` with the registers `entry`
+///knows.
+fn explore(program: &[u32], instructions: &[Instruction], entry: Registers, pointers: &BTreeSet<usize>) -> Analysis {
+    let length = program.len();
+
+    let mut leaders = BTreeSet::from([0]);
+
+    leaders.extend((0..length).filter(|index| ends_block(&instructions[*index])).map(|index| index + 1).filter(|index| *index < length));
+
+    //Jumps to known targets start new blocks, which may reveal more of them.
+    //Potential targets only start blocks once a jump to an unknown target is
+    //found
+    loop {
+        let ranges = split(instructions, &leaders);
+        let block_at = |offset: usize| ranges.binary_search_by_key(&offset, |(start, _)| *start).ok();
+        let mut states: Vec<Option<Registers>> = vec![None; ranges.len()];
+        let mut successors = vec![BTreeSet::new(); ranges.len()];
+        let mut unknown = vec![false; ranges.len()];
+        let mut targets = BTreeSet::new();
+        let mut returns = BTreeSet::new();
+        let (mut reads, mut writes) = (HashSet::new(), HashSet::new());
+        let mut work = Vec::new();
+
+        //Every register starts at 0
+        merge(&mut states, &mut work, 0, [Some(0); 8]);
+
+        loop {
+            let block = match work.pop() {
+                Some(block) => block,
+                //Once the program jumps to unknown targets, its return
+                //offsets are reachable and any potential target may be
+                //entered with only the registers never written known
+                None if unknown.contains(&true) => {
+                    for (block, (start, _)) in ranges.iter().enumerate() {
+                        if returns.contains(start) || (states[block].is_some() && pointers.contains(start)) {
+                            merge(&mut states, &mut work, block, entry);
+                        }
+                    }
+
+                    match work.pop() {
+                        Some(block) => block,
+                        None => break,
+                    }
+                }
+                None => break,
+            };
+
+            let (start, end) = ranges[block];
+            let mut registers = states[block].unwrap();
+            //The two known values a `CMov` of unknown condition left in a
+            //register, like conditional jumps do
+            let mut choices: [Option<[u32; 2]>; 8] = [None; 8];
+
+            for instruction in &instructions[start..end] {
+                let (a, b, c) = (instruction.a as usize, instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+
+                match instruction.opcode {
+                    //A segment of unknown id may be segment 0
+                    Opcode::Load if registers[b].unwrap_or(0) == 0 => {
+                        if let Some(offset) = registers[c] {
+                            reads.insert(offset as usize);
+                        }
+                    }
+                    Opcode::Store => {
+                        if let (Some(0), Some(offset)) = (registers[a], registers[b]) {
+                            writes.insert(offset as usize);
+                        }
+
+                        match registers[c] {
+                            Some(value) if value > 0 && (value as usize) < length
+                                && instructions[value as usize - 1].opcode == Opcode::LoadProgram => {
+                                returns.insert(value as usize);
+                            }
+                            _ => {}
+                        }
+                    }
+                    Opcode::LoadProgram if registers[b].is_some_and(|id| id != 0) => {}
+                    Opcode::LoadProgram => {
+                        let possible = match (registers[c], choices[c]) {
+                            (Some(target), _) => vec![target],
+                            (None, Some(choice)) => choice.to_vec(),
+                            (None, None) => {
+                                unknown[block] = true;
+                                vec![]
+                            }
+                        };
+
+                        for target in possible.into_iter().filter(|target| (*target as usize) < length) {
+                            let mut taken = registers;
+
+                            taken[c] = Some(target);
+                            targets.insert(target as usize);
+
+                            if let Some(next) = block_at(target as usize) {
+                                successors[block].insert(target as usize);
+                                merge(&mut states, &mut work, next, taken);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                let choice = match (instruction.opcode, registers[a], registers[b], registers[c]) {
+                    (Opcode::CMov, Some(kept), Some(moved), None) if kept != moved => Some([kept, moved]),
+                    _ => None,
+                };
+
+                if let Some(register) = written_register(instruction) {
+                    choices[register] = choice;
+                }
+
+                transfer(&mut registers, instruction);
+            }
+
+            if !ends_block(&instructions[end - 1]) && end < length {
+                successors[block].insert(end);
+                merge(&mut states, &mut work, block + 1, registers);
+            }
+        }
+
+        if unknown.contains(&true) && !pointers.is_subset(&leaders) {
+            leaders.extend(pointers);
+        } else if targets.is_subset(&leaders) {
+            let blocks = ranges
+                .into_iter()
+                .zip(states)
+                .zip(successors.into_iter().zip(unknown))
+                .filter_map(|(((start, end), state), (successors, unknown))| {
+                    Some(Block { start, end, registers: state?, successors: successors.into_iter().collect(), unknown })
+                })
+                .collect();
+
+            return Analysis { blocks, reads, writes, returns };
+        } else {
+            leaders.extend(targets);
+        }
+    }
+}
+
+///Function: `merge(states: &mut [Option<Registers>], work: &mut Vec<usize>, block: usize, registers: Registers)`
+///
+///This helper function adds a way to enter `block` with `registers`, keeping
+///the values known on every way, and queues the block when that changed.
+fn merge(states: &mut [Option<Registers>], work: &mut Vec<usize>, block: usize, registers: Registers) {
+    let merged = match states[block] {
+        Some(state) => {
+            let mut merged = state;
+
+            for (value, other) in merged.iter_mut().zip(registers) {
+                if *value != other {
+                    *value = None;
+                }
+            }
+
+            merged
+        }
+        None => registers,
+    };
+
+    if states[block] != Some(merged) {
+        states[block] = Some(merged);
+        work.push(block);
+    }
+}
+
+///Function: `ends_block(instruction: &Instruction) -> bool`
+///
+///This function tells whether execution never falls through `instruction`.
+pub(crate) fn ends_block(instruction: &Instruction) -> bool {
+    matches!(instruction.opcode, Opcode::Halt | Opcode::LoadProgram | Opcode::Err)
+}
+
+///Function: `entry(instructions: &[Instruction], written: [bool; 8]) -> Registers`
+///
+///This function returns the register values known at the start of every
+///block: 0 for the registers not `written`, as long as every jump stays in
+///segment 0.
+fn entry(instructions: &[Instruction], written: [bool; 8]) -> Registers {
+    let local = instructions
+        .iter()
+        .filter(|instruction| instruction.opcode == Opcode::LoadProgram)
+        .all(|instruction| !written[instruction.b.unwrap_or(0) as usize]);
+
+    written.map(|written| (local && !written).then_some(0))
+}
+
+///Function: `written_register(instruction: &Instruction) -> Option<usize>`
+///
+///This function returns the register `instruction` writes, if any.
+pub(crate) fn written_register(instruction: &Instruction) -> Option<usize> {
+    match instruction.opcode {
+        Opcode::CMov | Opcode::Load | Opcode::Add | Opcode::Mul | Opcode::Div | Opcode::Nand | Opcode::LoadValue => {
+            Some(instruction.a as usize)
+        }
+        Opcode::MapSegment => instruction.b.map(|b| b as usize),
+        Opcode::Input => instruction.c.map(|c| c as usize),
+        _ => None,
+    }
+}
+
+///Function: `split(instructions: &[Instruction], leaders: &BTreeSet<usize>) -> Vec<(usize, usize)>`
+///
+///This function splits the whole image into blocks starting at `leaders`.
+fn split(instructions: &[Instruction], leaders: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut starts: Vec<usize> = leaders.iter().copied().filter(|leader| *leader < instructions.len()).collect();
+
+    starts.dedup();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| (*start, starts.get(index + 1).copied().unwrap_or(instructions.len())))
+        .collect()
+}
+
+///Function: `fold(registers: &Registers, instruction: &Instruction) -> Option<u32>`
+///
+///This function returns the value an instruction without side effects
+///writes into `r[a]`, when it is known.
+fn fold(registers: &Registers, instruction: &Instruction) -> Option<u32> {
+    let (b, c) = (instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+
+    match instruction.opcode {
+        Opcode::LoadValue => instruction.value,
+        Opcode::CMov => match registers[c]? {
+            0 => registers[instruction.a as usize],
+            _ => registers[b],
+        },
+        Opcode::Add => Some(registers[b]?.wrapping_add(registers[c]?)),
+        Opcode::Mul => Some(registers[b]?.wrapping_mul(registers[c]?)),
+        Opcode::Div => registers[b]?.checked_div(registers[c]?),
+        Opcode::Nand => Some(!(registers[b]? & registers[c]?)),
+        _ => None,
+    }
+}
+
+///Function: `transfer(registers: &mut Registers, instruction: &Instruction)`
+///
+///This function updates the known register values past `instruction`.
+pub(crate) fn transfer(registers: &mut Registers, instruction: &Instruction) {
+    let (a, b, c) = (instruction.a as usize, instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+
+    match instruction.opcode {
+        Opcode::CMov if registers[c].is_none() && registers[a] != registers[b] => registers[a] = None,
+        Opcode::CMov if registers[c].is_none() => {}
+        Opcode::CMov | Opcode::Add | Opcode::Mul | Opcode::Div | Opcode::Nand | Opcode::LoadValue | Opcode::Load => {
+            registers[a] = fold(registers, instruction);
+        }
+        Opcode::MapSegment => registers[b] = None,
+        Opcode::Input => registers[c] = None,
+        _ => {}
+    }
+}
+
+///Function: `forward(words: &mut [u32], start: usize, end: usize, mut registers: Registers, reads: &HashSet<usize>, stats: &mut Stats)`
+///
+///This function rewrites the block `start..end` using the register values
+///known at each of its instructions, `registers` when it starts.
+fn forward(words: &mut [u32], start: usize, end: usize, mut registers: Registers, reads: &HashSet<usize>, stats: &mut Stats) {
+
+    for index in start..end {
+        let instruction = Instruction::new(words[index]);
+        let (a, b, c) = (instruction.a, instruction.b.unwrap_or(0), instruction.c.unwrap_or(0));
+
+        if words[index] == NOP || reads.contains(&index) {
+            transfer(&mut registers, &instruction);
+            continue;
+        }
+
+        let next = (index + 1 < end && !reads.contains(&(index + 1))).then(|| Instruction::new(words[index + 1]));
+
+        match instruction.opcode {
+            Opcode::CMov if a == b || registers[c as usize] == Some(0) => {
+                words[index] = NOP;
+                stats.conditional_moves += 1;
+            }
+            Opcode::Nand if b == c && next == Some(instruction) && a == b => {
+                words[index] = NOP;
+                words[index + 1] = NOP;
+                stats.nands += 2;
+            }
+            Opcode::Nand if b == c && a != b && next == Some(Instruction::nand(a, a, a)) => {
+                //r[a] := ~~r[b] is a move, done with a register known to be 0
+                //(by `Add`) or known not to be 0 (by `CMov`)
+                let zero = (0..8).find(|register| *register != a && registers[*register as usize] == Some(0));
+                let nonzero = (0..8).find(|register| *register != a && matches!(registers[*register as usize], Some(value) if value != 0));
+                let replacement = match (zero, nonzero) {
+                    (Some(zero), _) => Some(Instruction::add(a, b, zero)),
+                    (None, Some(nonzero)) => Some(Instruction::cmov(a, b, nonzero)),
+                    (None, None) => None,
+                };
+
+                if let Some(replacement) = replacement {
+                    words[index] = NOP;
+                    words[index + 1] = replacement.encode();
+                    stats.nands += 1;
+                }
+            }
+            Opcode::LoadValue => {}
+            _ => {
+                if let Some(value) = fold(&registers, &instruction).filter(|value| *value <= MAX_VALUE) {
+                    if matches!(instruction.opcode, Opcode::CMov | Opcode::Add | Opcode::Mul | Opcode::Div | Opcode::Nand) {
+                        words[index] = Instruction::load_value(a, value).unwrap().encode();
+                        stats.folded += 1;
+                    }
+                }
+            }
+        }
+
+        transfer(&mut registers, &Instruction::new(words[index]));
+    }
+}
+
+///Function: `backward(words: &mut [u32], start: usize, end: usize, reads: &HashSet<usize>, stats: &mut Stats)`
+///
+///This function removes the instructions of the block `start..end` without
+///side effects whose result is never read. Every register is considered
+///read past the block, unless it ends with `Halt`.
+fn backward(words: &mut [u32], start: usize, end: usize, reads: &HashSet<usize>, stats: &mut Stats) {
+    let mut live = if Instruction::new(words[end - 1]).opcode == Opcode::Halt { [false; 8] } else { [true; 8] };
+
+    for index in (start..end).rev() {
+        let instruction = Instruction::new(words[index]);
+        let (a, b, c) = (instruction.a as usize, instruction.b.unwrap_or(0) as usize, instruction.c.unwrap_or(0) as usize);
+
+        match instruction.opcode {
+            Opcode::LoadValue | Opcode::CMov | Opcode::Add | Opcode::Mul | Opcode::Nand
+                if !live[a] && words[index] != NOP && !reads.contains(&index) =>
+            {
+                words[index] = NOP;
+                stats.dead += 1;
+            }
+            Opcode::LoadValue => live[a] = false,
+            Opcode::CMov => {
+                live[b] = live[b] || live[a];
+                live[c] = live[c] || live[a];
+            }
+            Opcode::Add | Opcode::Mul | Opcode::Div | Opcode::Nand | Opcode::Load => {
+                live[a] = false;
+                live[b] = true;
+                live[c] = true;
+            }
+            Opcode::Input => live[c] = false,
+            Opcode::MapSegment => {
+                live[b] = false;
+                live[c] = true;
+            }
+            Opcode::Store | Opcode::LoadProgram | Opcode::UnmapSegment | Opcode::Output => {
+                live[a] = live[a] || instruction.opcode == Opcode::Store;
+                live[b] = live[b] || matches!(instruction.opcode, Opcode::Store | Opcode::LoadProgram);
+                live[c] = true;
+            }
+            Opcode::Halt | Opcode::Err => {}
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} dead instructions, {} conditional moves, {} nands removed, {} instructions folded",
+            self.dead, self.conditional_moves, self.nands, self.folded
+        )
+    }
+}
+
+impl fmt::Display for Optimized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.skipped {
+            Some(reason) => write!(f, "left untouched: {}", reason),
+            None => write!(f, "{} reachable instructions in {} blocks: {}", self.reachable, self.blocks, self.stats),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use crate::fault::Fault;
+use crate::rum::State;
+use crate::um_io::UmIo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///Structure: Effect
+///
+///What a single instruction of the `ReferenceMachine` did besides updating
+///registers: the segment word it wrote, the segment it mapped and the byte it
+///output.
+pub struct Effect {
+    pub write: Option<(u32, u32, u32)>,
+    pub mapped: Option<u32>,
+    pub output: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: ReferenceMachine
+///
+///An intentionally naive UM interpreter used to check `Rum`. It decodes
+///every word by hand, keeps segments in a `HashMap` and checks every rule of
+///the specification before doing anything, faulting on every violation.
+///Segment identifiers are chosen the way `Segment::map_segment` chooses them
+///(the most recently unmapped one first, otherwise a fresh one) so both
+///machines can be compared register by register.
+pub struct ReferenceMachine {
+    pub registers: [u32; 8],
+    pub segments: HashMap<u32, Vec<u32>>,
+    pub free: Vec<u32>,
+    pub next_id: u32,
+    pub pc: u32,
+}
+
+impl ReferenceMachine {
+
+    ///Function: `new(program: &[u32]) -> ReferenceMachine`
+    ///
+    ///This function creates a machine with `program` in segment 0.
+    pub fn new(program: &[u32]) -> ReferenceMachine {
+        let mut segments = HashMap::new();
+        segments.insert(0, program.to_vec());
+
+        ReferenceMachine { registers: [0; 8], segments, free: Vec::new(), next_id: 1, pc: 0 }
+    }
+
+    ///Function: `word(&self, id: u32, offset: u32) -> Result<u32, Fault>`
+    ///
+    ///This function reads a segment word, checking that it exists.
+    pub fn word(&self, id: u32, offset: u32) -> Result<u32, Fault> {
+        let segment = self.segments.get(&id).ok_or(Fault::UnmappedSegment { id })?;
+
+        segment.get(offset as usize).copied().ok_or(Fault::OutOfBounds { id, offset })
+    }
+
+    ///Function: `step(&mut self, io: &mut dyn UmIo) -> Result<(State, Effect), Fault>`
+    ///
+    ///This function executes the instruction at `pc`. A faulting instruction
+    ///leaves the machine untouched.
+    pub fn step(&mut self, io: &mut dyn UmIo) -> Result<(State, Effect), Fault> {
+        let pc = self.pc;
+        let word = self.word(0, pc).map_err(|_| Fault::PcOutOfBounds { pc: pc as usize })?;
+
+        let opcode = word >> 28;
+        let a = ((word >> 6) & 7) as usize;
+        let b = ((word >> 3) & 7) as usize;
+        let c = (word & 7) as usize;
+
+        let mut effect = Effect::default();
+        let mut next_pc = pc.wrapping_add(1);
+
+        match opcode {
+            0 => {
+                if self.registers[c] != 0 {
+                    self.registers[a] = self.registers[b];
+                }
+            }
+            1 => self.registers[a] = self.word(self.registers[b], self.registers[c])?,
+            2 => {
+                let (id, offset, value) = (self.registers[a], self.registers[b], self.registers[c]);
+
+                self.word(id, offset)?;
+                self.segments.get_mut(&id).unwrap()[offset as usize] = value;
+                effect.write = Some((id, offset, value));
+            }
+            3 => self.registers[a] = ((self.registers[b] as u64 + self.registers[c] as u64) % (1 << 32)) as u32,
+            4 => self.registers[a] = ((self.registers[b] as u64 * self.registers[c] as u64) % (1 << 32)) as u32,
+            5 => {
+                if self.registers[c] == 0 {
+                    return Err(Fault::DivideByZero);
+                }
+
+                self.registers[a] = self.registers[b] / self.registers[c];
+            }
+            6 => self.registers[a] = !(self.registers[b] & self.registers[c]),
+            7 => return Ok((State::Halted, effect)),
+            8 => {
+                let id = match self.free.pop() {
+                    Some(id) => id,
+                    None => {
+                        self.next_id += 1;
+                        self.next_id - 1
+                    }
+                };
+
+                self.segments.insert(id, vec![0; self.registers[c] as usize]);
+                self.registers[b] = id;
+                effect.mapped = Some(id);
+            }
+            9 => {
+                let id = self.registers[c];
+
+                if id == 0 {
+                    return Err(Fault::UnmapProgram);
+                }
+
+                self.segments.remove(&id).ok_or(Fault::UnmappedSegment { id })?;
+                self.free.push(id);
+            }
+            10 => {
+                let value = self.registers[c];
+
+                if value > 255 {
+                    return Err(Fault::OutputRange { value });
+                }
+
+                io.output(value as u8);
+                effect.output = Some(value as u8);
+            }
+            11 => {
+                self.registers[c] = match io.input() {
+                    Some(value) => value as u32,
+                    None => u32::MAX,
+                };
+            }
+            12 => {
+                let id = self.registers[b];
+
+                if id != 0 {
+                    let program = self.segments.get(&id).ok_or(Fault::UnmappedSegment { id })?.clone();
+                    self.segments.insert(0, program);
+                }
+
+                next_pc = self.registers[c];
+            }
+            13 => self.registers[((word >> 25) & 7) as usize] = word & 0x1ff_ffff,
+            _ => return Err(Fault::InvalidOpcode { pc: pc as usize, word }),
+        }
+
+        self.pc = next_pc;
+
+        Ok((State::Running, effect))
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::VecDeque;
+use std::task::Poll;
+
+use crate::fault::Fault;
+use crate::rum::{Rum, State};
+use crate::um_io::UmIo;
+
+///The index of a machine in its `Scheduler`, handed out by `spawn`.
+pub type MachineId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Status
+///
+///Where a machine of a `Scheduler` stands. `Ready` machines take turns
+///running, a `Waiting` machine is parked on an `Input` instruction until
+///input arrives or is closed, and `Halted` and `Faulted` machines never run
+///again.
+pub enum Status {
+    Ready,
+    Waiting,
+    Halted,
+    Faulted(Fault)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///Structure: Stats
+///
+///What a machine did under the scheduler: the instructions it executed, the
+///time slices it was given, how many times it was parked waiting for input,
+///and the bytes it read and wrote.
+pub struct Stats {
+    pub instructions: u64,
+    pub slices: u64,
+    pub waits: u64,
+    pub input_bytes: u64,
+    pub output_bytes: u64
+}
+
+#[derive(Debug, Default)]
+///Structure: Pipe
+///
+///The I/O of a scheduled machine: `input` holds the bytes the host provided
+///and not read yet, `closed` is set once the host will provide no more, and
+///`output` collects what the machine wrote until the host takes it.
+struct Pipe {
+    input: VecDeque<u8>,
+    closed: bool,
+    output: Vec<u8>,
+    stats: Stats
+}
+
+impl UmIo for Pipe {
+    fn input(&mut self) -> Option<u8> {
+        let value = self.input.pop_front();
+
+        self.stats.input_bytes += value.is_some() as u64;
+
+        value
+    }
+
+    fn try_input(&mut self) -> Poll<Option<u8>> {
+        if self.input.is_empty() && !self.closed {
+            return Poll::Pending;
+        }
+
+        Poll::Ready(self.input())
+    }
+
+    fn output(&mut self, value: u8) {
+        self.stats.output_bytes += 1;
+        self.output.push(value);
+    }
+}
+
+#[derive(Debug)]
+///Structure: Session
+///
+///A machine of the scheduler along with its I/O and its `Status`.
+struct Session {
+    machine: Rum,
+    pipe: Pipe,
+    status: Status
+}
+
+#[derive(Debug)]
+///Structure: Scheduler
+///
+///The structure time-slices any number of machines on the calling thread:
+///`run_slice` lets the next `Ready` machine execute at most `slice`
+///instructions before the following one gets its turn. A machine about to
+///execute `Input` with no input available is parked instead of blocking,
+///and becomes `Ready` again once the host calls `provide_input` or
+///`close_input`. The scheduler never touches the standard I/O of the
+///process, the host moves bytes in and out of the machines. The ids of
+///removed machines are handed out again by `spawn`.
+pub struct Scheduler {
+    sessions: Vec<Option<Session>>,
+    free: Vec<MachineId>,
+    ready: VecDeque<MachineId>,
+    slice: u64
+}
+
+impl Scheduler {
+
+    ///Function: `new(slice: u64) -> Scheduler`
+    ///
+    ///This function creates a scheduler without machines, giving each
+    ///machine `slice` instructions per turn (at least 1).
+    pub fn new(slice: u64) -> Scheduler
+    {
+        Scheduler {
+            sessions: Vec::new(),
+            free: Vec::new(),
+            ready: VecDeque::new(),
+            slice: slice.max(1)
+        }
+    }
+
+    ///Function: `spawn(&mut self, machine: Rum) -> MachineId`
+    ///
+    ///This function adds `machine` to the scheduler, `Ready` to run with no
+    ///input yet.
+    pub fn spawn(&mut self, machine: Rum) -> MachineId
+    {
+        let session = Some(Session { machine, pipe: Pipe::default(), status: Status::Ready });
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.sessions[id] = session;
+                id
+            }
+            None => {
+                self.sessions.push(session);
+                self.sessions.len() - 1
+            }
+        };
+
+        self.ready.push_back(id);
+
+        id
+    }
+
+    ///Function: `remove(&mut self, id: MachineId) -> Rum`
+    ///
+    ///This function takes machine `id` out of the scheduler, whatever its
+    ///`Status`, and returns it.
+    pub fn remove(&mut self, id: MachineId) -> Rum
+    {
+        let session = self.sessions.get_mut(id).and_then(Option::take)
+            .unwrap_or_else(|| panic!("no machine {}", id));
+
+        self.ready.retain(|ready| *ready != id);
+        self.free.push(id);
+
+        session.machine
+    }
+
+    ///Function: `provide_input(&mut self, id: MachineId, bytes: &[u8])`
+    ///
+    ///This function queues `bytes` for the `Input` instructions of machine
+    ///`id`, waking it if it was waiting. Input provided after `close_input`
+    ///is ignored.
+    pub fn provide_input(&mut self, id: MachineId, bytes: &[u8])
+    {
+        let session = self.session_mut(id);
+
+        if session.pipe.closed || bytes.is_empty() {
+            return;
+        }
+
+        session.pipe.input.extend(bytes);
+        self.wake(id);
+    }
+
+    ///Function: `close_input(&mut self, id: MachineId)`
+    ///
+    ///This function marks the end of the input of machine `id`: once the
+    ///queued bytes are read, `Input` fills its register with all 1's.
+    pub fn close_input(&mut self, id: MachineId)
+    {
+        self.session_mut(id).pipe.closed = true;
+        self.wake(id);
+    }
+
+    ///Function: `pending_input(&self, id: MachineId) -> usize`
+    ///
+    ///This function returns the number of bytes queued for machine `id` and
+    ///not read yet.
+    pub fn pending_input(&self, id: MachineId) -> usize
+    {
+        self.session(id).pipe.input.len()
+    }
+
+    ///Function: `take_output(&mut self, id: MachineId) -> Vec<u8>`
+    ///
+    ///This function returns the bytes machine `id` wrote since the last
+    ///call.
+    pub fn take_output(&mut self, id: MachineId) -> Vec<u8>
+    {
+        std::mem::take(&mut self.session_mut(id).pipe.output)
+    }
+
+    ///Function: `status(&self, id: MachineId) -> Status`
+    ///
+    ///This getter function returns where machine `id` stands.
+    pub fn status(&self, id: MachineId) -> Status
+    {
+        self.session(id).status
+    }
+
+    ///Function: `stats(&self, id: MachineId) -> Stats`
+    ///
+    ///This getter function returns the statistics of machine `id`.
+    pub fn stats(&self, id: MachineId) -> Stats
+    {
+        self.session(id).pipe.stats
+    }
+
+    ///Function: `machine(&self, id: MachineId) -> &Rum`
+    ///
+    ///This getter function returns machine `id` itself.
+    pub fn machine(&self, id: MachineId) -> &Rum
+    {
+        &self.session(id).machine
+    }
+
+    ///Function: `len(&self) -> usize`
+    ///
+    ///This function returns the number of machines spawned and not removed.
+    pub fn len(&self) -> usize
+    {
+        self.sessions.len() - self.free.len()
+    }
+
+    ///Function: `is_empty(&self) -> bool`
+    ///
+    ///This function tells whether the scheduler has no machine.
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    ///Function: `is_idle(&self) -> bool`
+    ///
+    ///This function tells whether no machine is `Ready`, in which case
+    ///nothing runs until the host provides input.
+    pub fn is_idle(&self) -> bool
+    {
+        self.ready.is_empty()
+    }
+
+    ///Function: `run_slice(&mut self) -> Option<MachineId>`
+    ///
+    ///This function runs the next `Ready` machine for one slice and returns
+    ///its id, or `None` when no machine is `Ready`. The machine goes back to
+    ///the end of the queue unless it halted, faulted or is now waiting for
+    ///input.
+    pub fn run_slice(&mut self) -> Option<MachineId>
+    {
+        let id = self.ready.pop_front()?;
+        let slice = self.slice;
+        let session = self.session_mut(id);
+
+        session.status = session.run(slice);
+
+        if session.status == Status::Ready {
+            self.ready.push_back(id);
+        }
+
+        Some(id)
+    }
+
+    ///Function: `run_until_idle(&mut self)`
+    ///
+    ///This function runs slices until every machine is waiting, halted or
+    ///faulted.
+    pub fn run_until_idle(&mut self)
+    {
+        while self.run_slice().is_some() {}
+    }
+
+    ///Function: `wake(&mut self, id: MachineId)`
+    ///
+    ///This helper function puts machine `id` back in the queue if it was
+    ///waiting for input.
+    fn wake(&mut self, id: MachineId)
+    {
+        let session = self.session_mut(id);
+
+        if session.status == Status::Waiting {
+            session.status = Status::Ready;
+            self.ready.push_back(id);
+        }
+    }
+
+    ///Function: `session(&self, id: MachineId) -> &Session`
+    ///
+    ///This helper function returns the session of machine `id`, panicking
+    ///when there is no such machine.
+    fn session(&self, id: MachineId) -> &Session
+    {
+        self.sessions.get(id).and_then(Option::as_ref).unwrap_or_else(|| panic!("no machine {}", id))
+    }
+
+    ///Function: `session_mut(&mut self, id: MachineId) -> &mut Session`
+    ///
+    ///This helper function is the mutable version of `session`.
+    fn session_mut(&mut self, id: MachineId) -> &mut Session
+    {
+        self.sessions.get_mut(id).and_then(Option::as_mut).unwrap_or_else(|| panic!("no machine {}", id))
+    }
+}
+
+impl Session {
+
+    ///Function: `run(&mut self, slice: u64) -> Status`
+    ///
+    ///This function runs the machine for at most `slice` instructions and
+    ///returns its new `Status`. An `Input` instruction finding no input
+    ///suspends the machine, which executes it once resumed.
+    fn run(&mut self, slice: u64) -> Status
+    {
+        let start = self.machine.executed();
+        let state = self.machine.run_for(&mut self.pipe, slice);
+
+        self.pipe.stats.slices += 1;
+        self.pipe.stats.instructions += self.machine.executed() - start;
+
+        match state {
+            Ok(State::Running) => Status::Ready,
+            Ok(State::Halted) => Status::Halted,
+            Ok(State::NeedsInput) => {
+                self.pipe.stats.waits += 1;
+                Status::Waiting
+            }
+            Err(fault) => Status::Faulted(fault),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fault::Fault;
+use crate::rum::Rum;
+use crate::scheduler::{MachineId, Scheduler, Status};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Structure: ServeConfig
+///
+///`max_sessions` is the number of connections served at once, further ones
+///being turned away. Each session ends once its machine executed
+///`max_instructions`, tried to map more than `max_words` segment words in
+///total, or waited for input longer than `idle_timeout`. Machines take turns
+///running `slice` instructions; the memory limit is checked by every
+///`MapSegment` and the other limits between turns.
+pub struct ServeConfig {
+    pub max_sessions: usize,
+    pub max_instructions: u64,
+    pub max_words: usize,
+    pub idle_timeout: Duration,
+    pub slice: u64,
+}
+
+impl Default for ServeConfig {
+    fn default() -> ServeConfig {
+        ServeConfig {
+            max_sessions: 16,
+            max_instructions: 10_000_000_000,
+            max_words: 64 * 1024 * 1024,
+            idle_timeout: Duration::from_secs(15 * 60),
+            slice: 100_000,
+        }
+    }
+}
+
+//How long `run` sleeps when no machine is ready and no socket had anything,
+//how long a closing session may take to send the rest of its output, how
+//much output a client may leave unread before its session ends, and how much
+//input a machine may leave unread before the server stops reading its socket
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_PENDING: usize = 16 * 1024 * 1024;
+const MAX_INPUT: usize = 1024 * 1024;
+
+#[derive(Debug)]
+///Structure: Connection
+///
+///A client of the server: its socket, whether the client stopped sending,
+///the output of its machine not sent yet, and what the session log needs.
+struct Connection {
+    stream: TcpStream,
+    peer: SocketAddr,
+    session: u64,
+    eof: bool,
+    pending: Vec<u8>,
+    started: Instant,
+    last_input: Instant,
+    ended: Option<String>,
+}
+
+#[derive(Debug)]
+///Structure: Server
+///
+///The structure serves a UM program over TCP: every accepted connection gets
+///a fresh machine running `program`, whose `Input` reads from the socket and
+///`Output` writes to it. Everything runs on the calling thread, the sockets
+///being non-blocking and the machines time-sliced by a `Scheduler`. The
+///machines are forks of `template`, so they share the program until they
+///modify it. Session lifecycles are logged one line at a time to the `log`
+///given to `poll` and `run`. The connections of ended sessions are kept in
+///`closing`, along with a deadline, while the rest of their output is sent.
+pub struct Server {
+    listener: TcpListener,
+    template: Rum,
+    config: ServeConfig,
+    scheduler: Scheduler,
+    connections: HashMap<MachineId, Connection>,
+    closing: Vec<(Connection, Instant)>,
+    sessions: u64,
+}
+
+impl Server {
+
+    ///Function: `new(listener: TcpListener, program: &[u32], config: ServeConfig) -> io::Result<Server>`
+    ///
+    ///This function creates a server accepting connections on `listener`.
+    pub fn new(listener: TcpListener, program: &[u32], config: ServeConfig) -> io::Result<Server>
+    {
+        listener.set_nonblocking(true)?;
+
+        let mut template = Rum::new(program);
+
+        template.set_memory_limit(config.max_words);
+
+        Ok(Server {
+            listener,
+            template,
+            config,
+            scheduler: Scheduler::new(config.slice),
+            connections: HashMap::new(),
+            closing: Vec::new(),
+            sessions: 0,
+        })
+    }
+
+    ///Function: `local_addr(&self) -> io::Result<SocketAddr>`
+    ///
+    ///This function returns the address the server listens on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr>
+    {
+        self.listener.local_addr()
+    }
+
+    ///Function: `active(&self) -> usize`
+    ///
+    ///This function returns the number of sessions in progress, leaving out
+    ///the ended ones still sending the rest of their output.
+    pub fn active(&self) -> usize
+    {
+        self.connections.len()
+    }
+
+    ///Function: `run(&mut self, log: &mut dyn Write) -> io::Result<()>`
+    ///
+    ///This function serves forever, only returning when accepting
+    ///connections fails.
+    pub fn run(&mut self, log: &mut dyn Write) -> io::Result<()>
+    {
+        loop {
+            if !self.poll(log)? {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+
+    ///Function: `poll(&mut self, log: &mut dyn Write) -> io::Result<bool>`
+    ///
+    ///This function does one round of serving without blocking: it accepts
+    ///the pending connections, feeds the machines what their clients sent,
+    ///gives every ready machine a turn, sends their output and ends the
+    ///sessions that are over. It returns whether anything happened.
+    pub fn poll(&mut self, log: &mut dyn Write) -> io::Result<bool>
+    {
+        let mut busy = self.accept(log)?;
+
+        busy |= self.drain();
+
+        for (id, connection) in self.connections.iter_mut() {
+            busy |= receive(&mut self.scheduler, *id, connection);
+        }
+
+        for _ in 0..self.connections.len() {
+            busy |= self.scheduler.run_slice().is_some();
+        }
+
+        let mut ended = Vec::new();
+
+        for (id, connection) in self.connections.iter_mut() {
+            connection.pending.extend(self.scheduler.take_output(*id));
+            busy |= send(connection);
+
+            if let Some(reason) = end_reason(&self.config, &self.scheduler, *id, connection) {
+                connection.ended = Some(reason);
+                ended.push(*id);
+            }
+        }
+
+        for id in ended {
+            let connection = self.connections.remove(&id).unwrap();
+
+            self.close(id, connection, log);
+        }
+
+        Ok(busy)
+    }
+
+    ///Function: `accept(&mut self, log: &mut dyn Write) -> io::Result<bool>`
+    ///
+    ///This helper function starts a session for every pending connection, or
+    ///turns it away when `max_sessions` are in progress.
+    fn accept(&mut self, log: &mut dyn Write) -> io::Result<bool>
+    {
+        let mut accepted = false;
+
+        loop {
+            let (mut stream, peer) = match self.listener.accept() {
+                Ok(connection) => connection,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(accepted),
+                Err(error) if error.kind() == ErrorKind::ConnectionAborted => continue,
+                Err(error) => return Err(error),
+            };
+
+            accepted = true;
+
+            if self.connections.len() >= self.config.max_sessions {
+                let _ = stream.write_all(b"server busy, try again later\n");
+                let _ = writeln!(log, "refused {}: {} sessions in progress", peer, self.connections.len());
+                continue;
+            }
+
+            if let Err(error) = stream.set_nonblocking(true) {
+                let _ = writeln!(log, "refused {}: {}", peer, error);
+                continue;
+            }
+
+            self.sessions += 1;
+
+            let now = Instant::now();
+            let id = self.scheduler.spawn(self.template.fork());
+            let connection = Connection {
+                stream,
+                peer,
+                session: self.sessions,
+                eof: false,
+                pending: Vec::new(),
+                started: now,
+                last_input: now,
+                ended: None,
+            };
+
+            let _ = writeln!(log, "session {} from {} started", connection.session, peer);
+            self.connections.insert(id, connection);
+        }
+    }
+
+    ///Function: `close(&mut self, id: MachineId, connection: Connection, log: &mut dyn Write)`
+    ///
+    ///This helper function drops machine `id`, logs how the session went and
+    ///closes the connection, or leaves it to `drain` when there is output
+    ///left to send.
+    fn close(&mut self, id: MachineId, mut connection: Connection, log: &mut dyn Write)
+    {
+        let stats = self.scheduler.stats(id);
+
+        self.scheduler.remove(id);
+
+        let _ = writeln!(
+            log,
+            "session {} from {} ended after {:.1}s: {} ({} instructions, {} bytes in, {} bytes out)",
+            connection.session,
+            connection.peer,
+            connection.started.elapsed().as_secs_f64(),
+            connection.ended.take().unwrap_or_default(),
+            stats.instructions,
+            stats.input_bytes,
+            stats.output_bytes
+        );
+
+        if connection.pending.is_empty() {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        } else {
+            let _ = connection.stream.shutdown(Shutdown::Read);
+            self.closing.push((connection, Instant::now() + FLUSH_TIMEOUT));
+        }
+    }
+
+    ///Function: `drain(&mut self) -> bool`
+    ///
+    ///This helper function sends what the socket takes of the output left
+    ///by ended sessions, closing each connection once its output is sent,
+    ///the client is gone or its deadline passed. It returns whether anything
+    ///was sent.
+    fn drain(&mut self) -> bool
+    {
+        let now = Instant::now();
+        let mut sent = false;
+
+        self.closing.retain_mut(|(connection, deadline)| {
+            sent |= send(connection);
+
+            let done = connection.pending.is_empty() || connection.ended.is_some() || now >= *deadline;
+
+            if done {
+                let _ = connection.stream.shutdown(Shutdown::Both);
+            }
+
+            !done
+        });
+
+        sent
+    }
+}
+
+///Function: `receive(scheduler: &mut Scheduler, id: MachineId, connection: &mut Connection) -> bool`
+///
+///This helper function hands machine `id` whatever its client sent, and the
+///end of its input once the client stopped sending. It stops reading while
+///the machine has `MAX_INPUT` bytes left to read, which leaves the rest to
+///TCP flow control. It returns whether anything was received.
+fn receive(scheduler: &mut Scheduler, id: MachineId, connection: &mut Connection) -> bool
+{
+    let mut buffer = [0_u8; 4096];
+    let mut received = false;
+
+    while connection.ended.is_none() && !connection.eof && scheduler.pending_input(id) < MAX_INPUT {
+        match connection.stream.read(&mut buffer) {
+            Ok(0) => {
+                connection.eof = true;
+                scheduler.close_input(id);
+            }
+            Ok(length) => {
+                scheduler.provide_input(id, &buffer[..length]);
+                connection.last_input = Instant::now();
+                received = true;
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => connection.ended = Some(format!("disconnected: {}", error)),
+        }
+    }
+
+    received
+}
+
+///Function: `send(connection: &mut Connection) -> bool`
+///
+///This helper function writes as much of the pending output as the socket
+///takes without blocking. It returns whether anything was sent.
+fn send(connection: &mut Connection) -> bool
+{
+    let mut sent = 0;
+
+    while sent < connection.pending.len() && connection.ended.is_none() {
+        match connection.stream.write(&connection.pending[sent..]) {
+            Ok(0) => connection.ended = Some("disconnected".to_string()),
+            Ok(length) => sent += length,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => connection.ended = Some(format!("disconnected: {}", error)),
+        }
+    }
+
+    connection.pending.drain(..sent);
+
+    sent > 0
+}
+
+///Function: `end_reason(config: &ServeConfig, scheduler: &Scheduler, id: MachineId, connection: &Connection) -> Option<String>`
+///
+///This helper function tells why the session of machine `id` is over,
+///or `None` while it goes on.
+fn end_reason(config: &ServeConfig, scheduler: &Scheduler, id: MachineId, connection: &Connection) -> Option<String>
+{
+    let stats = scheduler.stats(id);
+    let machine = scheduler.machine(id);
+
+    if let Some(reason) = &connection.ended {
+        return Some(reason.clone());
+    }
+
+    match scheduler.status(id) {
+        Status::Halted => Some("halted".to_string()),
+        Status::Faulted(Fault::MemoryLimit { .. }) => Some("memory limit reached".to_string()),
+        Status::Faulted(fault) => Some(format!("fault at pc {}: {}", machine.pc(), fault)),
+        _ if connection.pending.len() > MAX_PENDING => Some("output not read".to_string()),
+        _ if stats.instructions >= config.max_instructions => Some("instruction limit reached".to_string()),
+        Status::Waiting if connection.last_input.elapsed() >= config.idle_timeout => Some("idle timeout".to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::lifetime::LifetimeTracker;
+use crate::monitor::Monitor;
+use crate::rum::Rum;
+use crate::um_instruction::{Instruction, Opcode};
+
+//Every `Opcode` in encoding order, `Err` standing for opcodes 14 and 15
+const OPCODES: [Opcode; 15] = [
+    Opcode::CMov,
+    Opcode::Load,
+    Opcode::Store,
+    Opcode::Add,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Nand,
+    Opcode::Halt,
+    Opcode::MapSegment,
+    Opcode::UnmapSegment,
+    Opcode::Output,
+    Opcode::Input,
+    Opcode::LoadProgram,
+    Opcode::LoadValue,
+    Opcode::Err,
+];
+
+#[derive(Debug, Clone)]
+///Structure: StatsMonitor
+///
+///A `Monitor` gathering the statistics of a run: how many instructions of
+///each `Opcode` executed, what the `LifetimeTracker` knows about segments,
+///how `LoadProgram` was used and how many bytes went through the I/O. The
+///clock starts when the monitor is created.
+pub struct StatsMonitor {
+    opcodes: [u64; 15],
+    lifetimes: LifetimeTracker,
+    copies: u64,
+    jumps: u64,
+    input_bytes: u64,
+    output_bytes: u64,
+    start: Instant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+///Structure: RunStats
+///
+///The statistics of a run so far. `opcodes` holds the count of every
+///`Opcode` that executed at least once, in encoding order, and
+///`peak_segments` does not count segment 0.
+pub struct RunStats {
+    pub instructions: u64,
+    pub opcodes: Vec<(Opcode, u64)>,
+    pub maps: u64,
+    pub unmaps: u64,
+    pub peak_segments: usize,
+    pub peak_words: usize,
+    pub load_program_copies: u64,
+    pub load_program_jumps: u64,
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl StatsMonitor {
+
+    ///Function: `new() -> StatsMonitor`
+    ///
+    ///This function creates a monitor for a run starting now.
+    pub fn new() -> StatsMonitor {
+        StatsMonitor {
+            opcodes: [0; 15],
+            lifetimes: LifetimeTracker::new(),
+            copies: 0,
+            jumps: 0,
+            input_bytes: 0,
+            output_bytes: 0,
+            start: Instant::now(),
+        }
+    }
+
+    ///Function: `report(&self) -> RunStats`
+    ///
+    ///This function returns the statistics gathered since the monitor was created.
+    pub fn report(&self) -> RunStats {
+        RunStats {
+            instructions: self.opcodes.iter().sum(),
+            opcodes: OPCODES.iter().zip(self.opcodes).filter(|(_, count)| *count > 0).map(|(opcode, count)| (*opcode, count)).collect(),
+            maps: self.lifetimes.maps,
+            unmaps: self.lifetimes.unmaps,
+            peak_segments: self.lifetimes.peak_segments,
+            peak_words: self.lifetimes.peak_words,
+            load_program_copies: self.copies,
+            load_program_jumps: self.jumps,
+            input_bytes: self.input_bytes,
+            output_bytes: self.output_bytes,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
+
+impl Default for StatsMonitor {
+    fn default() -> StatsMonitor {
+        StatsMonitor::new()
+    }
+}
+
+impl Monitor for StatsMonitor {
+    fn before_step(&mut self, _machine: &Rum, _pc: usize, instruction: Instruction) {
+        //`Halt` never gets an `after_step`
+        if instruction.opcode == Opcode::Halt {
+            self.opcodes[Opcode::Halt as usize] += 1;
+        }
+    }
+
+    fn after_step(&mut self, machine: &Rum, pc: usize, instruction: Instruction) {
+        let registers = machine.registers();
+
+        self.opcodes[instruction.opcode as usize] += 1;
+        self.lifetimes.after_step(machine, pc, instruction);
+
+        match instruction.opcode {
+            Opcode::LoadProgram if registers[instruction.b.unwrap() as usize] == 0 => self.jumps += 1,
+            Opcode::LoadProgram => self.copies += 1,
+            Opcode::Output => self.output_bytes += 1,
+            Opcode::Input if registers[instruction.c.unwrap() as usize] != u32::MAX => self.input_bytes += 1,
+            _ => {}
+        }
+    }
+}
+
+impl RunStats {
+
+    ///Function: `instructions_per_second(&self) -> f64`
+    ///
+    ///This function returns how many instructions executed per second of
+    ///wall-clock time.
+    pub fn instructions_per_second(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            seconds if seconds > 0.0 => self.instructions as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+
+    ///Function: `json(&self) -> String`
+    ///
+    ///This function formats the statistics as a JSON object, with a key
+    ///for every `Opcode` that executed.
+    pub fn json(&self) -> String {
+        let opcodes: Vec<String> = self.opcodes.iter().map(|(opcode, count)| format!("\"{:?}\": {}", opcode, count)).collect();
+
+        format!(
+            concat!(
+                "{{\n",
+                "  \"instructions\": {},\n",
+                "  \"opcodes\": {{{}}},\n",
+                "  \"maps\": {},\n",
+                "  \"unmaps\": {},\n",
+                "  \"peak_segments\": {},\n",
+                "  \"peak_words\": {},\n",
+                "  \"load_program_copies\": {},\n",
+                "  \"load_program_jumps\": {},\n",
+                "  \"input_bytes\": {},\n",
+                "  \"output_bytes\": {},\n",
+                "  \"seconds\": {:.6},\n",
+                "  \"instructions_per_second\": {:.0}\n",
+                "}}\n"
+            ),
+            self.instructions,
+            opcodes.join(", "),
+            self.maps,
+            self.unmaps,
+            self.peak_segments,
+            self.peak_words,
+            self.load_program_copies,
+            self.load_program_jumps,
+            self.input_bytes,
+            self.output_bytes,
+            self.elapsed.as_secs_f64(),
+            self.instructions_per_second(),
+        )
+    }
+}
+
+impl fmt::Display for RunStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions: {}", self.instructions)?;
+
+        for (opcode, count) in &self.opcodes {
+            writeln!(f, "  {:<14} {:>14} {:>7.2}%", format!("{:?}", opcode), count, *count as f64 * 100.0 / self.instructions as f64)?;
+        }
+
+        writeln!(f, "maps: {}, unmaps: {}, peak live segments: {}, peak live words: {}", self.maps, self.unmaps, self.peak_segments, self.peak_words)?;
+        writeln!(f, "load program: {} segment copies, {} jumps", self.load_program_copies, self.load_program_jumps)?;
+        writeln!(f, "input: {} bytes, output: {} bytes", self.input_bytes, self.output_bytes)?;
+        write!(f, "{:.3}s, {:.0} instructions per second", self.elapsed.as_secs_f64(), self.instructions_per_second())
+    }
+}
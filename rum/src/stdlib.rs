@@ -0,0 +1,35 @@
+//! The standard library, a module of UM routines bundled with rum.
+//!
+//! | routine      | arguments                  | result                             |
+//! |--------------|----------------------------|------------------------------------|
+//! | `print_u32`  | `r1` value                 | prints it in decimal               |
+//! | `print_i32`  | `r1` value                 | prints it in signed decimal        |
+//! | `print_hex`  | `r1` value                 | prints it in hexadecimal           |
+//! | `read_line`  | `r1` segment, `r2` size    | length of the line read, or -1     |
+//! | `memcpy`     | `r1` to, `r2` from, `r3` count | copies the first `r3` words    |
+//! | `memset`     | `r1` segment, `r2` value, `r3` count | sets the first `r3` words |
+//! | `strcmp`     | `r1`, `r2` segments        | -1, 0 or 1                         |
+//! | `ult`        | `r1`, `r2`                 | `r1 < r2`, unsigned                |
+//! | `slt`, `sle`, `sgt`, `sge` | `r1`, `r2`   | `r1 < r2`, ..., signed             |
+//! | `heap_init`  | `r1` size of the arena     | segment of the arena               |
+//! | `heap_alloc` | `r1` size of the block     | offset of the block, or -1         |
+//! | `heap_free`  | `r1` offset of the block   |                                    |
+//!
+//! The comments of `stdlib.s` describe each routine in detail. Programs
+//! declare the routines they use `.extern`, call them once `initstack` has run
+//! and are linked with `unit()`, the main module first.
+
+use crate::assembler::{assemble_object, Unit};
+
+///The assembly source of the library.
+pub const SOURCE: &str = include_str!("stdlib.s");
+
+///The name of the library in symbol maps and link errors.
+pub const NAME: &str = "stdlib.s";
+
+///Function: `unit() -> Unit`
+///
+///This function returns the assembled library, ready to be linked.
+pub fn unit() -> Unit {
+    assemble_object(SOURCE, NAME).expect("the standard library assembles")
+}
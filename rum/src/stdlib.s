@@ -0,0 +1,344 @@
+; The rum standard library.
+;
+; Routines take their arguments in r1, r2 and r3 and return their result in
+; r1. They preserve every other register except the temporaries r6 and r7,
+; and need the stack set up by `initstack` in the main module.
+
+.global print_u32, print_i32, print_hex
+.global read_line
+.global memcpy, memset, strcmp
+.global ult, slt, sle, sgt, sge
+.global heap_init, heap_alloc, heap_free, heap_segment
+
+; prints r1 as an unsigned decimal number
+print_u32:
+    push r2
+    loadv r2, 10
+    call print_digits
+    pop r2
+    ret
+
+; prints r1 as a signed decimal number
+print_i32:
+    push r1
+    push r2
+    li32 r2, 0x80000000
+    div r2, r1, r2
+    jz r2, print_i32_positive
+    loadv r2, '-'
+    out r2
+    not r1, r1
+    loadv r7, 1
+    add r1, r1, r7
+print_i32_positive:
+    call print_u32
+    pop r2
+    pop r1
+    ret
+
+; prints r1 as a lower-case hexadecimal number, without a prefix
+print_hex:
+    push r2
+    loadv r2, 16
+    call print_digits
+    pop r2
+    ret
+
+; prints r1 in base r2 (at most 16); the digits are pushed from the lowest
+; and popped to be printed from the highest
+print_digits:
+    push r1
+    push r3
+    push r4
+    loadv r3, 0
+print_digits_split:
+    div r4, r1, r2
+    mul r7, r4, r2
+    nand r7, r7, r7
+    add r1, r1, r7
+    loadv r7, 1
+    add r1, r1, r7          ; r1 = the lowest digit
+    loadv r7, 10
+    div r7, r1, r7
+    loadv r6, 39            ; 'a' - '0' - 10 for the digits a to f
+    mul r7, r7, r6
+    add r1, r1, r7
+    loadv r7, '0'
+    add r1, r1, r7
+    push r1
+    loadv r7, 1
+    add r3, r3, r7
+    mov r1, r4
+    jnz r1, print_digits_split
+print_digits_print:
+    pop r1
+    out r1
+    nand r7, r0, r0
+    add r3, r3, r7
+    jnz r3, print_digits_print
+    pop r4
+    pop r3
+    pop r1
+    ret
+
+; reads a line into segment r1, which holds r2 (at least 1) words: at most
+; r2 - 1 bytes followed by 0, without the newline. r1 := the length of the
+; line, or -1 when the input ended before any byte was read
+read_line:
+    push r2
+    push r3
+    push r4
+    mov r4, r1
+    loadv r3, 0
+    nand r7, r0, r0
+    add r2, r2, r7
+read_line_loop:
+    sub r1, r2, r3
+    jz r1, read_line_done
+    in r1
+    sstore r4, r3, r1
+    loadv r7, 1
+    add r1, r1, r7
+    jz r1, read_line_end
+    sload r1, r4, r3
+    li32 r7, -10
+    add r1, r1, r7
+    jz r1, read_line_done
+    loadv r7, 1
+    add r3, r3, r7
+    jmp read_line_loop
+read_line_end:
+    jnz r3, read_line_done
+    sstore r4, r3, r0
+    nand r1, r0, r0
+    jmp read_line_return
+read_line_done:
+    sstore r4, r3, r0
+    mov r1, r3
+read_line_return:
+    pop r4
+    pop r3
+    pop r2
+    ret
+
+; copies the first r3 words of segment r2 to segment r1
+memcpy:
+    push r3
+    push r4
+memcpy_loop:
+    jz r3, memcpy_done
+    nand r7, r0, r0
+    add r3, r3, r7
+    sload r4, r2, r3
+    sstore r1, r3, r4
+    jmp memcpy_loop
+memcpy_done:
+    pop r4
+    pop r3
+    ret
+
+; sets the first r3 words of segment r1 to r2
+memset:
+    push r3
+memset_loop:
+    jz r3, memset_done
+    nand r7, r0, r0
+    add r3, r3, r7
+    sstore r1, r3, r2
+    jmp memset_loop
+memset_done:
+    pop r3
+    ret
+
+; compares the 0-terminated strings of bytes in segments r1 and r2:
+; r1 := -1, 0 or 1 when the first is smaller, equal or greater
+strcmp:
+    push r2
+    push r3
+    push r4
+    loadv r3, 0
+strcmp_loop:
+    sload r4, r1, r3
+    sload r7, r2, r3
+    nand r7, r7, r7
+    add r4, r4, r7
+    loadv r7, 1
+    add r4, r4, r7          ; r4 = the difference of the bytes
+    jnz r4, strcmp_differ
+    sload r4, r1, r3
+    jz r4, strcmp_equal
+    loadv r7, 1
+    add r3, r3, r7
+    jmp strcmp_loop
+strcmp_differ:
+    li32 r1, 0x80000000
+    div r1, r4, r1          ; 1 when the first byte is smaller
+    add r1, r1, r1
+    not r1, r1
+    loadv r7, 2
+    add r1, r1, r7          ; 1 - 2 * r1
+    jmp strcmp_return
+strcmp_equal:
+    loadv r1, 0
+strcmp_return:
+    pop r4
+    pop r3
+    pop r2
+    ret
+
+; r1 := r1 < r2, unsigned: the borrow of r1 - r2 computed on halves so that
+; it lands in bit 31
+ult:
+    push r2
+    push r3
+    push r4
+    loadv r7, 2
+    div r3, r1, r7
+    div r4, r2, r7
+    loadv r7, 1
+    and r1, r1, r7
+    and r2, r2, r7
+    not r1, r1
+    and r1, r1, r2
+    sub r3, r3, r4
+    sub r3, r3, r1
+    li32 r1, 0x80000000
+    div r1, r3, r1
+    pop r4
+    pop r3
+    pop r2
+    ret
+
+; r1 := r1 < r2, signed: flipping the sign bits makes it unsigned
+slt:
+    push r2
+    push r3
+    li32 r3, 0x80000000
+    add r1, r1, r3
+    add r2, r2, r3
+    call ult
+    pop r3
+    pop r2
+    ret
+
+; r1 := r1 > r2, signed
+sgt:
+    push r2
+    mov r7, r1
+    mov r1, r2
+    mov r2, r7
+    call slt
+    pop r2
+    ret
+
+; r1 := r1 <= r2, signed
+sle:
+    call sgt
+    not r1, r1
+    loadv r7, 2
+    add r1, r1, r7
+    ret
+
+; r1 := r1 >= r2, signed
+sge:
+    call slt
+    not r1, r1
+    loadv r7, 2
+    add r1, r1, r7
+    ret
+
+; The heap is a segment (the arena) whose words 0, 1 and 2 hold the first
+; free block, the offset where the next new block starts and the size of the
+; arena. A block is preceded by a word holding its size; a free block holds
+; the next free block in its first word (0 ends the list).
+
+; maps an arena of r1 (at least 3) words: r1 := its segment
+heap_init:
+    push r2
+    map r2, r1
+    loadv r7, 1
+    loadv r6, 3
+    sstore r2, r7, r6
+    loadv r7, 2
+    sstore r2, r7, r1
+    loadv r7, heap_segment
+    sstore r0, r7, r2
+    mov r1, r2
+    pop r2
+    ret
+
+; allocates a block of r1 words: r1 := its offset in the arena, or -1 when
+; the arena is full. Freed blocks of exactly r1 words are reused first
+heap_alloc:
+    push r2
+    push r3
+    push r4
+    loadv r7, heap_segment
+    sload r2, r0, r7
+    loadv r3, 0
+    sload r4, r2, r3
+heap_alloc_search:
+    jz r4, heap_alloc_new
+    push r1
+    nand r7, r0, r0
+    add r7, r4, r7
+    sload r7, r2, r7
+    nand r7, r7, r7
+    add r7, r7, r1
+    nand r1, r7, r7         ; r1 = the size of the block - the size wanted
+    jz r1, heap_alloc_reuse
+    pop r1
+    mov r3, r4
+    sload r4, r2, r4
+    jmp heap_alloc_search
+heap_alloc_reuse:
+    pop r1
+    sload r7, r2, r4
+    sstore r2, r3, r7
+    mov r1, r4
+    jmp heap_alloc_return
+heap_alloc_new:
+    loadv r7, 1
+    sload r3, r2, r7
+    loadv r7, 1
+    add r4, r3, r7
+    add r4, r4, r1          ; r4 = the end of the new block
+    push r1
+    push r2
+    loadv r7, 2
+    sload r1, r2, r7
+    mov r2, r4
+    call ult
+    pop r2
+    jnz r1, heap_alloc_full
+    pop r1
+    sstore r2, r3, r1
+    loadv r7, 1
+    sstore r2, r7, r4
+    add r1, r3, r7
+    jmp heap_alloc_return
+heap_alloc_full:
+    pop r1
+    nand r1, r0, r0
+heap_alloc_return:
+    pop r4
+    pop r3
+    pop r2
+    ret
+
+; frees the block at offset r1 of the arena
+heap_free:
+    push r2
+    push r3
+    loadv r7, heap_segment
+    sload r2, r0, r7
+    sload r3, r2, r0
+    sstore r2, r1, r3
+    sstore r2, r0, r1
+    pop r3
+    pop r2
+    ret
+
+; the segment of the arena
+heap_segment:
+    .word 0
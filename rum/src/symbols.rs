@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///Structure: SourceLine
+///
+///The assembler source line an instruction word was generated from.
+pub struct SourceLine {
+    pub file: String,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+///Structure: SymbolMap
+///
+///The structure ties offsets of segment 0 back to the assembler source: the
+///`labels` defined at each offset and the source `lines` each word comes
+///from. As a text file, it holds one entry per line:
+///
+///```text
+///label <offset> <name>
+///line <offset> <line> <file>
+///```
+///
+///Empty lines and lines starting with `#` are ignored.
+pub struct SymbolMap {
+    labels: BTreeMap<u32, Vec<String>>,
+    lines: BTreeMap<u32, SourceLine>,
+}
+
+impl SymbolMap {
+
+    ///Function: `new() -> SymbolMap`
+    ///
+    ///This function creates an empty symbol map.
+    pub fn new() -> SymbolMap {
+        SymbolMap::default()
+    }
+
+    ///Function: `parse(text: &str) -> Result<SymbolMap, String>`
+    ///
+    ///This function reads a symbol map in the text format described above.
+    pub fn parse(text: &str) -> Result<SymbolMap, String> {
+        let mut symbols = SymbolMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || format!("symbol map line {}: cannot read `{}`", number + 1, line);
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            let offset = fields.get(1).and_then(|field| field.parse().ok()).ok_or_else(error)?;
+
+            match (fields[0], fields.len()) {
+                ("label", 3) => symbols.add_label(offset, fields[2]),
+                ("line", 4) => {
+                    let source = fields[2].parse().map_err(|_| error())?;
+                    symbols.add_line(offset, fields[3], source);
+                }
+                _ => return Err(error()),
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    ///Function: `load(path: &str) -> Result<SymbolMap, String>`
+    ///
+    ///This function reads and parses the symbol map file at `path`.
+    pub fn load(path: &str) -> Result<SymbolMap, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+
+        SymbolMap::parse(&text)
+    }
+
+    ///Function: `add_label(&mut self, offset: u32, name: &str)`
+    ///
+    ///This function records that the label `name` is defined at `offset`.
+    pub fn add_label(&mut self, offset: u32, name: &str) {
+        self.labels.entry(offset).or_default().push(name.to_string());
+    }
+
+    ///Function: `add_line(&mut self, offset: u32, file: &str, line: u32)`
+    ///
+    ///This function records that the word at `offset` comes from `file:line`.
+    pub fn add_line(&mut self, offset: u32, file: &str, line: u32) {
+        self.lines.insert(offset, SourceLine { file: file.to_string(), line });
+    }
+
+    ///Function: `labels_at(&self, offset: u32) -> impl Iterator<Item = &str>`
+    ///
+    ///This function returns the labels defined at `offset`.
+    pub fn labels_at(&self, offset: u32) -> impl Iterator<Item = &str> {
+        self.labels.get(&offset).into_iter().flatten().map(|label| label.as_str())
+    }
+
+    ///Function: `labels(&self) -> impl Iterator<Item = (u32, &str)>`
+    ///
+    ///This function returns every label along with its offset, in order of offset.
+    pub fn labels(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.labels.iter().flat_map(|(offset, labels)| labels.iter().map(move |label| (*offset, label.as_str())))
+    }
+
+    ///Function: `address_of(&self, name: &str) -> Option<u32>`
+    ///
+    ///This function returns the offset where the label `name` is defined.
+    pub fn address_of(&self, name: &str) -> Option<u32> {
+        self.labels().find(|(_, label)| *label == name).map(|(offset, _)| offset)
+    }
+
+    ///Function: `line(&self, offset: u32) -> Option<&SourceLine>`
+    ///
+    ///This function returns the source line the word at `offset` comes from.
+    pub fn line(&self, offset: u32) -> Option<&SourceLine> {
+        self.lines.get(&offset)
+    }
+
+    ///Function: `lines(&self) -> impl Iterator<Item = (u32, &SourceLine)>`
+    ///
+    ///This function returns the source line of every offset that has one.
+    pub fn lines(&self) -> impl Iterator<Item = (u32, &SourceLine)> {
+        self.lines.iter().map(|(offset, line)| (*offset, line))
+    }
+}
+
+impl fmt::Display for SymbolMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# rum symbol map")?;
+
+        for (offset, label) in self.labels() {
+            writeln!(f, "label {} {}", offset, label)?;
+        }
+
+        for (offset, line) in self.lines() {
+            writeln!(f, "line {} {} {}", offset, line.line, line.file)?;
+        }
+
+        Ok(())
+    }
+}
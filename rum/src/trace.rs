@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use crate::disassembler;
+use crate::monitor::Monitor;
+use crate::rum::Rum;
+use crate::symbols::SymbolMap;
+use crate::um_instruction::Instruction;
+
+///Structure: Tracer
+///
+///A `Monitor` writing a line to `out` for every instruction executed: its
+///step number, offset (with the labels of `symbols` there), disassembly and
+///the registers once it executed. `Halt` and a faulting instruction get a
+///line without registers.
+pub struct Tracer<'a> {
+    out: Box<dyn Write>,
+    symbols: Option<&'a SymbolMap>,
+    step: u64,
+    pending: Option<String>,
+}
+
+impl<'a> Tracer<'a> {
+
+    ///Function: `new(out: Box<dyn Write>, symbols: Option<&SymbolMap>) -> Tracer`
+    ///
+    ///This function creates a tracer writing to `out`.
+    pub fn new(out: Box<dyn Write>, symbols: Option<&'a SymbolMap>) -> Tracer<'a> {
+        Tracer { out, symbols, step: 0, pending: None }
+    }
+
+    ///Function: `finish(mut self) -> std::io::Result<()>`
+    ///
+    ///This function writes the line of the last instruction, if it halted
+    ///or faulted, and flushes `out`.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if let Some(line) = self.pending.take() {
+            writeln!(self.out, "{}", line)?;
+        }
+
+        self.out.flush()
+    }
+}
+
+impl Monitor for Tracer<'_> {
+    fn before_step(&mut self, machine: &Rum, pc: usize, _instruction: Instruction) {
+        let labels: Vec<&str> = self.symbols.map(|symbols| symbols.labels_at(pc as u32).collect()).unwrap_or_default();
+        let location = match labels.is_empty() {
+            true => format!("{:>8}", pc),
+            false => format!("{:>8} <{}>", pc, labels.join(", ")),
+        };
+
+        self.step += 1;
+        self.pending = Some(format!("{:>10} {}: {}", self.step, location, disassembler::disassemble(machine.segments().fetch(pc).unwrap_or(0))));
+    }
+
+    fn after_step(&mut self, machine: &Rum, _pc: usize, _instruction: Instruction) {
+        let registers: Vec<String> = machine.registers().iter().map(|value| format!("{:08x}", value)).collect();
+
+        if let Some(line) = self.pending.take() {
+            let _ = writeln!(self.out, "{:<56} ; {}", line, registers.join(" "));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, stdin, stdout, Read, StdinLock, StdoutLock, Write};
+
+pub use rum_core::um_io::{BufferIo, UmIo};
+
+#[derive(Debug)]
+///Structure: StdIo
+///
+///The standard I/O of the process: `Input` reads from stdin and `Output`
+///writes to stdout. Both are locked once for the life of the `StdIo`
+///instead of on every byte. Output is buffered by line and flushed before
+///every read of stdin; whoever runs the machine flushes what is left when it
+///stops. Once a write fails, the rest of the output is dropped and `flush`
+///reports the error.
+pub struct StdIo {
+    input: StdinLock<'static>,
+    output: StdoutLock<'static>,
+    error: Option<io::Error>,
+}
+
+impl StdIo {
+    ///Function: `new() -> StdIo`
+    ///
+    ///This function locks the standard input and output of the process.
+    pub fn new() -> StdIo {
+        StdIo {
+            input: stdin().lock(),
+            output: stdout().lock(),
+            error: None,
+        }
+    }
+
+    ///Function: `flush(&mut self) -> std::io::Result<()>`
+    ///
+    ///This function writes out whatever output is still buffered, or returns
+    ///the error that stopped the output.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.output.flush(),
+        }
+    }
+}
+
+impl Default for StdIo {
+    fn default() -> StdIo {
+        StdIo::new()
+    }
+}
+
+impl UmIo for StdIo {
+    fn input(&mut self) -> Option<u8> {
+        let mut value = [0_u8];
+
+        //Shows any prompt before blocking on the read
+        let _ = self.output.flush();
+
+        match self.input.read(&mut value) {
+            Ok(1) => Some(value[0]),
+            _ => None,
+        }
+    }
+
+    fn output(&mut self, value: u8) {
+        if self.error.is_none() {
+            self.error = self.output.write_all(&[value]).err();
+        }
+    }
+}
+
+///Structure: StreamIo
+///
+///An I/O over any byte streams, such as the files the command line redirects
+///a program to. Output is buffered by `output` and flushed before every
+///`Input`, so prompts show up before the program waits; `flush` pushes out
+///the rest. Once a write fails, the rest of the output is dropped and
+///`flush` reports the error.
+pub struct StreamIo {
+    input: Box<dyn Read>,
+    output: Box<dyn Write>,
+    error: Option<io::Error>,
+}
+
+impl StreamIo {
+    ///Function: `new(input: Box<dyn Read>, output: Box<dyn Write>) -> StreamIo`
+    ///
+    ///This function creates an I/O reading `input` and writing `output`.
+    pub fn new(input: Box<dyn Read>, output: Box<dyn Write>) -> StreamIo {
+        StreamIo { input, output, error: None }
+    }
+
+    ///Function: `flush(&mut self) -> std::io::Result<()>`
+    ///
+    ///This function writes out whatever output is still buffered, or returns
+    ///the error that stopped the output.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.output.flush(),
+        }
+    }
+}
+
+impl UmIo for StreamIo {
+    fn input(&mut self) -> Option<u8> {
+        let mut value = [0_u8];
+
+        let _ = self.output.flush();
+
+        match self.input.read(&mut value) {
+            Ok(1) => Some(value[0]),
+            _ => None,
+        }
+    }
+
+    fn output(&mut self, value: u8) {
+        if self.error.is_none() {
+            self.error = self.output.write_all(&[value]).err();
+        }
+    }
+}
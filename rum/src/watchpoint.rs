@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use crate::rum::Rum;
+use crate::um_instruction::{Instruction, Opcode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum WatchTarget
+///
+///The `enum` tells which segment a watchpoint follows. `Segment` follows a raw
+///segment identifier whatever is mapped there, while `Allocation` follows the
+///segment created by a given `MapSegment` (see `Segment::allocation`) even
+///though its identifier is recycled once it is unmapped.
+pub enum WatchTarget {
+    Segment(u32),
+    Allocation(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Access
+///
+///The kind of word accesses a watchpoint triggers on.
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum Watchpoint
+///
+///`Words` triggers when the offsets `start..end` of the target are accessed,
+///`Mapping` triggers when the target is mapped or unmapped.
+pub enum Watchpoint {
+    Words { target: WatchTarget, start: u32, end: u32, access: Access },
+    Mapping { target: WatchTarget },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Enum WatchEvent
+///
+///The memory event that triggered a watchpoint.
+pub enum WatchEvent {
+    Read { id: u32, offset: u32, value: u32 },
+    Write { id: u32, offset: u32, old: u32, new: u32 },
+    Mapped { id: u32, size: u32 },
+    Unmapped { id: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Structure: WatchHit
+///
+///The report of a triggered watchpoint: which `watchpoint`, the `pc` and
+///`instruction` that triggered it, the `allocation` of the segment involved
+///and the `event` itself with its old and new values.
+pub struct WatchHit {
+    pub watchpoint: usize,
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub allocation: Option<u64>,
+    pub event: WatchEvent,
+}
+
+///Structure: Probe
+///
+///The memory events an instruction is about to cause, gathered before it
+///executes so that old values and allocations can still be observed.
+pub struct Probe {
+    pc: usize,
+    instruction: Instruction,
+    events: Vec<(WatchEvent, Option<u64>)>,
+    map_size: Option<u32>,
+}
+
+impl WatchTarget {
+
+    ///Function: `matches(&self, id: u32, allocation: Option<u64>) -> bool`
+    ///
+    ///This function tells whether the segment `id` holding `allocation` is
+    ///the one followed by the target.
+    pub fn matches(&self, id: u32, allocation: Option<u64>) -> bool
+    {
+        match *self {
+            WatchTarget::Segment(segment) => segment == id,
+            WatchTarget::Allocation(serial) => allocation == Some(serial),
+        }
+    }
+}
+
+impl Watchpoint {
+
+    ///Function: `matches(&self, event: &WatchEvent, allocation: Option<u64>) -> bool`
+    ///
+    ///This function tells whether `event`, happening on a segment holding
+    ///`allocation`, triggers the watchpoint.
+    pub fn matches(&self, event: &WatchEvent, allocation: Option<u64>) -> bool
+    {
+        match (*self, *event) {
+            (Watchpoint::Words { target, start, end, access }, WatchEvent::Read { id, offset, .. }) =>
+                access != Access::Write && (start..end).contains(&offset) && target.matches(id, allocation),
+            (Watchpoint::Words { target, start, end, access }, WatchEvent::Write { id, offset, .. }) =>
+                access != Access::Read && (start..end).contains(&offset) && target.matches(id, allocation),
+            (Watchpoint::Mapping { target }, WatchEvent::Mapped { id, .. })
+            | (Watchpoint::Mapping { target }, WatchEvent::Unmapped { id }) => target.matches(id, allocation),
+            _ => false,
+        }
+    }
+}
+
+///Function: `probe(watchpoints: &BTreeMap<usize, Watchpoint>, machine: &Rum) -> Option<Probe>`
+///
+///This function looks at the instruction `machine` is about to execute and
+///gathers the memory events it will cause. `None` is returned when the
+///instruction cannot be fetched, in which case the step faults anyway.
+pub fn probe(watchpoints: &BTreeMap<usize, Watchpoint>, machine: &Rum) -> Option<Probe>
+{
+    let pc = machine.pc();
+    let instruction = machine.get_instruction(pc).ok()?;
+    let registers = machine.registers();
+    let segments = machine.segments();
+    let word = |id: u32, offset: u32| {
+        segments.get_segment_value(id as usize, offset as usize)
+    };
+
+    let mut events = Vec::new();
+    let mut map_size = None;
+
+    match instruction.opcode {
+        Opcode::Load => {
+            let (id, offset) = (registers[instruction.b.unwrap() as usize], registers[instruction.c.unwrap() as usize]);
+
+            if let Some(value) = word(id, offset) {
+                events.push((WatchEvent::Read { id, offset, value }, segments.allocation(id as usize)));
+            }
+        }
+        Opcode::Store => {
+            let (id, offset) = (registers[instruction.a as usize], registers[instruction.b.unwrap() as usize]);
+            let new = registers[instruction.c.unwrap() as usize];
+
+            if let Some(old) = word(id, offset) {
+                events.push((WatchEvent::Write { id, offset, old, new }, segments.allocation(id as usize)));
+            }
+        }
+        Opcode::MapSegment => map_size = Some(registers[instruction.c.unwrap() as usize]),
+        Opcode::UnmapSegment => {
+            let id = registers[instruction.c.unwrap() as usize];
+
+            events.push((WatchEvent::Unmapped { id }, segments.allocation(id as usize)));
+        }
+        Opcode::LoadProgram if registers[instruction.b.unwrap() as usize] != 0 => {
+            let id = registers[instruction.b.unwrap() as usize];
+            let source_len = segments.segment_len(id as usize)?;
+            let program_len = segments.segment_len(0)?;
+
+            //the whole source segment is read and copied over segment 0, so only
+            //the offsets covered by a watchpoint are turned into events
+            for watchpoint in watchpoints.values() {
+                if let Watchpoint::Words { start, end, .. } = *watchpoint {
+                    for offset in start..end.min(source_len as u32) {
+                        let value = word(id, offset)?;
+                        let old = word(0, offset).unwrap_or(0);
+
+                        events.push((WatchEvent::Read { id, offset, value }, segments.allocation(id as usize)));
+
+                        if old != value || offset as usize >= program_len {
+                            events.push((WatchEvent::Write { id: 0, offset, old, new: value }, segments.allocation(0)));
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Some(Probe { pc, instruction, events, map_size })
+}
+
+impl Probe {
+
+    ///Function: `finish(self, watchpoints: &BTreeMap<usize, Watchpoint>, machine: &Rum) -> Option<WatchHit>`
+    ///
+    ///This function completes the events with the state of `machine` once
+    ///the instruction executed and returns the first triggered watchpoint.
+    pub fn finish(mut self, watchpoints: &BTreeMap<usize, Watchpoint>, machine: &Rum) -> Option<WatchHit>
+    {
+        if let Some(size) = self.map_size {
+            let id = machine.registers()[self.instruction.b.unwrap() as usize];
+
+            self.events.push((WatchEvent::Mapped { id, size }, machine.segments().allocation(id as usize)));
+        }
+
+        for (event, allocation) in self.events {
+            for (&number, watchpoint) in watchpoints {
+                if watchpoint.matches(&event, allocation) {
+                    return Some(WatchHit {
+                        watchpoint: number,
+                        pc: self.pc,
+                        instruction: self.instruction,
+                        allocation,
+                        event,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
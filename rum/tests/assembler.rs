@@ -0,0 +1,149 @@
+mod common;
+
+use std::fs;
+use rum::assembler::{assemble, assemble_file, AsmError};
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+fn output(source: &str) -> Vec<u8> {
+    let assembly = assemble(source, "test.s").unwrap();
+    let mut rum = Rum::new(&assembly.words);
+    let mut io = BufferIo::new(&[]);
+
+    rum.run(&mut io).unwrap();
+
+    io.output
+}
+
+fn error(source: &str) -> AsmError {
+    assemble(source, "test.s").unwrap_err()
+}
+
+#[test]
+fn assembles_directives() {
+    let source = "
+        .equ answer, 42
+        loadv r1, answer
+        loadv r2, data
+        halt
+    data:
+        .word 7, data, 'x'
+        .string \"hi\"
+        .space 2
+    ";
+
+    let assembly = assemble(source, "test.s").unwrap();
+
+    assert_eq!(assembly.words, [loadv(1, 42), loadv(2, 3), op(7, 0, 0, 0), 7, 3, 'x' as u32, 'h' as u32, 'i' as u32, 0, 0, 0]);
+    assert_eq!(assembly.symbols.address_of("data"), Some(3));
+    assert_eq!(assembly.symbols.address_of("answer"), None);
+}
+
+#[test]
+fn substitutes_whole_parameters() {
+    let source = "
+    .macro show a, ab
+        loadv r1, \\ab
+        out r1
+        loadv r1, \\a
+        out r1
+    .endm
+        show 'x', 'y'
+        halt
+    ";
+
+    assert_eq!(output(source), b"yx");
+}
+
+#[test]
+fn labels_are_local_to_each_expansion() {
+    //Prints `count` stars; `loop$1` and `loop$2` must not clash with each
+    //other nor with the program's own `loop1` and `loop2`
+    let source = "
+    .macro stars count
+        loadv r1, \\count
+        loadv r2, '*'
+    loop\\@:
+        out r2
+        loadv r3, 1
+        sub r1, r1, r3
+        jnz r1, loop\\@
+    .endm
+        stars 2
+    loop1:
+        stars 3
+    loop2:
+        halt
+    ";
+
+    assert_eq!(output(source), b"*****");
+}
+
+#[test]
+fn generated_labels_do_not_clash() {
+    let source = "
+        loadv r1, 1
+        jz r1, .L1
+        loadv r2, 'a'
+        out r2
+    .L1:
+        jnz r1, .L2
+        halt
+    .L2:
+        loadv r2, 'b'
+        out r2
+        halt
+    ";
+
+    assert_eq!(output(source), b"ab");
+}
+
+#[test]
+fn reserves_dollar_for_generated_labels() {
+    assert_eq!(error("nop:\n    halt\nloop$1:\n").line, 3);
+    assert!(error("loop$1:").message.contains("reserved"));
+
+    //Inside strings and comments it is text
+    assert_eq!(output("loadv r1, '$'\nout r1 ; costs $0\nhalt\n.string \"$\""), b"$");
+}
+
+#[test]
+fn reports_error_locations() {
+    let unknown = error("halt\n\nfrobnicate r1\n");
+    assert_eq!((unknown.file.as_str(), unknown.line), ("test.s", 3));
+    assert_eq!(unknown.to_string(), "test.s:3: unknown instruction `frobnicate`");
+
+    let arguments = error(".macro two a, b\n    halt\n.endm\n\n    two r1\n");
+    assert_eq!(arguments.line, 5);
+    assert!(arguments.message.contains("takes 2 argument(s), 1 given"), "{}", arguments);
+
+    //Errors inside a macro body are reported on the line using the macro
+    let body = error(".macro bad\n    loadv r9, 0\n.endm\n    halt\n    bad\n");
+    assert_eq!(body.line, 5);
+
+    let unterminated = error("halt\n.macro open\n    halt\n");
+    assert_eq!(unterminated.line, 2);
+    assert!(unterminated.message.contains("no `.endm`"), "{}", unterminated);
+
+    let duplicate = error("here:\n    halt\nhere:\n");
+    assert_eq!(duplicate.line, 3);
+    assert!(duplicate.message.contains("defined more than once"), "{}", duplicate);
+
+    let undefined = error("    halt\n    loadv r1, nowhere\n");
+    assert_eq!(undefined.line, 2);
+}
+
+#[test]
+fn reports_errors_in_included_files() {
+    let directory = std::env::temp_dir().join(format!("rum-assembler-{}", std::process::id()));
+    let (main, included) = (directory.join("main.s"), directory.join("included.s"));
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(&main, "halt\n.include \"included.s\"\n").unwrap();
+    fs::write(&included, "halt\nhalt r1\n").unwrap();
+
+    let error = assemble_file(main.to_str().unwrap()).unwrap_err();
+
+    assert_eq!((error.file, error.line), (included.to_string_lossy().into_owned(), 2));
+}
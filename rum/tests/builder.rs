@@ -0,0 +1,68 @@
+use rum::builder::{BuildError, ProgramBuilder};
+use rum::rum::Rum;
+use rum::um_instruction::{Instruction, Opcode, MAX_VALUE};
+use rum::um_io::BufferIo;
+
+#[test]
+fn encode_round_trips() {
+    let instructions = [
+        Instruction::cmov(1, 2, 3),
+        Instruction::nand(7, 0, 7),
+        Instruction::map_segment(4, 5),
+        Instruction::output(6),
+        Instruction::load_program(0, 7),
+        Instruction::load_value(3, MAX_VALUE).unwrap(),
+        Instruction::halt(),
+    ];
+
+    for instruction in instructions {
+        assert_eq!(Instruction::new(instruction.encode()), instruction);
+    }
+
+    assert_eq!(Instruction::add(1, 2, 3).encode(), 3 << 28 | 1 << 6 | 2 << 3 | 3);
+    assert_eq!(Instruction::load_value(5, 42).unwrap().encode(), 13 << 28 | 5 << 25 | 42);
+}
+
+#[test]
+fn load_value_checks_its_operands() {
+    assert_eq!(Instruction::load_value(0, MAX_VALUE + 1), None);
+    assert_eq!(Instruction::load_value(8, 0), None);
+    assert_eq!(Instruction::load_value(7, 0).map(|instruction| instruction.opcode), Some(Opcode::LoadValue));
+}
+
+#[test]
+fn resolves_forward_labels() {
+    let mut builder = ProgramBuilder::new();
+    builder
+        .load_label(1, "end")
+        .push(Instruction::load_value(2, 'A' as u32).unwrap())
+        .push(Instruction::load_program(0, 1))
+        .push(Instruction::output(2))
+        .label("end")
+        .push(Instruction::output(2))
+        .push(Instruction::halt());
+
+    let program = builder.build().unwrap();
+    let mut rum = Rum::new(&program);
+    let mut io = BufferIo::new(&[]);
+
+    rum.run(&mut io).unwrap();
+
+    assert_eq!(io.output, b"A");
+    assert_eq!(builder.symbols().address_of("end"), Some(4));
+}
+
+#[test]
+fn reports_label_errors() {
+    let mut undefined = ProgramBuilder::new();
+    undefined.load_label(0, "nowhere");
+    assert_eq!(undefined.build(), Err(BuildError::UndefinedLabel("nowhere".to_string())));
+
+    let mut duplicate = ProgramBuilder::new();
+    duplicate.label("twice").push(Instruction::halt()).label("twice");
+    assert_eq!(duplicate.build(), Err(BuildError::DuplicateLabel("twice".to_string())));
+
+    let mut register = ProgramBuilder::new();
+    register.load_label(8, "start").label("start");
+    assert_eq!(register.build(), Err(BuildError::InvalidRegister { label: "start".to_string(), register: 8 }));
+}
@@ -0,0 +1,67 @@
+use rum::compiler::{compile, compile_program};
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+
+//Compiles `tests/cc/<name>.sm`, runs it on `<name>.in` (if any) and compares
+//its output with `<name>.out`
+fn check(name: &str) {
+    let directory = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cc/");
+    let source = std::fs::read_to_string(format!("{}{}.sm", directory, name)).unwrap();
+    let input = std::fs::read(format!("{}{}.in", directory, name)).unwrap_or_default();
+    let expected = std::fs::read(format!("{}{}.out", directory, name)).unwrap();
+
+    let program = compile_program(&source, &format!("{}.s", name)).unwrap_or_else(|error| panic!("{}.sm:{}", name, error));
+    let mut rum = Rum::new(&program.words);
+    let mut io = BufferIo::new(&input);
+
+    rum.run(&mut io).unwrap_or_else(|fault| panic!("{} faulted at pc {}: {}", name, rum.pc(), fault));
+
+    assert_eq!(String::from_utf8_lossy(&io.output), String::from_utf8_lossy(&expected), "output of {}", name);
+}
+
+#[test]
+fn count() {
+    check("count");
+}
+
+#[test]
+fn hello() {
+    check("hello");
+}
+
+#[test]
+fn fib() {
+    check("fib");
+}
+
+#[test]
+fn sieve() {
+    check("sieve");
+}
+
+#[test]
+fn signed() {
+    check("signed");
+}
+
+#[test]
+fn echo() {
+    check("echo");
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("fn main() { x = 1; }", "1: unknown variable `x`"),
+        ("fn main() {\n  f(1);\n}\nfn f(a, b) {}", "2: `f` takes 2 argument(s), 1 given"),
+        ("fn f() {}", "0: the program has no `main`"),
+        ("fn main() { var a; var a; }", "1: variable `a` is already defined"),
+        ("fn main() { 1 = 2; }", "1: only variables and array elements can be assigned"),
+        ("fn main() { putc('ab'); }", "1: a character literal holds a single character"),
+        ("fn main() { return }", "1: expected an expression"),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(compile(source).unwrap_err().to_string(), expected, "{:?}", source);
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use rum::cfg::{recover, EdgeKind};
+use common::{loadv, op};
+
+//Counts r1 down from 3, jumping back to 3 until it reaches 0
+fn countdown() -> Vec<u32> {
+    vec![
+        loadv(1, 3),
+        loadv(7, 0),
+        op(6, 7, 7, 7),
+        op(3, 1, 1, 7),
+        loadv(5, 3),
+        loadv(6, 8),
+        op(0, 6, 5, 1),
+        op(12, 0, 0, 6),
+        op(7, 0, 0, 0),
+        42,
+    ]
+}
+
+//Calls the routine at 6 after storing the return offset 5 at offset 10,
+//where the routine loads it back from to return
+fn call() -> Vec<u32> {
+    vec![
+        loadv(1, 5),
+        loadv(2, 10),
+        op(2, 0, 2, 1),
+        loadv(3, 6),
+        op(12, 0, 0, 3),
+        op(7, 0, 0, 0),
+        loadv(2, 10),
+        op(1, 4, 0, 2),
+        op(12, 0, 0, 4),
+        op(7, 0, 0, 0),
+        0,
+    ]
+}
+
+#[test]
+fn splits_blocks_at_jump_targets() {
+    let graph = recover(&countdown(), None);
+    let ranges: Vec<(usize, usize)> = graph.blocks.iter().map(|block| (block.start, block.end)).collect();
+
+    assert_eq!(ranges, [(0, 3), (3, 8), (8, 9)]);
+    assert_eq!(graph.edges, [(0, 3, EdgeKind::FallThrough), (3, 3, EdgeKind::Jump), (3, 8, EdgeKind::Jump)]);
+    assert_eq!(graph.blocks[0].registers, [Some(0); 8]);
+    assert_eq!((graph.blocks[1].registers[1], graph.blocks[1].registers[7]), (None, Some(u32::MAX)));
+    assert!(graph.blocks.iter().all(|block| !block.unknown && !block.call));
+
+    let listing = graph.listing();
+
+    assert!(listing.contains("; block 3..8 in start -> 3, 8\n"), "{}", listing);
+    assert!(listing.contains("; unreachable\n"), "{}", listing);
+}
+
+#[test]
+fn follows_calls_and_returns() {
+    let graph = recover(&call(), None);
+    let ranges: Vec<(usize, usize)> = graph.blocks.iter().map(|block| (block.start, block.end)).collect();
+
+    assert_eq!(ranges, [(0, 5), (5, 6), (6, 9)]);
+    assert!(graph.blocks[0].call);
+    assert_eq!(graph.edges, [(0, 6, EdgeKind::Call), (0, 5, EdgeKind::Return)]);
+
+    let functions: Vec<(&str, &[usize])> = graph.functions.iter().map(|function| (function.name.as_str(), function.blocks.as_slice())).collect();
+
+    assert_eq!(functions, [("start", &[0, 5][..]), ("sub_6", &[6][..])]);
+    assert!(graph.dot().contains("b0 -> b6 [style=bold];"));
+    assert!(graph.dot().contains("b0 -> b5 [style=dashed];"));
+}
+
+#[test]
+fn marks_unknown_targets() {
+    let graph = recover(&call(), None);
+    let unknown: Vec<usize> = graph.blocks.iter().filter(|block| block.unknown).map(|block| block.start).collect();
+
+    assert_eq!(unknown, [6]);
+    assert!(graph.blocks[2].successors.is_empty());
+    assert!(graph.dot().contains("-> ?\\l"));
+    assert!(graph.listing().contains("; block 6..9 in sub_6 -> ?\n"));
+
+    //Without the stored return offset, nothing is known to come back after 4
+    let mut jump = call();
+
+    jump[2] = 0;
+
+    let graph = recover(&jump, None);
+
+    assert!(!graph.blocks[0].call);
+    assert_eq!(graph.edges, [(0, 6, EdgeKind::Jump)]);
+    assert!(graph.blocks.iter().all(|block| block.start != 5));
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use common::{loadv, op};
+
+//Writes `program` as a UM image into a directory of its own, named after the
+//test, and returns that directory
+fn image(test: &str, program: &[u32]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rum-cli-{}-{}", std::process::id(), test));
+    let bytes: Vec<u8> = program.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("program.um"), bytes).unwrap();
+
+    directory
+}
+
+fn rum(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rum")).args(arguments).output().unwrap()
+}
+
+//Prints `input` back, upper-cased by subtracting 32 from every byte
+fn shout() -> Vec<u32> {
+    vec![
+        loadv(6, 32),
+        loadv(7, 1),
+        op(11, 0, 0, 1),
+        op(6, 2, 1, 1),
+        loadv(3, 14),
+        loadv(4, 8),
+        op(0, 3, 4, 2),
+        op(12, 0, 0, 3),
+        op(6, 5, 6, 6),
+        op(3, 1, 1, 5),
+        op(3, 1, 1, 7),
+        op(10, 0, 0, 1),
+        loadv(3, 2),
+        op(12, 0, 0, 3),
+        op(7, 0, 0, 0),
+    ]
+}
+
+#[test]
+fn redirects_input_and_output() {
+    let directory = image("redirect", &shout());
+    let program = directory.join("program.um");
+    let (input, output) = (directory.join("in"), directory.join("out"));
+
+    fs::write(&input, "abc").unwrap();
+
+    let status = rum(&["run", program.to_str().unwrap(), "--input", input.to_str().unwrap(), "--output", output.to_str().unwrap()]).status;
+
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(fs::read(output).unwrap(), b"ABC");
+}
+
+#[test]
+fn exit_codes() {
+    let directory = image("exit", &[loadv(1, 1), loadv(2, 0), op(5, 0, 1, 2)]);
+    let program = directory.join("program.um");
+    let program = program.to_str().unwrap();
+
+    let fault = rum(&[program]);
+    assert_eq!(fault.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&fault.stderr).starts_with("Fault at pc 2"));
+
+    assert_eq!(rum(&["run", program, "--steps", "1"]).status.code(), Some(4));
+    assert_eq!(rum(&["run"]).status.code(), Some(2));
+    assert_eq!(rum(&["run", directory.join("missing.um").to_str().unwrap()]).status.code(), Some(3));
+
+    assert_eq!(rum(&["run", program, "--coverage"]).status.code(), Some(2));
+    assert_eq!(rum(&["asm", directory.join("missing.s").to_str().unwrap()]).status.code(), Some(3));
+
+    let halt = image("halt", &[op(7, 0, 0, 0)]);
+    let halt = halt.join("program.um");
+    assert_eq!(rum(&[halt.to_str().unwrap()]).status.code(), Some(0));
+    assert_eq!(rum(&["reach", halt.to_str().unwrap(), "5", "--runs", "4"]).status.code(), Some(5));
+}
+
+#[test]
+fn stops_when_the_output_is_closed() {
+    //Prints x's forever
+    let directory = image("closed", &[loadv(1, 'x' as u32), op(10, 0, 0, 1), loadv(2, 1), op(12, 0, 0, 2)]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rum"))
+        .arg(directory.join("program.um"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut head = [0; 16];
+    child.stdout.take().unwrap().read_exact(&mut head).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(head, [b'x'; 16]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn runs_test_cases() {
+    let directory = image("cases", &shout());
+    let program = directory.join("program.um");
+
+    fs::write(directory.join("pass.in"), "ok").unwrap();
+    fs::write(directory.join("pass.out"), "OK").unwrap();
+    fs::write(directory.join("fail.out"), "something").unwrap();
+
+    let output = rum(&["test", program.to_str().unwrap(), directory.to_str().unwrap()]);
+    let report = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(report.contains("ok   pass"), "{}", report);
+    assert!(report.contains("FAIL fail"), "{}", report);
+    assert!(report.contains("1 passed, 1 failed"), "{}", report);
+}
+
+#[test]
+fn writes_stats() {
+    let directory = image("stats", &shout());
+    let program = directory.join("program.um");
+    let (input, stats) = (directory.join("in"), directory.join("stats.json"));
+
+    fs::write(&input, "ab").unwrap();
+
+    let output = rum(&["run", program.to_str().unwrap(), "--input", input.to_str().unwrap(), "--stats", "--stats-json", stats.to_str().unwrap()]);
+    let json = fs::read_to_string(stats).unwrap();
+
+    assert_eq!(output.stdout, b"AB");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("instructions: 33\n"));
+    assert!(json.contains("\"instructions\": 33,"), "{}", json);
+    assert!(json.contains("\"Input\": 3, \"LoadProgram\": 5"), "{}", json);
+    assert!(json.contains("\"load_program_jumps\": 5,"), "{}", json);
+    assert!(json.contains("\"input_bytes\": 2,\n  \"output_bytes\": 2,"), "{}", json);
+}
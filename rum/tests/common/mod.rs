@@ -0,0 +1,11 @@
+//Hand encodings of the instructions shared by the integration tests
+
+#[allow(dead_code)]
+pub fn op(opcode: u32, a: u32, b: u32, c: u32) -> u32 {
+    opcode << 28 | a << 6 | b << 3 | c
+}
+
+#[allow(dead_code)]
+pub fn loadv(a: u32, value: u32) -> u32 {
+    13 << 28 | a << 25 | value
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use rum::builder::ProgramBuilder;
+use rum::concolic::{search, solve, Constraint, Expr, Search, Unsolved};
+use rum::um_instruction::Instruction;
+
+//Reads a password of two bytes and prints `!` at `granted` when it is "ok".
+//Past `granted`, `vault` is only reached when the second byte times 0 is not
+//0, which no input satisfies
+fn password() -> ProgramBuilder {
+    let mut builder = ProgramBuilder::new();
+
+    for (expected, next) in [(b'o', "second"), (b'k', "granted")] {
+        //r6 = r1 - expected, which is 0 only for the expected byte
+        builder
+            .push(Instruction::input(1))
+            .push(Instruction::load_value(5, expected as u32 - 1).unwrap())
+            .push(Instruction::nand(5, 5, 5))
+            .push(Instruction::add(6, 1, 5))
+            .load_label(3, "denied")
+            .load_label(4, next)
+            .push(Instruction::cmov(4, 3, 6))
+            .push(Instruction::load_program(0, 4))
+            .label(next);
+    }
+
+    builder
+        .push(Instruction::load_value(2, '!' as u32).unwrap())
+        .push(Instruction::output(2))
+        .push(Instruction::load_value(7, 0).unwrap())
+        .push(Instruction::mul(7, 1, 7))
+        .load_label(3, "vault")
+        .load_label(4, "denied")
+        .push(Instruction::cmov(4, 3, 7))
+        .push(Instruction::load_program(0, 4))
+        .label("vault")
+        .push(Instruction::output(2))
+        .label("denied")
+        .push(Instruction::halt());
+
+    builder
+}
+
+//The search for `label` of the password program, from an empty input
+fn options(label: &str) -> (Vec<u32>, Search) {
+    let builder = password();
+    let target = builder.symbols().address_of(label).unwrap() as usize;
+
+    (builder.build().unwrap(), Search { target, ..Search::default() })
+}
+
+#[test]
+fn finds_the_password() {
+    let (program, options) = options("granted");
+    let report = search(&program, &options);
+
+    assert_eq!(report.found, [b"ok".to_vec()]);
+    assert!(report.runs <= 3, "{:?}", report);
+}
+
+#[test]
+fn reports_unsatisfiable_branches() {
+    let (program, options) = options("vault");
+    let report = search(&program, &options);
+
+    //Every path was explored without the solver giving up
+    assert!(report.found.is_empty());
+    assert_eq!((report.abandoned, report.truncated, report.exhausted), (0, 0, true));
+
+    //Asking the solver directly for the second byte times 0 not to be 0
+    let goal = Constraint {
+        expr: Rc::new(Expr::Mul(Rc::new(Expr::Input(0)), Rc::new(Expr::Const(0)))),
+        nonzero: true,
+        branch: true,
+    };
+
+    assert_eq!(solve(&[], &[], &goal, &BTreeSet::from([0]), &[b'k' as u16]), Err(Unsolved::Unsatisfiable));
+}
+
+#[test]
+fn stops_at_the_run_and_step_limits() {
+    //A single run on the empty seed cannot get past the first byte
+    let (program, mut options) = options("granted");
+    options.runs = 1;
+
+    let report = search(&program, &options);
+
+    assert!(report.found.is_empty());
+    assert_eq!((report.runs, report.exhausted), (1, false));
+
+    //Runs cut short before the first byte is checked find no branch to take,
+    //which does not make every path explored
+    options.runs = 1000;
+    options.steps = 3;
+
+    let report = search(&program, &options);
+
+    assert!(report.found.is_empty());
+    assert_eq!((report.runs, report.truncated, report.exhausted), (1, 1, false));
+    assert!(report.to_string().ends_with("(0 branches abandoned by the solver, 1 runs stopped by the step limit)"), "{}", report);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use rum::coverage::Coverage;
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+fn cover(program: &[u32]) -> Coverage {
+    let mut rum = Rum::new(program);
+    let mut coverage = Coverage::new(program);
+
+    let result = rum.run_monitored(&mut BufferIo::new(&[]), &mut coverage);
+    assert!(result.is_ok(), "{:?}", result);
+
+    coverage
+}
+
+//Counts r1 down from 3, then halts, leaving the last word unreachable
+fn countdown() -> Vec<u32> {
+    vec![
+        op(6, 7, 0, 0),
+        loadv(1, 3),
+        loadv(3, 9),
+        loadv(4, 6),
+        op(0, 3, 4, 1),
+        op(12, 0, 0, 3),
+        op(3, 1, 1, 7),
+        loadv(5, 2),
+        op(12, 0, 0, 5),
+        op(7, 0, 0, 0),
+        op(7, 0, 0, 0),
+    ]
+}
+
+#[test]
+fn counts_every_offset() {
+    let coverage = cover(&countdown());
+    let images = coverage.images();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].loads, 1);
+    assert_eq!(images[0].hits, [1, 1, 4, 4, 4, 4, 3, 3, 3, 1, 0]);
+}
+
+#[test]
+fn exports_listing_and_lcov() {
+    let coverage = cover(&countdown());
+    let listing = coverage.listing(None);
+    let lcov = coverage.lcov("countdown.um", None);
+
+    assert!(listing.starts_with("image 0: 11 words, loaded 1 time(s), 10/11 offsets executed\n"), "{}", listing);
+    assert_eq!(listing.matches("#####").count(), 1, "{}", listing);
+
+    assert!(lcov.starts_with("TN:\nSF:countdown.um#image0\nDA:1,1\nDA:2,1\nDA:3,4\n"), "{}", lcov);
+    assert!(lcov.ends_with("DA:11,0\nLF:11\nLH:10\nend_of_record\n"), "{}", lcov);
+    assert!(coverage.profile(1, None).starts_with("28 instructions executed in 1 image(s)\n"));
+}
+
+#[test]
+fn tracks_loaded_images() {
+    //Copies the `Halt` at offset 7 into a new segment and loads it
+    let program = [
+        loadv(1, 2),
+        op(8, 0, 2, 1),
+        loadv(3, 7),
+        op(1, 4, 0, 3),
+        op(2, 2, 0, 4),
+        op(12, 0, 2, 0),
+        op(7, 0, 0, 0),
+        op(7, 0, 0, 0),
+    ];
+    let coverage = cover(&program);
+    let images = coverage.images();
+
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].hits, [1, 1, 1, 1, 1, 1, 0, 0]);
+    assert_eq!(images[1].words, [op(7, 0, 0, 0), 0]);
+    assert_eq!(images[1].hits, [1, 0]);
+}
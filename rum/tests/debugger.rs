@@ -0,0 +1,97 @@
+mod common;
+
+use rum::checkpoint::CheckpointConfig;
+use rum::debugger::{Debugger, ReplayError, Stop};
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+//Adds every byte of input to r1 forever, the end of input adding all 1's
+fn accumulate() -> Vec<u32> {
+    vec![
+        loadv(3, 1),
+        op(11, 0, 0, 4),
+        op(3, 1, 1, 4),
+        op(12, 0, 0, 3),
+    ]
+}
+
+//Checkpoints every few steps so that going backwards crosses checkpoints
+fn debugger() -> Debugger {
+    let config = CheckpointConfig { interval: 4, ..CheckpointConfig::default() };
+
+    Debugger::new(Rum::new(&accumulate()), Box::new(BufferIo::new(b"abcdefgh")), config)
+}
+
+//The pc and registers of the machine
+fn state(debugger: &Debugger) -> (usize, Vec<u32>) {
+    (debugger.machine().pc(), debugger.machine().registers().to_vec())
+}
+
+#[test]
+fn reverse_step_restores_every_state() {
+    let mut debugger = debugger();
+    let mut states = vec![state(&debugger)];
+
+    for _ in 0..50 {
+        assert_eq!(debugger.step(), Stop::Step);
+        states.push(state(&debugger));
+    }
+
+    while let Some(expected) = states.pop() {
+        assert_eq!(state(&debugger), expected, "after {} steps", states.len());
+
+        let stop = debugger.reverse_step();
+        assert_eq!(stop, if states.is_empty() { Stop::StartOfHistory } else { Stop::Step });
+    }
+
+    //Running forwards again replays the recorded input
+    for _ in 0..50 {
+        debugger.step();
+    }
+
+    let mut fresh = self::debugger();
+    for _ in 0..50 {
+        fresh.step();
+    }
+
+    assert_eq!(state(&debugger), state(&fresh));
+}
+
+#[test]
+fn reverse_continue_stops_at_breakpoints() {
+    let mut debugger = debugger();
+    let mut states = vec![state(&debugger)];
+
+    for _ in 0..30 {
+        debugger.step();
+        states.push(state(&debugger));
+    }
+
+    debugger.add_breakpoint(2);
+
+    let expected = states.iter().rposition(|(pc, _)| *pc == 2).unwrap();
+    assert!(expected < 30);
+
+    assert_eq!(debugger.reverse_continue(), Stop::Breakpoint(2));
+    assert_eq!(debugger.steps(), expected as u64);
+    assert_eq!(state(&debugger), states[expected]);
+
+    debugger.remove_breakpoint(2);
+
+    assert_eq!(debugger.reverse_continue(), Stop::StartOfHistory);
+    assert_eq!(state(&debugger), states[0]);
+}
+
+#[test]
+fn seek_reports_unreached_steps() {
+    let mut debugger = debugger();
+
+    for _ in 0..10 {
+        debugger.step();
+    }
+
+    assert_eq!(debugger.seek(11), Err(ReplayError::PastFrontier { step: 11, frontier: 10 }));
+    assert_eq!(debugger.seek(3), Ok(()));
+    assert_eq!(debugger.steps(), 3);
+}
@@ -0,0 +1,137 @@
+use rum::builder::ProgramBuilder;
+use rum::decompiler::decompile;
+use rum::um_instruction::Instruction;
+
+//The declaration of the registers, which the declaration of the temporaries follows
+const REGISTERS: &str = "uint32_t r0 = 0, r1 = 0, r2 = 0, r3 = 0, r4 = 0, r5 = 0, r6 = 0, r7 = 0;\n";
+
+fn decompiled(builder: &ProgramBuilder) -> String {
+    decompile(&builder.build().unwrap(), None)
+}
+
+#[test]
+fn folds_arithmetic_into_expressions() {
+    let mut builder = ProgramBuilder::new();
+    builder
+        .push(Instruction::input(1))
+        .push(Instruction::input(2))
+        .push(Instruction::add(3, 1, 2))
+        .push(Instruction::load_value(4, 3).unwrap())
+        .push(Instruction::mul(3, 3, 4))
+        .push(Instruction::div(3, 3, 2))
+        .push(Instruction::nand(3, 3, 1))
+        .push(Instruction::nand(3, 3, 3))
+        .push(Instruction::output(3))
+        .push(Instruction::halt());
+
+    assert_eq!(decompiled(&builder), format!("// 10 words, 1 functions\n{}uint32_t t1, t2;
+
+void start(void)
+{{
+    t1 = getchar();
+    t2 = getchar();
+    putchar((t1 + t2) * 3 / t2 & t1);
+    halt();
+}}
+", REGISTERS));
+}
+
+#[test]
+fn structures_branches() {
+    let mut builder = ProgramBuilder::new();
+    builder
+        .push(Instruction::input(1))
+        .load_label(3, "zero")
+        .load_label(4, "nonzero")
+        .push(Instruction::cmov(3, 4, 1))
+        .push(Instruction::load_program(0, 3))
+        .label("zero")
+        .push(Instruction::load_value(2, 'n' as u32).unwrap())
+        .push(Instruction::output(2))
+        .push(Instruction::halt())
+        .label("nonzero")
+        .push(Instruction::load_value(2, 'y' as u32).unwrap())
+        .push(Instruction::output(2))
+        .push(Instruction::halt());
+
+    assert_eq!(decompiled(&builder), format!("// 11 words, 1 functions\n{}uint32_t t1;
+
+void start(void)
+{{
+    t1 = getchar();
+    if (!t1) {{
+        putchar('n');
+        halt();
+    }}
+    putchar('y');
+    halt();
+}}
+", REGISTERS));
+}
+
+#[test]
+fn structures_loops() {
+    //Prints 10 stars, counting r1 down by adding all 1's
+    let mut builder = ProgramBuilder::new();
+    builder
+        .push(Instruction::load_value(1, 10).unwrap())
+        .push(Instruction::load_value(6, 0).unwrap())
+        .push(Instruction::nand(6, 6, 6))
+        .label("loop")
+        .push(Instruction::load_value(2, '*' as u32).unwrap())
+        .push(Instruction::output(2))
+        .push(Instruction::add(1, 1, 6))
+        .load_label(3, "done")
+        .load_label(4, "loop")
+        .push(Instruction::cmov(3, 4, 1))
+        .push(Instruction::load_program(0, 3))
+        .label("done")
+        .push(Instruction::halt());
+
+    assert_eq!(decompiled(&builder), format!("// 11 words, 1 functions\n{}
+void start(void)
+{{
+    r1 = 10;
+    do {{
+        putchar('*');
+        r1 = r1 - 1;
+    }} while (r1);
+    halt();
+}}
+", REGISTERS));
+}
+
+#[test]
+fn declares_the_temporaries_of_input() {
+    //Echoes its input until the end of input, which reads as all 1's
+    let mut builder = ProgramBuilder::new();
+    builder
+        .push(Instruction::load_value(6, 1).unwrap())
+        .label("loop")
+        .push(Instruction::input(1))
+        .push(Instruction::add(2, 1, 6))
+        .load_label(3, "done")
+        .load_label(4, "echo")
+        .push(Instruction::cmov(3, 4, 2))
+        .push(Instruction::load_program(0, 3))
+        .label("echo")
+        .push(Instruction::output(1))
+        .load_label(5, "loop")
+        .push(Instruction::load_program(0, 5))
+        .label("done")
+        .push(Instruction::halt());
+
+    assert_eq!(decompiled(&builder), format!("// 11 words, 1 functions\n{}uint32_t t1;
+
+void start(void)
+{{
+    while (1) {{
+        t1 = getchar();
+        r1 = t1;
+        if (t1 == 0xffffffff) break;
+        putchar(r1);
+    }}
+    halt();
+}}
+", REGISTERS));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use rum::differential::{self, Mismatch, Verdict};
+use rum::fault::Fault;
+use common::{loadv, op};
+
+#[test]
+fn agrees_on_halting_programs() {
+    //Echoes one byte of input, upper-cased, from a segment
+    let program = [
+        op(11, 0, 0, 1),
+        loadv(2, 1),
+        op(8, 0, 3, 2),
+        op(2, 3, 0, 1),
+        op(1, 4, 3, 0),
+        loadv(5, 32),
+        op(6, 5, 5, 5),
+        op(3, 4, 4, 5),
+        op(3, 4, 4, 2),
+        op(10, 0, 0, 4),
+        op(9, 0, 0, 3),
+        op(7, 0, 0, 0),
+    ];
+
+    assert_eq!(differential::run(&program, b"x", None), Verdict::Halted { steps: 12, output: b"X".to_vec() });
+    assert_eq!(differential::run(&program, b"x", Some(5)), Verdict::LimitReached { steps: 5 });
+}
+
+#[test]
+fn agrees_on_faults() {
+    let divide = [loadv(1, 1), op(5, 0, 1, 2)];
+    assert_eq!(differential::run(&divide, &[], None), Verdict::Faulted { steps: 1, fault: Fault::DivideByZero });
+
+    let unmap_program = [op(9, 0, 0, 0)];
+    assert_eq!(differential::run(&unmap_program, &[], None), Verdict::Faulted { steps: 0, fault: Fault::UnmapProgram });
+}
+
+#[test]
+fn describes_missing_output() {
+    let mismatch = Mismatch::Output { rum: b"ab".to_vec(), reference: b"a".to_vec() };
+
+    assert_eq!(mismatch.to_string(), "output byte 1 is 0x62 on rum, nothing on the reference");
+}
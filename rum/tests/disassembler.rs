@@ -0,0 +1,91 @@
+use rum::builder::ProgramBuilder;
+use rum::disassembler::{annotated_listing, annotations};
+use rum::um_instruction::Instruction;
+
+//Prints 6 * 7 and a newline, then jumps over an unreachable `Output`
+fn answer() -> ProgramBuilder {
+    let mut builder = ProgramBuilder::new();
+    builder
+        .push(Instruction::load_value(1, 6).unwrap())
+        .push(Instruction::load_value(2, 7).unwrap())
+        .push(Instruction::mul(3, 1, 2))
+        .push(Instruction::output(3))
+        .push(Instruction::load_value(5, 0x1fffff).unwrap())
+        .push(Instruction::nand(6, 5, 5))
+        .push(Instruction::load_value(5, 10).unwrap())
+        .push(Instruction::output(5))
+        .load_label(4, "end")
+        .push(Instruction::load_program(0, 4))
+        .push(Instruction::output(3))
+        .label("end")
+        .push(Instruction::halt());
+
+    builder
+}
+
+//Prints the zero-terminated string at `message`, one word at a time
+fn greet() -> ProgramBuilder {
+    let mut builder = ProgramBuilder::new();
+    builder
+        .load_label(1, "message")
+        .push(Instruction::load_value(7, 1).unwrap())
+        .label("loop")
+        .push(Instruction::load(2, 0, 1))
+        .load_label(3, "print")
+        .load_label(4, "end")
+        .push(Instruction::cmov(4, 3, 2))
+        .push(Instruction::load_program(0, 4))
+        .label("print")
+        .push(Instruction::output(2))
+        .push(Instruction::add(1, 1, 7))
+        .load_label(5, "loop")
+        .push(Instruction::load_program(0, 5))
+        .label("end")
+        .push(Instruction::halt())
+        .label("message")
+        .word('h' as u32)
+        .word('i' as u32)
+        .word(0);
+
+    builder
+}
+
+#[test]
+fn annotates_constants_and_output() {
+    let notes = annotations(&answer().build().unwrap(), None);
+
+    assert_eq!(notes[2], ["r3 = 42"]);
+    assert_eq!(notes[3], ["'*'"]);
+    assert_eq!(notes[5], ["r6 = 0xffe00000"]);
+    assert_eq!(notes[7], ["'\\n'", "prints \"*\\n\""]);
+    assert!(notes[10].is_empty(), "{:?}", notes[10]);
+}
+
+#[test]
+fn annotates_jump_targets() {
+    let builder = answer();
+    let program = builder.build().unwrap();
+
+    assert_eq!(annotations(&program, None)[9], ["-> 11"]);
+
+    let listing = annotated_listing(&program, Some(&builder.symbols()));
+
+    assert!(listing.contains("       9: c0000004  loadp r0, r4  ; -> end\n"), "{}", listing);
+
+    //A conditional jump has both of its targets
+    assert_eq!(annotations(&greet().build().unwrap(), None)[6], ["-> 11", "-> 7"]);
+}
+
+#[test]
+fn annotates_string_loops() {
+    let builder = greet();
+    let program = builder.build().unwrap();
+    let notes = annotations(&program, None);
+
+    assert_eq!(notes[2], ["prints \"hi\" from r0[r1]"]);
+    assert_eq!(notes[10], ["-> 2"]);
+
+    let listing = annotated_listing(&program, Some(&builder.symbols()));
+
+    assert!(listing.contains("loop:\n       2: 10000081  sload r2, r0, r1  ; prints \"hi\" from r0[r1]\n"), "{}", listing);
+}
@@ -0,0 +1,48 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+use rum::ffi;
+
+#[test]
+fn header_is_up_to_date() {
+    let header = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/rum.h")).unwrap();
+
+    assert!(header == ffi::header(), "include/rum.h is stale, regenerate it with `rum header -o include/rum.h`");
+}
+
+//Builds `tests/ffi/test.c` against the cdylib next to the test binary and
+//runs it. Skipped when there is no C compiler
+#[test]
+fn c_program() {
+    let directory = env!("CARGO_MANIFEST_DIR");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    //The test binary is in `target/<profile>/deps`, the library one level up
+    let libraries = env::current_exe().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    let executable: PathBuf = env::temp_dir().join(format!("rum-ffi-test-{}", std::process::id()));
+
+    let built = match Command::new(&compiler)
+        .arg(format!("{}/tests/ffi/test.c", directory))
+        .arg(format!("-I{}/include", directory))
+        .arg(format!("-L{}", libraries.display()))
+        .arg(format!("-Wl,-rpath,{}", libraries.display()))
+        .arg("-lrum")
+        .arg("-o")
+        .arg(&executable)
+        .status()
+    {
+        Ok(status) => status,
+        Err(_) => {
+            eprintln!("skipped: no C compiler `{}`", compiler);
+            return;
+        }
+    };
+
+    assert!(built.success(), "could not build tests/ffi/test.c");
+
+    let output = Command::new(&executable).output().unwrap();
+    let _ = std::fs::remove_file(&executable);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,166 @@
+/* Exercises the C interface of the machine: built and run by tests/ffi.rs
+ * against the cdylib. Prints "ok" and exits with 0 when every check holds. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "rum.h"
+
+#define CHECK(condition) do { \
+        if (!(condition)) { \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+            exit(1); \
+        } \
+    } while (0)
+
+static uint32_t op(uint32_t opcode, uint32_t a, uint32_t b, uint32_t c)
+{
+    return opcode << 28 | a << 6 | b << 3 | c;
+}
+
+static uint32_t loadv(uint32_t a, uint32_t value)
+{
+    return 13u << 28 | a << 25 | value;
+}
+
+/* The I/O of a machine: the input is handed out one byte at a time and the
+ * output is collected */
+struct io {
+    const char *input;
+    char output[64];
+    size_t length;
+};
+
+static int input(void *context)
+{
+    struct io *io = context;
+
+    return *io->input ? (unsigned char)*io->input++ : -1;
+}
+
+/* An input function that never has data, leaving it to rum_provide_input */
+static int no_input(void *context)
+{
+    (void)context;
+    return RUM_NO_INPUT;
+}
+
+static void output(void *context, uint8_t value)
+{
+    struct io *io = context;
+
+    io->output[io->length++] = (char)value;
+    io->output[io->length] = 0;
+}
+
+int main(void)
+{
+    /* Prints 'H', echoes a byte of input and halts */
+    uint32_t echo[] = { loadv(1, 'H'), op(10, 0, 0, 1), op(11, 0, 0, 2), op(10, 0, 0, 2), op(7, 0, 0, 0) };
+    struct io io = { "xy", "", 0 };
+    rum_machine *machine = rum_new(echo, sizeof echo / sizeof *echo);
+    rum_snapshot *snapshot;
+    uint32_t value;
+    uint64_t executed;
+    size_t length;
+    char message[128];
+
+    CHECK(machine != NULL);
+    CHECK(rum_set_io(machine, input, output, &io) == RUM_OK);
+
+    /* Stepping and reading registers */
+    CHECK(rum_step(machine) == RUM_RUNNING);
+    CHECK(rum_pc(machine) == 1);
+    CHECK(rum_get_register(machine, 1, &value) == RUM_OK && value == 'H');
+    CHECK(rum_get_register(machine, 8, &value) == RUM_ERROR);
+
+    /* Running to the end, then again from a snapshot with another register */
+    snapshot = rum_snapshot_take(machine);
+    CHECK(snapshot != NULL);
+    CHECK(rum_run(machine, 100, &executed) == RUM_HALTED);
+    CHECK(executed == 4);
+    CHECK(strcmp(io.output, "Hx") == 0);
+    CHECK(rum_get_register(machine, 2, &value) == RUM_OK && value == 'x');
+
+    CHECK(rum_snapshot_restore(machine, snapshot) == RUM_OK);
+    CHECK(rum_pc(machine) == 1);
+    CHECK(rum_set_register(machine, 1, 'Y') == RUM_OK);
+    CHECK(rum_set_register(machine, 8, 0) == RUM_ERROR);
+    CHECK(rum_run(machine, 100, NULL) == RUM_HALTED);
+    CHECK(strcmp(io.output, "HxYy") == 0);
+
+    /* The end of input fills the register with all 1's */
+    CHECK(rum_snapshot_restore(machine, snapshot) == RUM_OK);
+    CHECK(rum_run(machine, 2, &executed) == RUM_RUNNING);
+    CHECK(executed == 2);
+    CHECK(rum_get_register(machine, 2, &value) == RUM_OK && value == 0xffffffff);
+    rum_snapshot_free(snapshot);
+
+    /* Reading and writing segment words: the program is patched to halt
+     * right away */
+    CHECK(rum_segment_length(machine, 0, &length) == RUM_OK && length == 5);
+    CHECK(rum_segment_length(machine, 1, &length) == RUM_ERROR);
+    CHECK(rum_read_word(machine, 0, 4, &value) == RUM_OK && value == echo[4]);
+    CHECK(rum_read_word(machine, 0, 5, &value) == RUM_ERROR);
+    CHECK(rum_write_word(machine, 0, 0, op(7, 0, 0, 0)) == RUM_OK);
+    CHECK(rum_write_word(machine, 0, 5, 0) == RUM_ERROR);
+    CHECK(rum_read_word(machine, 0, 0, &value) == RUM_OK && value == op(7, 0, 0, 0));
+    rum_free(machine);
+
+    /* Faults are reported and described */
+    {
+        uint32_t invalid[] = { 0xf0000000 };
+
+        machine = rum_new(invalid, 1);
+        CHECK(rum_fault(machine, message, sizeof message) == 0 && message[0] == 0);
+        CHECK(rum_step(machine) == RUM_FAULTED);
+        CHECK(rum_pc(machine) == 0);
+        CHECK(rum_fault(machine, message, sizeof message) == strlen(message));
+        CHECK(strstr(message, "opcode") != NULL);
+        CHECK(rum_fault(machine, message, 4) > 3 && strlen(message) == 3);
+        rum_free(machine);
+    }
+
+    /* The budget stops a program that never halts */
+    {
+        uint32_t loop[] = { loadv(7, 0), op(12, 0, 0, 7) };
+
+        machine = rum_new(loop, 2);
+        CHECK(rum_run(machine, 1000, &executed) == RUM_RUNNING);
+        CHECK(executed == 1000);
+        rum_free(machine);
+    }
+
+    /* Input can be provided once the machine asks for it */
+    {
+        io.length = 0;
+        machine = rum_new(echo, sizeof echo / sizeof *echo);
+        CHECK(rum_set_io(machine, no_input, output, &io) == RUM_OK);
+        CHECK(rum_run(machine, 100, &executed) == RUM_NEEDS_INPUT);
+        CHECK(executed == 2 && rum_pc(machine) == 2);
+        CHECK(rum_step(machine) == RUM_NEEDS_INPUT && rum_pc(machine) == 2);
+        CHECK(rum_provide_input(machine, 256) == RUM_ERROR);
+        CHECK(rum_provide_input(machine, 'z') == RUM_OK);
+        CHECK(rum_run(machine, 100, &executed) == RUM_HALTED && executed == 3);
+        CHECK(strcmp(io.output, "Hz") == 0);
+        rum_free(machine);
+    }
+
+    /* Loading the contents of a .um file */
+    {
+        uint8_t halt[] = { 0x70, 0, 0, 0 };
+
+        CHECK(rum_new_from_bytes(halt, 3) == NULL);
+        machine = rum_new_from_bytes(halt, 4);
+        CHECK(machine != NULL);
+        CHECK(rum_run(machine, 10, &executed) == RUM_HALTED && executed == 1);
+        rum_free(machine);
+    }
+
+    CHECK(rum_step(NULL) == RUM_ERROR);
+    CHECK(rum_provide_input(NULL, -1) == RUM_ERROR);
+    rum_free(NULL);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use rum::rum::{Rum, State};
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+//Maps a segment of `length` words into r1, then outputs r3 and halts
+fn machine(length: u32) -> Rum {
+    let program = [loadv(2, length), op(8, 0, 1, 2), op(10, 0, 0, 3), op(7, 0, 0, 0)];
+    let mut rum = Rum::new(&program);
+
+    assert_eq!(rum.run_for(&mut BufferIo::new(b""), 2).unwrap(), State::Running);
+
+    rum
+}
+
+#[test]
+fn writes_to_a_fork_stay_in_the_fork() {
+    //Long enough to span several pages
+    let original = machine(100_000);
+    let mut fork = original.fork();
+
+    for offset in [0, 1, 16_383, 16_384, 50_000, 99_999] {
+        fork.set_word(1, offset, offset as u32 + 1).unwrap();
+    }
+    fork.set_word(0, 3, op(7, 0, 0, 0)).unwrap();
+    fork.set_register(3, b'f' as u32);
+
+    for offset in [0, 1, 16_383, 16_384, 50_000, 99_999] {
+        assert_eq!(fork.segments().get_segment_value(1, offset), Some(offset as u32 + 1));
+        assert_eq!(original.segments().get_segment_value(1, offset), Some(0));
+    }
+    assert_eq!(fork.segments().get_segment_value(1, 2), Some(0));
+    assert_eq!(original.segments().get_segment_value(0, 3), Some(op(7, 0, 0, 0)));
+    assert!(fork.set_word(1, 100_000, 0).is_err());
+    assert_eq!(fork.segments().segment_len(1), Some(100_000));
+
+    let mut output = BufferIo::new(b"");
+    fork.run(&mut output).unwrap();
+    assert_eq!(output.output, b"f");
+}
+
+#[test]
+fn forks_run_independently() {
+    let mut original = machine(4);
+    let mut forks: Vec<Rum> = (0..3).map(|_| original.fork()).collect();
+
+    for (index, fork) in forks.iter_mut().enumerate() {
+        fork.set_register(3, b'a' as u32 + index as u32);
+        fork.set_word(1, 0, index as u32).unwrap();
+    }
+
+    let mut output = BufferIo::new(b"");
+    for fork in forks.iter_mut() {
+        fork.run(&mut output).unwrap();
+    }
+    original.run(&mut output).unwrap();
+
+    assert_eq!(output.output, b"abc\0");
+    assert_eq!(original.segments().segment_words(1), Some(vec![0; 4]));
+    assert_eq!(forks[2].segments().segment_words(1), Some(vec![2, 0, 0, 0]));
+}
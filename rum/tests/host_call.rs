@@ -0,0 +1,118 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use rum::fault::Fault;
+use rum::rum::{Mode, Rum, State};
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+//Executes opcode 14 with a = 1, b = 2 and c = 3, then halts
+fn program() -> Vec<u32> {
+    vec![
+        loadv(2, 20),
+        loadv(3, 22),
+        op(14, 1, 2, 3),
+        op(7, 0, 0, 0),
+    ]
+}
+
+//Runs `rum` in both modes from a copy, checking they end the same way
+fn run(rum: &Rum) -> (Rum, Result<State, Fault>) {
+    let outcomes: Vec<(Rum, Result<State, Fault>)> = [Mode::Fast, Mode::Strict]
+        .into_iter()
+        .map(|mode| {
+            let mut rum = rum.clone();
+            rum.set_mode(mode);
+            let result = rum.run(&mut BufferIo::new(&[]));
+            (rum, result)
+        })
+        .collect();
+
+    assert_eq!((outcomes[0].0.pc(), outcomes[0].1), (outcomes[1].0.pc(), outcomes[1].1));
+
+    outcomes.into_iter().next().unwrap()
+}
+
+#[test]
+fn faults_without_a_handler() {
+    for opcode in [14, 15] {
+        let word = op(opcode, 1, 2, 3);
+        let (rum, result) = run(&Rum::new(&[word]));
+
+        assert_eq!(result, Err(Fault::InvalidOpcode { pc: 0, word }));
+        assert_eq!(rum.pc(), 0);
+    }
+}
+
+#[test]
+fn calls_the_registered_handler() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let mut rum = Rum::new(&program());
+
+    rum.register_host_call(14, move |call| {
+        counter.set(counter.get() + 1);
+        assert_eq!((call.pc, call.a, call.b, call.c), (2, 1, 2, 3));
+
+        call.registers[call.a] = call.registers[call.b] + call.registers[call.c];
+        Ok(())
+    });
+
+    let (rum, result) = run(&rum);
+
+    assert_eq!(result, Ok(State::Halted));
+    assert_eq!(rum.registers()[1], 42);
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn handlers_reach_memory_through_checked_accesses() {
+    let mut rum = Rum::new(&program());
+
+    //Patches the `Halt` after the call into an `Output` of r1, which is then
+    //followed by the end of the program
+    rum.register_host_call(14, |call| {
+        assert_eq!(call.segment_len(0), Ok(4));
+        assert_eq!(call.load(0, 3), Ok(op(7, 0, 0, 0)));
+        assert_eq!(call.load(0, 4), Err(Fault::OutOfBounds { id: 0, offset: 4 }));
+        assert_eq!(call.store(1, 0, 0), Err(Fault::UnmappedSegment { id: 1 }));
+
+        call.registers[1] = '!' as u32;
+        call.store(0, 3, op(10, 0, 0, 1))
+    });
+
+    let mut io = BufferIo::new(&[]);
+
+    assert_eq!(rum.run(&mut io), Err(Fault::PcOutOfBounds { pc: 4 }));
+    assert_eq!(io.output, b"!");
+}
+
+#[test]
+fn faults_raised_by_handlers_stop_the_machine() {
+    let mut rum = Rum::new(&program());
+
+    rum.register_host_call(14, |call| call.load(5, 0).map(|_| ()));
+
+    let (rum, result) = run(&rum);
+
+    assert_eq!(result, Err(Fault::UnmappedSegment { id: 5 }));
+    assert_eq!(rum.pc(), 2);
+}
+
+#[test]
+fn unregistered_handlers_fault_again() {
+    let mut rum = Rum::new(&program());
+
+    rum.register_host_call(14, |_| Ok(()));
+    assert_eq!(run(&rum).1, Ok(State::Halted));
+
+    rum.unregister_host_call(14);
+    assert_eq!(run(&rum).1, Err(Fault::InvalidOpcode { pc: 2, word: op(14, 1, 2, 3) }));
+}
+
+#[test]
+#[should_panic]
+fn only_extends_opcodes_14_and_15() {
+    Rum::new(&[]).register_host_call(13, |_| Ok(()));
+}
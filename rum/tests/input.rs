@@ -0,0 +1,64 @@
+mod common;
+
+use std::task::Poll;
+
+use rum::rum::{Rum, State};
+use rum::um_io::{BufferIo, UmIo};
+use common::{op};
+
+//Reads two bytes into r1 and r2, outputs the first and halts
+fn program() -> Rum {
+    Rum::new(&[op(11, 0, 0, 1), op(11, 0, 0, 2), op(10, 0, 0, 1), op(7, 0, 0, 0)])
+}
+
+//An I/O that never has input ready, leaving it to `provide_input`
+#[derive(Default)]
+struct Suspending {
+    output: Vec<u8>,
+}
+
+impl UmIo for Suspending {
+    fn input(&mut self) -> Option<u8> {
+        panic!("the machine must not wait for input")
+    }
+
+    fn try_input(&mut self) -> Poll<Option<u8>> {
+        Poll::Pending
+    }
+
+    fn output(&mut self, value: u8) {
+        self.output.push(value);
+    }
+}
+
+#[test]
+fn suspends_until_input_is_provided() {
+    let mut rum = program();
+    let mut io = Suspending::default();
+
+    assert_eq!(rum.step(&mut io), Ok(State::NeedsInput));
+    assert_eq!(rum.run(&mut io), Ok(State::NeedsInput));
+    assert_eq!(rum.pc(), 0);
+
+    rum.provide_input(Some(b'a'));
+    assert_eq!(rum.run_for(&mut io, 10), Ok(State::NeedsInput));
+    assert_eq!(rum.pc(), 1);
+    assert_eq!(rum.registers()[1], b'a' as u32);
+
+    rum.provide_input(None);
+    assert_eq!(rum.run(&mut io), Ok(State::Halted));
+    assert_eq!(rum.registers()[2], u32::MAX);
+    assert_eq!(io.output, b"a");
+}
+
+#[test]
+fn provided_input_comes_before_the_io() {
+    let mut rum = program();
+    let mut io = BufferIo::new(b"c");
+
+    rum.provide_input(Some(b'p'));
+
+    assert_eq!(rum.run(&mut io), Ok(State::Halted));
+    assert_eq!(&rum.registers()[1..3], [b'p' as u32, b'c' as u32]);
+    assert_eq!(io.output, b"p");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use rum::lifetime::{LifetimeTracker, SegmentLife};
+use rum::rum::{Rum, State};
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+#[test]
+fn reports_leaks_and_statistics() {
+    //Maps segments 1 and 2, unmaps 2 and maps it again, leaking both
+    let program = [
+        loadv(1, 4),
+        op(8, 0, 2, 1),
+        loadv(3, 8),
+        op(8, 0, 4, 3),
+        op(2, 2, 0, 3),
+        op(9, 0, 0, 4),
+        op(8, 0, 5, 1),
+        op(1, 6, 5, 0),
+        op(7, 0, 0, 0),
+    ];
+    let mut rum = Rum::new(&program);
+    let mut tracker = LifetimeTracker::new();
+
+    assert_eq!(rum.run_monitored(&mut BufferIo::new(&[]), &mut tracker), Ok(State::Halted));
+    assert_eq!(rum.executed(), 9);
+
+    let report = tracker.report(&rum);
+
+    assert_eq!(report.leaks, [
+        SegmentLife { id: 1, size: 4, allocation: 1, map_pc: 1, last_access_pc: Some(4) },
+        SegmentLife { id: 2, size: 4, allocation: 3, map_pc: 6, last_access_pc: Some(7) },
+    ]);
+    assert_eq!((report.maps, report.unmaps), (3, 1));
+    assert_eq!((report.peak_segments, report.peak_words), (2, 12));
+    assert!(report.to_string().starts_with("leak report: 2 segment(s) still mapped, 8 word(s)\n"), "{}", report);
+}
+
+#[test]
+fn reports_nothing_when_everything_is_unmapped() {
+    let program = [loadv(1, 3), op(8, 0, 2, 1), op(9, 0, 0, 2), op(7, 0, 0, 0)];
+    let mut rum = Rum::new(&program);
+    let mut tracker = LifetimeTracker::new();
+
+    rum.run_monitored(&mut BufferIo::new(&[]), &mut tracker).unwrap();
+
+    let report = tracker.report(&rum);
+
+    assert!(report.leaks.is_empty());
+    assert_eq!((report.maps, report.unmaps, report.peak_segments, report.peak_words), (1, 1, 1, 3));
+}
@@ -0,0 +1,93 @@
+use rum::assembler::{assemble_object, Fixup, FixupKind, Unit};
+use rum::object::{link, read, write, LinkError};
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+
+fn object(name: &str, source: &str) -> (String, Unit) {
+    (name.to_string(), assemble_object(source, name).unwrap())
+}
+
+//Prints the character at `message` then returns to the caller
+const PRINT: &str = "
+    .global print, message
+print:
+    loadv r1, message
+    sload r1, r0, r1
+    out r1
+    jmp r3
+message:
+    .word '!'
+";
+
+#[test]
+fn objects_round_trip() {
+    let (_, unit) = object("print.s", PRINT);
+    let bytes = write(&unit);
+
+    assert_eq!(read(&bytes), Ok(unit));
+    assert!(read(&bytes[..bytes.len() - 1]).is_err());
+    assert!(read(b"UMOX").is_err());
+}
+
+#[test]
+fn relocates_labels_across_objects() {
+    let main = object("main.s", "
+    .extern print
+    loadv r3, back
+    jmp print
+back:
+    halt
+    ");
+    let print = object("print.s", PRINT);
+    let assembly = link(&[main.clone(), print]).unwrap();
+    let base = main.1.words.len() as u32;
+
+    assert_eq!(assembly.symbols.address_of("print"), Some(base));
+    assert_eq!(assembly.symbols.address_of("message"), Some(base + 4));
+    assert_eq!(assembly.words[base as usize] & 0x1ff_ffff, base + 4);
+
+    let mut rum = Rum::new(&assembly.words);
+    let mut io = BufferIo::new(&[]);
+
+    rum.run(&mut io).unwrap();
+
+    assert_eq!(io.output, b"!");
+}
+
+#[test]
+fn reports_unresolved_and_duplicate_symbols() {
+    let main = object("main.s", ".extern missing\n    loadv r1, missing\n    halt\n");
+
+    assert_eq!(
+        link(&[main]).unwrap_err(),
+        LinkError::UndefinedSymbol { symbol: "missing".to_string(), object: "main.s".to_string() }
+    );
+
+    let (first, second) = (object("a.s", PRINT), object("b.s", PRINT));
+
+    assert_eq!(
+        link(&[first, second]).unwrap_err(),
+        LinkError::DuplicateSymbol { symbol: "print".to_string(), first: "a.s".to_string(), second: "b.s".to_string() }
+    );
+}
+
+#[test]
+fn rejects_offsets_past_the_end() {
+    let (name, mut unit) = object("print.s", PRINT);
+    let end = unit.words.len() as u32;
+
+    unit.labels.push(("past".to_string(), end + 1));
+    assert!(read(&write(&unit)).unwrap_err().contains("`past`"));
+    assert_eq!(
+        link(&[(name.clone(), unit.clone())]).unwrap_err(),
+        LinkError::InvalidOffset { symbol: "past".to_string(), offset: end + 1, object: name.clone() }
+    );
+
+    unit.labels.pop();
+    unit.fixups.push(Fixup { offset: end, kind: FixupKind::Word, label: "print".to_string() });
+    assert!(read(&write(&unit)).is_err());
+    assert_eq!(
+        link(&[(name.clone(), unit)]).unwrap_err(),
+        LinkError::InvalidOffset { symbol: "print".to_string(), offset: end, object: name }
+    );
+}
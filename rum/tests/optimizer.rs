@@ -0,0 +1,163 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use rum::assembler::to_bytes;
+use rum::{cfg, decompiler, disassembler};
+use rum::differential::{compare, Divergence, Mismatch, Verdict};
+use rum::optimizer::{optimize, Stats, NOP};
+use common::{loadv, op};
+
+fn halts_alike(program: &[u32], optimized: &[u32], input: &[u8], output: &[u8]) {
+    match compare(program, optimized, input, Some(1000)) {
+        Verdict::Halted { output: halted, .. } => assert_eq!(halted, output),
+        verdict => panic!("{}", verdict),
+    }
+}
+
+#[test]
+fn folds_constants_and_removes_dead_code() {
+    let program = [
+        loadv(1, 6),
+        loadv(2, 7),
+        op(4, 3, 1, 2),
+        loadv(4, 1),
+        op(10, 0, 0, 3),
+        op(7, 0, 0, 0),
+    ];
+
+    let optimized = optimize(&program);
+
+    assert_eq!(optimized.words, [NOP, NOP, loadv(3, 42), NOP, op(10, 0, 0, 3), op(7, 0, 0, 0)]);
+    assert_eq!(optimized.stats, Stats { dead: 3, conditional_moves: 0, nands: 0, folded: 1 });
+    halts_alike(&program, &optimized.words, b"", b"*");
+}
+
+#[test]
+fn computed_jumps_may_land_inside_a_block() {
+    //Prints r2 + r2 from 11 onwards, first with r2 = 25 then, jumping to 11
+    //+ the digit read, with r2 = 29 when the digit is 1
+    let program = [
+        op(11, 0, 0, 1),
+        loadv(7, 47),
+        op(6, 7, 7, 7),
+        op(3, 1, 1, 7),
+        loadv(6, 11),
+        op(12, 0, 0, 6),
+        loadv(2, 29),
+        op(3, 6, 6, 1),
+        op(12, 0, 0, 6),
+        op(7, 0, 0, 0),
+        op(7, 0, 0, 0),
+        loadv(2, 25),
+        op(3, 3, 2, 2),
+        op(10, 0, 0, 3),
+        loadv(5, 6),
+        loadv(7, 19),
+        op(0, 5, 7, 4),
+        loadv(4, 1),
+        op(12, 0, 0, 5),
+        op(7, 0, 0, 0),
+    ];
+
+    let optimized = optimize(&program);
+
+    halts_alike(&program, &optimized.words, b"0", b"22");
+    halts_alike(&program, &optimized.words, b"1", b"2:");
+}
+
+#[test]
+fn handles_empty_images() {
+    let optimized = optimize(&[]);
+
+    assert!(optimized.words.is_empty());
+    assert_eq!((optimized.blocks, optimized.reachable), (0, 0));
+
+    let graph = cfg::recover(&[], None);
+
+    assert!(graph.blocks.is_empty() && graph.edges.is_empty() && graph.functions.is_empty());
+    assert_eq!(graph.listing(), "");
+    assert!(decompiler::decompile(&[], None).starts_with("// 0 words"));
+    assert_eq!(disassembler::annotated_listing(&[], None), "");
+
+}
+
+#[test]
+fn compare_finds_different_output() {
+    let first = [loadv(1, '1' as u32), op(10, 0, 0, 1), loadv(1, '2' as u32), op(10, 0, 0, 1), op(7, 0, 0, 0)];
+    let second = [loadv(1, '1' as u32), op(10, 0, 0, 1), loadv(1, '3' as u32), op(10, 0, 0, 1), op(7, 0, 0, 0)];
+
+    let verdict = compare(&first, &second, &[], None);
+
+    assert_eq!(verdict, Verdict::Diverged(Divergence {
+        step: 3,
+        pc: 3,
+        word: op(10, 0, 0, 1),
+        mismatch: Mismatch::Output { rum: b"12".to_vec(), reference: b"13".to_vec() },
+    }));
+    assert!(verdict.to_string().ends_with("output byte 1 is 0x32 on rum, 0x33 on the reference"), "{}", verdict);
+}
+
+//Writes `words` as `program.um` in a fresh directory
+fn image(name: &str, words: &[u32]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rum-optimizer-{}-{}", name, std::process::id()));
+
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("program.um"), to_bytes(words)).unwrap();
+
+    directory
+}
+
+fn rum(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rum")).args(arguments).output().unwrap()
+}
+
+//Prints `input` back, upper-cased by subtracting 32 from every byte
+fn shout() -> Vec<u32> {
+    vec![
+        loadv(6, 32),
+        loadv(7, 1),
+        op(11, 0, 0, 1),
+        op(6, 2, 1, 1),
+        loadv(3, 14),
+        loadv(4, 8),
+        op(0, 3, 4, 2),
+        op(12, 0, 0, 3),
+        op(6, 5, 6, 6),
+        op(3, 1, 1, 5),
+        op(3, 1, 1, 7),
+        op(10, 0, 0, 1),
+        loadv(3, 2),
+        op(12, 0, 0, 3),
+        op(7, 0, 0, 0),
+    ]
+}
+
+#[test]
+fn optimizes_only_what_verifies() {
+    //Outputs the word at the offset read, which the optimizer removes when
+    //it is the dead `loadv r3, 1` at offset 3
+    let directory = image("diverges", &[op(11, 0, 0, 1), op(1, 2, 0, 1), op(10, 0, 0, 2), loadv(3, 1), loadv(3, 2), op(7, 0, 0, 0)]);
+    let (program, input, optimized) = (directory.join("program.um"), directory.join("in"), directory.join("optimized.um"));
+
+    fs::write(&input, [3]).unwrap();
+
+    let output = rum(&["opt", program.to_str().unwrap(), "-o", optimized.to_str().unwrap(), "--verify", "--input", input.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("diverged"));
+    assert!(!optimized.exists());
+
+    //Upper-cases its input, which the optimizer cannot break
+    let directory = image("agrees", &shout());
+    let (program, input, optimized) = (directory.join("program.um"), directory.join("in"), directory.join("optimized.um"));
+
+    fs::write(&input, "ok").unwrap();
+
+    let output = rum(&["opt", program.to_str().unwrap(), "-o", optimized.to_str().unwrap(), "--verify", "--input", input.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(optimized.exists());
+}
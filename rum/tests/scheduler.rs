@@ -0,0 +1,68 @@
+mod common;
+
+use rum::rum::Rum;
+use rum::scheduler::{Scheduler, Stats, Status};
+use common::{loadv, op};
+
+//Echoes a byte of input, then reads another into r2 and halts
+fn echo() -> Rum {
+    Rum::new(&[op(11, 0, 0, 1), op(10, 0, 0, 1), op(11, 0, 0, 2), op(7, 0, 0, 0)])
+}
+
+//Jumps to itself forever
+fn spin() -> Rum {
+    Rum::new(&[loadv(7, 1), op(12, 0, 0, 7)])
+}
+
+#[test]
+fn waits_for_input() {
+    let mut scheduler = Scheduler::new(100);
+    let id = scheduler.spawn(echo());
+
+    scheduler.run_until_idle();
+    assert_eq!(scheduler.status(id), Status::Waiting);
+    assert_eq!(scheduler.machine(id).pc(), 0);
+    assert!(scheduler.is_idle());
+
+    scheduler.provide_input(id, b"x");
+    assert_eq!(scheduler.status(id), Status::Ready);
+    assert_eq!(scheduler.pending_input(id), 1);
+    scheduler.run_until_idle();
+    assert_eq!(scheduler.status(id), Status::Waiting);
+    assert_eq!(scheduler.machine(id).pc(), 2);
+    assert_eq!(scheduler.take_output(id), b"x");
+    assert_eq!(scheduler.take_output(id), b"");
+
+    scheduler.close_input(id);
+    scheduler.run_until_idle();
+    assert_eq!(scheduler.status(id), Status::Halted);
+    assert_eq!(scheduler.machine(id).registers()[2], u32::MAX);
+    assert_eq!(scheduler.stats(id), Stats { instructions: 4, slices: 3, waits: 2, input_bytes: 1, output_bytes: 1 });
+
+    //A halted machine stays halted
+    scheduler.provide_input(id, b"y");
+    assert_eq!(scheduler.run_slice(), None);
+}
+
+#[test]
+fn time_slices_machines() {
+    let mut scheduler = Scheduler::new(10);
+    let spinning = scheduler.spawn(spin());
+    let echoing = scheduler.spawn(echo());
+    let faulting = scheduler.spawn(Rum::new(&[0xe000_0000]));
+
+    scheduler.provide_input(echoing, b"ab");
+
+    let order: Vec<_> = (0..5).map(|_| scheduler.run_slice().unwrap()).collect();
+
+    assert_eq!(order, [spinning, echoing, faulting, spinning, spinning]);
+    assert_eq!(scheduler.status(spinning), Status::Ready);
+    assert_eq!(scheduler.status(echoing), Status::Halted);
+    assert!(matches!(scheduler.status(faulting), Status::Faulted(_)));
+    assert_eq!(scheduler.stats(spinning).instructions, 30);
+    assert_eq!(scheduler.stats(spinning).slices, 3);
+    assert_eq!(scheduler.stats(faulting).instructions, 0);
+    assert_eq!(scheduler.stats(echoing).input_bytes, 2);
+    assert_eq!(scheduler.take_output(echoing), b"a");
+    assert!(!scheduler.is_idle());
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use rum::fault::Fault;
+use rum::rum::{Mode, Rum, State};
+use rum::um_io::BufferIo;
+use common::{loadv, op};
+
+//Runs `program` in both modes, checking they fault the same way at the same pc
+fn fault(program: &[u32]) -> (usize, Fault) {
+    let outcomes: Vec<(usize, Result<State, Fault>)> = [Mode::Fast, Mode::Strict]
+        .into_iter()
+        .map(|mode| {
+            let mut rum = Rum::new(program);
+            rum.set_mode(mode);
+            let result = rum.run(&mut BufferIo::new(&[]));
+            (rum.pc(), result)
+        })
+        .collect();
+
+    assert_eq!(outcomes[0], outcomes[1]);
+
+    match outcomes[0] {
+        (pc, Err(fault)) => (pc, fault),
+        (_, Ok(state)) => panic!("stopped without faulting: {:?}", state),
+    }
+}
+
+#[test]
+fn unmapping_twice_faults() {
+    let program = [
+        loadv(1, 4),
+        op(8, 0, 2, 1),
+        op(9, 0, 0, 2),
+        op(9, 0, 0, 2),
+    ];
+
+    assert_eq!(fault(&program), (3, Fault::UnmappedSegment { id: 1 }));
+}
+
+#[test]
+fn an_address_is_reused_once() {
+    //Maps a segment, unmaps it, then maps two more: only the first may reuse its id
+    let program = [
+        loadv(1, 4),
+        op(8, 0, 2, 1),
+        op(9, 0, 0, 2),
+        op(8, 0, 3, 1),
+        op(8, 0, 4, 1),
+        op(7, 0, 0, 0),
+    ];
+    let mut rum = Rum::new(&program);
+
+    assert_eq!(rum.run(&mut BufferIo::new(&[])), Ok(State::Halted));
+    assert_eq!(rum.executed(), 6);
+    assert_eq!(&rum.registers()[2..5], [1, 1, 2]);
+}
+
+#[test]
+fn unmapped_segments_fault_as_unmapped() {
+    let map_and_unmap = [loadv(1, 4), op(8, 0, 2, 1), op(9, 0, 0, 2)];
+
+    let load = [&map_and_unmap[..], &[op(1, 3, 2, 0)]].concat();
+    assert_eq!(fault(&load), (3, Fault::UnmappedSegment { id: 1 }));
+
+    let store = [&map_and_unmap[..], &[op(2, 2, 0, 0)]].concat();
+    assert_eq!(fault(&store), (3, Fault::UnmappedSegment { id: 1 }));
+
+    let load_program = [&map_and_unmap[..], &[op(12, 0, 2, 0)]].concat();
+    assert_eq!(fault(&load_program), (3, Fault::UnmappedSegment { id: 1 }));
+
+    let out_of_bounds = [loadv(1, 4), op(8, 0, 2, 1), op(1, 3, 2, 1)];
+    assert_eq!(fault(&out_of_bounds), (2, Fault::OutOfBounds { id: 1, offset: 4 }));
+}
+
+#[test]
+fn mapping_past_the_memory_limit_faults() {
+    //Maps 4 words, unmaps them and maps 4 again, then 5 more words than the
+    //program and the 4 words leave room for
+    let program = [
+        loadv(1, 4),
+        op(8, 0, 2, 1),
+        op(9, 0, 0, 2),
+        op(8, 0, 2, 1),
+        loadv(1, 5),
+        op(8, 0, 3, 1),
+        op(7, 0, 0, 0),
+    ];
+
+    for mode in [Mode::Fast, Mode::Strict] {
+        let mut rum = Rum::new(&program);
+        rum.set_mode(mode);
+        rum.set_memory_limit(program.len() + 8);
+
+        assert_eq!(rum.run(&mut BufferIo::new(&[])), Err(Fault::MemoryLimit { size: 5 }));
+        assert_eq!((rum.pc(), rum.registers()[3]), (5, 0));
+        assert_eq!(rum.segments().total_words(), program.len() + 4);
+    }
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rum::server::{ServeConfig, Server};
+use common::{loadv, op};
+
+//Echoes its input until the end of it, then halts
+fn echo() -> Vec<u32> {
+    vec![
+        op(11, 0, 0, 1),
+        op(6, 2, 1, 1),
+        loadv(3, 9),
+        loadv(4, 6),
+        op(0, 3, 4, 2),
+        op(12, 0, 0, 3),
+        op(10, 0, 0, 1),
+        loadv(5, 0),
+        op(12, 0, 0, 5),
+        op(7, 0, 0, 0),
+    ]
+}
+
+fn server(program: &[u32], config: ServeConfig) -> Server {
+    Server::new(TcpListener::bind("127.0.0.1:0").unwrap(), program, config).unwrap()
+}
+
+//Sends `input` to the server, closes the sending half and returns everything
+//received until the server closes the connection
+fn client(server: &Server, input: &'static [u8]) -> JoinHandle<Vec<u8>> {
+    let address = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut output = Vec::new();
+
+        stream.write_all(input).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        stream.read_to_end(&mut output).unwrap();
+
+        output
+    })
+}
+
+//Polls `server` until every client is done, returning what they received
+//and the log
+fn serve(server: &mut Server, clients: Vec<JoinHandle<Vec<u8>>>) -> (Vec<Vec<u8>>, String) {
+    let mut log = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(30);
+
+    while !clients.iter().all(JoinHandle::is_finished) {
+        assert!(Instant::now() < deadline, "clients still running");
+
+        if !server.poll(&mut log).unwrap() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    let outputs = clients.into_iter().map(|client| client.join().unwrap()).collect();
+
+    (outputs, String::from_utf8(log).unwrap())
+}
+
+#[test]
+fn one_machine_per_connection() {
+    let mut server = server(&echo(), ServeConfig::default());
+    let clients = vec![client(&server, b"hello"), client(&server, b"world"), client(&server, b"")];
+
+    let (outputs, log) = serve(&mut server, clients);
+
+    assert_eq!(outputs, [b"hello".to_vec(), b"world".to_vec(), Vec::new()]);
+    assert_eq!(log.matches(" started").count(), 3);
+    assert_eq!(log.matches("halted").count(), 3);
+    assert!(log.contains("5 bytes in, 5 bytes out"), "{}", log);
+    assert_eq!(server.active(), 0);
+}
+
+#[test]
+fn limits_sessions() {
+    let config = ServeConfig { max_instructions: 1000, slice: 100, ..ServeConfig::default() };
+    let mut server = server(&[loadv(7, 1), op(12, 0, 0, 7)], config);
+
+    let clients = vec![client(&server, b"")];
+    let (outputs, log) = serve(&mut server, clients);
+
+    assert_eq!(outputs, [Vec::new()]);
+    assert!(log.contains("instruction limit reached (1000 instructions"), "{}", log);
+}
+
+#[test]
+fn limits_memory() {
+    //Maps a segment of 2^20 words, past a limit of 2^16
+    let config = ServeConfig { max_words: 1 << 16, ..ServeConfig::default() };
+    let mut server = server(&[loadv(1, 1 << 20), op(8, 0, 2, 1), op(7, 0, 0, 0)], config);
+
+    let clients = vec![client(&server, b"")];
+    let (outputs, log) = serve(&mut server, clients);
+
+    assert_eq!(outputs, [Vec::new()]);
+    assert!(log.contains("memory limit reached (1 instructions"), "{}", log);
+}
+
+#[test]
+fn sends_the_rest_of_the_output_without_blocking() {
+    //Prints 2^20 x's and halts
+    let program = [
+        loadv(1, 'x' as u32),
+        loadv(2, 1 << 20),
+        loadv(6, 0),
+        op(6, 6, 6, 6),
+        op(10, 0, 0, 1),
+        op(3, 2, 2, 6),
+        loadv(3, 10),
+        loadv(4, 4),
+        op(0, 3, 4, 2),
+        op(12, 0, 0, 3),
+        op(7, 0, 0, 0),
+    ];
+    let mut server = server(&program, ServeConfig::default());
+    let address = server.local_addr().unwrap();
+
+    //The client only reads once the machine halted
+    let reader = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut output = Vec::new();
+
+        thread::sleep(Duration::from_secs(1));
+        stream.read_to_end(&mut output).unwrap();
+
+        output
+    });
+
+    let mut log = Vec::new();
+    let mut slowest = Duration::ZERO;
+
+    while !reader.is_finished() {
+        let start = Instant::now();
+
+        server.poll(&mut log).unwrap();
+        slowest = slowest.max(start.elapsed());
+    }
+
+    let output = reader.join().unwrap();
+
+    assert_eq!((output.len(), output.iter().all(|byte| *byte == b'x')), (1 << 20, true));
+    assert!(slowest < Duration::from_millis(500), "a poll took {:?}", slowest);
+    assert!(String::from_utf8(log).unwrap().contains("halted"));
+    assert_eq!(server.active(), 0);
+}
+
+#[test]
+fn caps_connections() {
+    let config = ServeConfig { max_sessions: 1, ..ServeConfig::default() };
+    let mut server = server(&echo(), config);
+    let address = server.local_addr().unwrap();
+    let mut log = Vec::new();
+
+    //The first session stays open, waiting for input
+    let mut first = TcpStream::connect(address).unwrap();
+    while server.active() == 0 {
+        server.poll(&mut log).unwrap();
+    }
+
+    //Sending nothing, since what the server does not read makes it reset the
+    //connection when turning it away
+    let clients = vec![client(&server, b"")];
+    let (outputs, log) = serve(&mut server, clients);
+
+    assert_eq!(outputs, [b"server busy, try again later\n".to_vec()]);
+    assert!(log.contains("refused"), "{}", log);
+    assert_eq!(server.active(), 1);
+
+    first.write_all(b"ok").unwrap();
+    first.shutdown(Shutdown::Write).unwrap();
+
+    let mut output = Vec::new();
+    let reader = thread::spawn(move || {
+        first.read_to_end(&mut output).unwrap();
+        output
+    });
+
+    assert_eq!(serve(&mut server, vec![reader]).0, [b"ok".to_vec()]);
+}
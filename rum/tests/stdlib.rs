@@ -0,0 +1,217 @@
+use rum::assembler::assemble_object;
+use rum::object::link;
+use rum::rum::Rum;
+use rum::stdlib;
+use rum::um_io::BufferIo;
+
+const EXTERNS: &str = "print_u32, print_i32, print_hex, read_line, memcpy, memset, strcmp, \
+                       ult, slt, sle, sgt, sge, heap_init, heap_alloc, heap_free, heap_segment";
+
+//Links `driver` (run once the stack is set up) with the library and runs it
+//on `input`, returning the machine once halted and the output
+fn run(driver: &str, input: &[u8]) -> (Rum, String) {
+    let source = format!(".extern {}\n    initstack 1000\n{}\n    halt\n", EXTERNS, driver);
+    let main = assemble_object(&source, "driver.s").unwrap_or_else(|error| panic!("{}", error));
+    let program = link(&[("driver.s".to_string(), main), (stdlib::NAME.to_string(), stdlib::unit())]).unwrap();
+    let mut rum = Rum::new(&program.words);
+    let mut io = BufferIo::new(input);
+
+    rum.run(&mut io).unwrap_or_else(|fault| panic!("fault at pc {}: {}", rum.pc(), fault));
+
+    (rum, String::from_utf8(io.output).unwrap())
+}
+
+//Calls `routine` on r1 = `value` and prints a space
+fn call(routine: &str, value: u32) -> String {
+    format!("    li32 r1, {}\n    call {}\n    loadv r7, ' '\n    out r7\n", value, routine)
+}
+
+//Fills the segment in `r1` with the bytes of `text` followed by 0, using r3 and r4
+fn string(text: &str) -> String {
+    text.bytes()
+        .chain([0])
+        .enumerate()
+        .map(|(index, byte)| format!("    loadv r3, {}\n    loadv r4, {}\n    sstore r1, r3, r4\n", index, byte))
+        .collect()
+}
+
+//Returns the words of segment `id`
+fn segment(rum: &Rum, id: u32) -> Vec<u32> {
+    rum.segments().segment_words(id as usize).unwrap()
+}
+
+#[test]
+fn print_u32() {
+    let driver: String = [0, 7, 1234567890, u32::MAX].iter().map(|value| call("print_u32", *value)).collect();
+
+    assert_eq!(run(&driver, b"").1, "0 7 1234567890 4294967295 ");
+}
+
+#[test]
+fn print_i32() {
+    let values = [0, -1, 42, i32::MIN, i32::MAX];
+    let driver: String = values.iter().map(|value| call("print_i32", *value as u32)).collect();
+
+    assert_eq!(run(&driver, b"").1, "0 -1 42 -2147483648 2147483647 ");
+}
+
+#[test]
+fn print_hex() {
+    let driver: String = [0, 255, 0xdeadbeef, 0x10].iter().map(|value| call("print_hex", *value)).collect();
+
+    assert_eq!(run(&driver, b"").1, "0 ff deadbeef 10 ");
+}
+
+#[test]
+fn read_line() {
+    //r2 and r3 keep the segments of the first two lines, r4 the one reused
+    //for the others
+    let driver = "
+    loadv r1, 16
+    map r2, r1
+    map r4, r1
+    loadv r1, 4
+    map r3, r1
+    push r2
+    mov r1, r2
+    loadv r2, 16
+    call read_line
+    call print_i32
+    mov r1, r3
+    loadv r2, 4
+    call read_line
+    call print_i32
+    loadv r2, 16
+    mov r1, r4
+    call read_line
+    call print_i32
+    mov r1, r4
+    call read_line
+    call print_i32
+    mov r1, r4
+    call read_line
+    call print_i32
+    pop r2
+    ";
+    let (rum, output) = run(driver, b"hello\nworld\nend");
+
+    //The second line is cut to 3 bytes, the rest of it is read next
+    assert_eq!(output, "5323-1");
+    assert_eq!(&segment(&rum, rum.registers()[2])[..6], &[104, 101, 108, 108, 111, 0]);
+    assert_eq!(segment(&rum, rum.registers()[3]), vec![119, 111, 114, 0]);
+    assert_eq!(segment(&rum, rum.registers()[4])[0], 0);
+}
+
+#[test]
+fn memcpy_and_memset() {
+    let driver = "
+    loadv r1, 5
+    map r2, r1
+    map r3, r1
+    mov r1, r2
+    push r2
+    push r3
+    loadv r2, 7
+    loadv r3, 3
+    call memset
+    pop r3
+    pop r2
+    mov r1, r3
+    loadv r3, 4
+    call memcpy
+    ";
+    let (rum, _) = run(driver, b"");
+
+    assert_eq!(segment(&rum, rum.registers()[2]), vec![7, 7, 7, 0, 0]);
+    assert_eq!(segment(&rum, rum.registers()[1]), vec![7, 7, 7, 0, 0]);
+}
+
+#[test]
+fn strcmp() {
+    let cases = [("abc", "abc"), ("abc", "abd"), ("b", "abc"), ("ab", "abc"), ("abc", "ab"), ("", "")];
+    let mut driver = String::new();
+
+    for (left, right) in cases {
+        driver += "    loadv r1, 8\n    map r2, r1\n    map r1, r1\n";
+        driver += &string(right);
+        driver += "    push r1\n    mov r1, r2\n";
+        driver += &string(left);
+        driver += "    pop r2\n    call strcmp\n    call print_i32\n    loadv r7, ' '\n    out r7\n";
+    }
+
+    assert_eq!(run(&driver, b"").1, "0 -1 1 -1 1 0 ");
+}
+
+#[test]
+fn comparisons() {
+    let pairs: [(i32, i32); 6] = [(1, 2), (2, 1), (3, 3), (-1, 1), (i32::MIN, i32::MAX), (-5, -6)];
+    let mut driver = String::new();
+
+    for routine in ["ult", "slt", "sle", "sgt", "sge"] {
+        for (left, right) in pairs {
+            driver += &format!(
+                "    li32 r1, {}\n    li32 r2, {}\n    call {}\n    loadv r7, '0'\n    add r1, r1, r7\n    out r1\n",
+                left as u32, right as u32, routine
+            );
+        }
+
+        driver += "    loadv r7, ' '\n    out r7\n";
+    }
+
+    assert_eq!(run(&driver, b"").1, "100000 100110 101110 010001 011001 ");
+}
+
+#[test]
+fn heap() {
+    //r2 keeps the block allocated first, to be freed then reused
+    let driver = "
+    loadv r1, 20
+    call heap_init
+    loadv r1, 3
+    call heap_alloc
+    mov r2, r1
+    call print_i32
+    loadv r7, ' '
+    out r7
+    loadv r1, 5
+    call heap_alloc
+    call print_i32
+    loadv r7, ' '
+    out r7
+    mov r1, r2
+    call heap_free
+    loadv r1, 3
+    call heap_alloc
+    call print_i32
+    loadv r7, ' '
+    out r7
+    loadv r1, 2
+    call heap_alloc
+    call print_i32
+    loadv r7, ' '
+    out r7
+    loadv r1, 10
+    call heap_alloc
+    call print_i32
+    loadv r7, ' '
+    out r7
+    ";
+
+    assert_eq!(run(driver, b"").1, "4 8 4 14 -1 ");
+}
+
+#[test]
+fn registers_are_preserved() {
+    let mut driver = "    loadv r1, 4\n    map r1, r1\n    push r1\n".to_string();
+
+    for routine in ["print_u32", "print_i32", "print_hex", "ult", "slt", "sle", "sgt", "sge", "strcmp", "memset", "memcpy"] {
+        driver += &format!("    pop r1\n    push r1\n    mov r2, r1\n    loadv r3, 3\n    loadv r4, 44\n    call {}\n", routine);
+    }
+
+    driver += "    pop r1\n";
+
+    let (rum, _) = run(&driver, b"");
+    let registers = rum.registers();
+
+    assert_eq!((registers[2], registers[3], registers[4], registers[5]), (registers[1], 3, 44, 0));
+}
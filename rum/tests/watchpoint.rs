@@ -0,0 +1,89 @@
+mod common;
+
+use rum::checkpoint::CheckpointConfig;
+use rum::debugger::{Debugger, Stop};
+use rum::rum::Rum;
+use rum::um_io::BufferIo;
+use rum::watchpoint::{Access, WatchEvent, WatchHit, WatchTarget, Watchpoint};
+use common::{loadv, op};
+
+//Writes and reads a word of segment 1, unmaps it and writes the same word of
+//the next segment mapped at 1
+fn program() -> Vec<u32> {
+    vec![
+        loadv(1, 4),
+        op(8, 0, 2, 1),
+        loadv(3, 2),
+        loadv(4, 77),
+        op(2, 2, 3, 4),
+        op(1, 5, 2, 3),
+        op(9, 0, 0, 2),
+        op(8, 0, 2, 1),
+        op(2, 2, 3, 4),
+        op(7, 0, 0, 0),
+    ]
+}
+
+fn debugger(watchpoint: Watchpoint) -> Debugger {
+    let mut debugger = Debugger::new(Rum::new(&program()), Box::new(BufferIo::new(&[])), CheckpointConfig::default());
+
+    assert_eq!(debugger.add_watchpoint(watchpoint), 0);
+
+    debugger
+}
+
+//Continues until the next watchpoint, returning its pc, allocation and event
+fn next(debugger: &mut Debugger) -> Option<(usize, Option<u64>, WatchEvent)> {
+    match debugger.continue_execution() {
+        Stop::Watchpoint(WatchHit { pc, allocation, event, .. }) => Some((pc, allocation, event)),
+        Stop::Halted => None,
+        stop => panic!("stopped with {:?}", stop),
+    }
+}
+
+#[test]
+fn triggers_on_segment_stores() {
+    let watchpoint = Watchpoint::Words { target: WatchTarget::Segment(1), start: 0, end: 4, access: Access::Write };
+    let mut debugger = debugger(watchpoint);
+
+    assert_eq!(next(&mut debugger), Some((4, Some(1), WatchEvent::Write { id: 1, offset: 2, old: 0, new: 77 })));
+    assert_eq!(debugger.machine().pc(), 5);
+    assert_eq!(next(&mut debugger), Some((8, Some(2), WatchEvent::Write { id: 1, offset: 2, old: 0, new: 77 })));
+    assert_eq!(next(&mut debugger), None);
+}
+
+#[test]
+fn follows_an_allocation() {
+    let watchpoint = Watchpoint::Words { target: WatchTarget::Allocation(1), start: 2, end: 3, access: Access::ReadWrite };
+    let mut debugger = debugger(watchpoint);
+
+    assert_eq!(next(&mut debugger), Some((4, Some(1), WatchEvent::Write { id: 1, offset: 2, old: 0, new: 77 })));
+    assert_eq!(next(&mut debugger), Some((5, Some(1), WatchEvent::Read { id: 1, offset: 2, value: 77 })));
+    assert_eq!(next(&mut debugger), None);
+}
+
+#[test]
+fn triggers_on_mapping() {
+    let mut debugger = debugger(Watchpoint::Mapping { target: WatchTarget::Segment(1) });
+
+    assert_eq!(next(&mut debugger), Some((1, Some(1), WatchEvent::Mapped { id: 1, size: 4 })));
+    assert_eq!(next(&mut debugger), Some((6, Some(1), WatchEvent::Unmapped { id: 1 })));
+    assert_eq!(next(&mut debugger), Some((7, Some(2), WatchEvent::Mapped { id: 1, size: 4 })));
+    assert_eq!(next(&mut debugger), None);
+}
+
+#[test]
+fn reverse_continue_stops_before_the_access() {
+    let watchpoint = Watchpoint::Words { target: WatchTarget::Segment(1), start: 2, end: 3, access: Access::Write };
+    let mut debugger = debugger(watchpoint);
+
+    while next(&mut debugger).is_some() {}
+
+    match debugger.reverse_continue() {
+        Stop::Watchpoint(hit) => assert_eq!(hit.pc, 8),
+        stop => panic!("stopped with {:?}", stop),
+    }
+
+    assert_eq!(debugger.machine().pc(), 8);
+    assert_eq!(debugger.machine().segments().get_segment_value(1, 2), Some(0));
+}